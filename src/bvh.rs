@@ -0,0 +1,134 @@
+// Bounding volume hierarchy over triangles for closest point queries. Nodes split their triangles at the median of
+// the centroids along the longest axis of their bounds, queries visit the nearer child first and skip the nodes
+// farther than the closest point found so far.
+use nalgebra::Vector3;
+
+// Triangles in a leaf
+const LEAF_SIZE: usize = 4;
+
+/// Triangles in world units, reordered along the tree
+pub struct Bvh {
+    triangles: Vec<[Vector3<f64>; 3]>,
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    min: Vector3<f64>,
+    max: Vector3<f64>,
+    // Children for an inner node, range of its triangles for a leaf
+    children: Option<(usize, usize)>,
+    start: usize,
+    end: usize,
+}
+
+impl Bvh {
+    pub fn new(mut triangles:Vec<[Vector3<f64>; 3]>) -> Self {
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            build(&mut triangles, 0, len, &mut nodes);
+        }
+        Bvh { triangles, nodes }
+    }
+
+    /// Closest point of the triangles to p with its squared distance, None without triangles
+    pub fn closest(&self, p:&Vector3<f64>) -> Option<(Vector3<f64>, f64)> {
+        let mut best: Option<(Vector3<f64>, f64)> = None;
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let Some(node) = self.nodes.get(n) else { continue };
+            if best.is_some_and(|(_, d)| box_distance(node, p) >= d) {
+                continue;
+            }
+            match node.children {
+                Some((left, right)) => {
+                    // The nearer child is pushed last to be visited first
+                    let (near, far) = match box_distance(&self.nodes[left], p) <= box_distance(&self.nodes[right], p) {
+                        true => (left, right),
+                        false => (right, left),
+                    };
+                    stack.push(far);
+                    stack.push(near);
+                }
+                None => {
+                    for t in &self.triangles[node.start..node.end] {
+                        let q = closest_on_triangle(p, t);
+                        let d = (q - p).norm_squared();
+                        if best.is_none_or(|(_, best)| d < best) {
+                            best = Some((q, d));
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+// Node of the triangles start..end, which it reorders, and the nodes under it. Returns the index of the node.
+fn build(triangles:&mut [[Vector3<f64>; 3]], start:usize, end:usize, nodes:&mut Vec<Node>) -> usize {
+    let (mut min, mut max) = (Vector3::repeat(f64::MAX), Vector3::repeat(f64::MIN));
+    for t in &triangles[start..end] {
+        for p in t {
+            min = min.inf(p);
+            max = max.sup(p);
+        }
+    }
+    let n = nodes.len();
+    nodes.push(Node { min, max, children: None, start, end });
+    if end - start <= LEAF_SIZE {
+        return n;
+    }
+    let axis = (max - min).imax();
+    let middle = (start + end) / 2;
+    let centroid = |t:&[Vector3<f64>; 3]| t[0][axis] + t[1][axis] + t[2][axis];
+    triangles[start..end].select_nth_unstable_by(middle - start, |a, b| centroid(a).total_cmp(&centroid(b)));
+    let left = build(triangles, start, middle, nodes);
+    let right = build(triangles, middle, end, nodes);
+    nodes[n].children = Some((left, right));
+    n
+}
+
+fn box_distance(node:&Node, p:&Vector3<f64>) -> f64 {
+    (node.min - p).sup(&(p - node.max)).sup(&Vector3::zeros()).norm_squared()
+}
+
+// Closest point of the triangle to p, through the Voronoi regions of its vertices and edges (Ericson)
+fn closest_on_triangle(p:&Vector3<f64>, [a, b, c]:&[Vector3<f64>; 3]) -> Vector3<f64> {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    // Degenerate triangles have no inside and their edge regions divide by zero, their closest point is on an edge
+    if ab.cross(&ac).norm_squared() == 0.0 {
+        return [(a, b), (b, c), (c, a)].into_iter().map(|(u, v)| {
+            let t = if (v - u).norm_squared() > 0.0 { ((p - u).dot(&(v - u)) / (v - u).norm_squared()).clamp(0.0, 1.0) } else { 0.0 };
+            u + (v - u) * t
+        }).min_by(|x, y| (x - p).norm_squared().total_cmp(&(y - p).norm_squared())).unwrap();
+    }
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denominator = va + vb + vc;
+    a + ab * (vb / denominator) + ac * (vc / denominator)
+}
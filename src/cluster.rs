@@ -0,0 +1,133 @@
+// Vertex clustering on a uniform grid (Rossignac–Borrel), with the representative of each cell placed at the
+// minimum of the summed quadrics of its triangles (Lindstrom). Every pass is linear in the size of the mesh.
+use log::info;
+use nalgebra::{Matrix3, Vector3};
+use std::collections::{HashMap, HashSet};
+
+use crate::metric::get_k_matrix;
+
+// Singular values of a cell quadric below this share of the largest one are too flat to place the vertex along,
+// which keeps the solve away from ill-conditioned quadrics
+const MIN_SINGULAR: f64 = 1e-3;
+
+// Merge the vertices of every cell of the given size into one, moved to where it best fits the triangles of the
+// cell. Locked vertices stay where they are in a cell of their own. Triangles left with fewer than three cells are dropped. The largest quadric error of a cell is returned,
+// as a distance like the error of the edge collapses, with the vertices merged into another one and the sum of their errors.
+pub fn cluster(index_list:&[u32], position_list:&mut HashMap<u32, Vector3<f32>>, cell:f32,
+                locked:&HashSet<u32>) -> (Vec<u32>, f32, usize, f64) {
+    let (min, max) = bounds(index_list, position_list);
+    // Quadrics are built around the center of the bounds, where the coordinates keep their precision
+    let center = (min + max) / 2.0;
+    let local = |i:&u32| position_list[i] - center;
+    // Locked vertices get a key out of the grid, made of their index
+    let key = |i:&u32, p:&Vector3<f32>| {
+        let c = (p - min) / cell;
+        match locked.contains(i) {
+            true => [i64::MIN, i64::MIN, *i as i64],
+            false => [c.x.floor() as i64, c.y.floor() as i64, c.z.floor() as i64],
+        }
+    };
+    // Quadric and vertices of every cell, the triangles add their quadric to the cells of their corners
+    let mut cells: HashMap<[i64; 3], (Vec<f64>, Vec<u32>)> = HashMap::new();
+    let mut vertices = index_list.to_vec();
+    vertices.sort();
+    vertices.dedup();
+    for i in &vertices {
+        cells.entry(key(i, &position_list[i])).or_insert_with(|| (vec![0.0; 10], Vec::new())).1.push(*i);
+    }
+    for t in index_list.chunks_exact(3) {
+        let k = get_k_matrix(&local(&t[0]), &local(&t[1]), &local(&t[2]));
+        let corners: HashSet<[i64; 3]> = t.iter().map(|i| key(i, &position_list[i])).collect();
+        for c in corners {
+            cells.get_mut(&c).unwrap().0.iter_mut().zip(&k).for_each(|(q, k)| *q += k);
+        }
+    }
+    // The vertex nearest to the placement stands for the cell, so that it brings its own attributes along
+    let mut representative: HashMap<[i64; 3], u32> = HashMap::new();
+    let (mut error, mut merged, mut error_sum) = (0.0f32, 0, 0.0f64);
+    let mut placed = Vec::new();
+    for (c, (q, members)) in &cells {
+        if c[0] == i64::MIN {
+            representative.insert(*c, members[0]);
+            continue;
+        }
+        let mean = (members.iter().map(local).sum::<Vector3<f32>>() / members.len() as f32).cast::<f64>();
+        let low = (min - center + Vector3::new(c[0] as f32, c[1] as f32, c[2] as f32) * cell).cast::<f64>();
+        let p = minimum(q, &mean).filter(|p| (0..3).all(|a| p[a] >= low[a] && p[a] <= low[a] + cell as f64))
+            .unwrap_or(mean);
+        let nearest = *members.iter().min_by(|a, b| {
+            (local(a).cast::<f64>() - p).norm_squared().total_cmp(&(local(b).cast::<f64>() - p).norm_squared())
+        }).unwrap();
+        let e = cost(q, &p).max(0.0).sqrt();
+        error = error.max(e as f32);
+        (merged, error_sum) = (merged + members.len() - 1, error_sum + e * (members.len() - 1) as f64);
+        representative.insert(*c, nearest);
+        placed.push((nearest, p.cast::<f32>() + center));
+    }
+    position_list.extend(placed);
+    let cell_of: HashMap<u32, [i64; 3]> = cells.iter()
+        .flat_map(|(c, (_, members))| members.iter().map(move |i| (*i, *c))).collect();
+    let mut seen = HashSet::new();
+    let mut new_index_list = Vec::with_capacity(index_list.len());
+    for t in index_list.chunks_exact(3) {
+        let r = [representative[&cell_of[&t[0]]], representative[&cell_of[&t[1]]], representative[&cell_of[&t[2]]]];
+        if r[0] == r[1] || r[1] == r[2] || r[2] == r[0] {
+            continue;
+        }
+        let mut sorted = r;
+        sorted.sort();
+        if seen.insert(sorted) {
+            new_index_list.extend_from_slice(&r);
+        }
+    }
+    info!(target: "decimate", "{} vertices clustered into {} cells of {}", cell_of.len(), cells.len(), cell);
+    (new_index_list, error, merged, error_sum)
+}
+
+// Cell size that leaves about count triangles, found by bisection
+pub fn cell_for_count(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, count:usize,
+                        locked:&HashSet<u32>) -> f32 {
+    let (min, max) = bounds(index_list, position_list);
+    let (mut low, mut high) = (0.0f32, (max - min).max().max(f32::MIN_POSITIVE) * 2.0);
+    for _ in 0..24 {
+        let cell = (low + high) / 2.0;
+        if cell <= low || cell >= high {
+            break;
+        }
+        let mut positions = position_list.clone();
+        if cluster(index_list, &mut positions, cell, locked).0.len() / 3 > count {
+            low = cell;
+        } else {
+            high = cell;
+        }
+    }
+    high
+}
+
+// Point of least error of a quadric nearest to reference, through the pseudo-inverse of its singular values
+// above MIN_SINGULAR. None when the quadric is empty.
+fn minimum(q:&[f64], reference:&Vector3<f64>) -> Option<Vector3<f64>> {
+    let a = Matrix3::new(q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]);
+    let b = Vector3::new(q[3], q[6], q[8]);
+    let svd = a.svd(true, true);
+    let largest = svd.singular_values.max();
+    if largest <= 0.0 || !largest.is_finite() {
+        return None;
+    }
+    svd.solve(&(-b - a * reference), largest * MIN_SINGULAR).ok().map(|p| reference + p)
+}
+
+fn cost(q:&[f64], p:&Vector3<f64>) -> f64 {
+    let a = Matrix3::new(q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]);
+    p.dot(&(a * p)) + 2.0 * p.dot(&Vector3::new(q[3], q[6], q[8])) + q[9]
+}
+
+fn bounds(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>) -> (Vector3<f32>, Vector3<f32>) {
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for i in index_list {
+        min = min.inf(&position_list[i]);
+        max = max.sup(&position_list[i]);
+    }
+    (min, max)
+}
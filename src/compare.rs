@@ -0,0 +1,221 @@
+// Distance between an original and a decimated mesh, sampled over both surfaces (Metro). Points spread over the
+// triangles of one surface by area, and its vertices, find their closest point on the other one through a BVH:
+// the largest distance is the one-sided Hausdorff distance, the area samples give the mean and RMS error.
+use clap::Parser;
+use log::info;
+use nalgebra::{Matrix4, Vector3, Vector4};
+use rayon::prelude::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::bvh::Bvh;
+use crate::{append_view, mesh_instances, new_buffer_view, read_glb, triangulate, unpack_gltf, write_glb};
+
+/// Measure how far a decimated glTF binary file is from its original
+#[derive(Parser, Debug, Clone)]
+#[command(name = "compare")]
+pub struct CompareArgs {
+    /// Path to the original .glb file
+    original: String,
+    /// Path to the decimated .glb file
+    decimated: String,
+    /// Points sampled over the area of each surface
+    #[arg(long, default_value_t = 100000)]
+    samples: usize,
+    /// Write the decimated file with its distance to the original as a COLOR_0 heatmap, blue to red, to this file
+    #[arg(long)]
+    heatmap: Option<String>,
+    /// Worker threads, one per core with 0
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+    /// Log what is done with -v, the details with -vv and the data itself with -vvv
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+}
+
+/// Distances from the samples of one surface to the other, in world units
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Distances {
+    pub hausdorff: f64,
+    pub mean: f64,
+    pub rms: f64,
+}
+
+/// Both one-sided distances between two surfaces
+#[derive(Debug, Clone, Copy)]
+pub struct Comparison {
+    pub forward: Distances,
+    pub backward: Distances,
+    pub symmetric: Distances,
+}
+
+// A triangle primitive in the local space of its mesh, with the world transforms of the mesh
+struct Primitive {
+    at: (usize, usize),
+    index_list: Vec<u32>,
+    position_list: HashMap<u32, Vector3<f32>>,
+    instances: Vec<Matrix4<f32>>,
+}
+
+impl Primitive {
+    fn triangles(&self) -> impl Iterator<Item = [Vector3<f64>; 3]> + '_ {
+        self.instances.iter().flat_map(move |m| self.index_list.chunks_exact(3).map(move |t| {
+            [world(m, &self.position_list[&t[0]]), world(m, &self.position_list[&t[1]]), world(m, &self.position_list[&t[2]])]
+        }))
+    }
+}
+
+fn world(m:&Matrix4<f32>, p:&Vector3<f32>) -> Vector3<f64> {
+    (m * Vector4::new(p.x, p.y, p.z, 1.0)).xyz().cast::<f64>()
+}
+
+/// Compare the original and decimated triangles, sampling about samples points over each of them
+pub fn compare(original:&[[Vector3<f64>; 3]], decimated:&[[Vector3<f64>; 3]], samples:usize) -> Comparison {
+    let forward = distances(original, &Bvh::new(decimated.to_vec()), samples);
+    let backward = distances(decimated, &Bvh::new(original.to_vec()), samples);
+    // The mean and RMS of both sides together, each side weighted by its samples like Metro
+    let count = |triangles:&[[Vector3<f64>; 3]]| spread(triangles, samples).len().max(1) as f64;
+    let (n, m) = (count(original), count(decimated));
+    let symmetric = Distances {
+        hausdorff: forward.hausdorff.max(backward.hausdorff),
+        mean: (forward.mean * n + backward.mean * m) / (n + m),
+        rms: ((forward.rms.powi(2) * n + backward.rms.powi(2) * m) / (n + m)).sqrt(),
+    };
+    Comparison { forward, backward, symmetric }
+}
+
+// Distances from the samples of the triangles to the surface in the BVH
+fn distances(triangles:&[[Vector3<f64>; 3]], other:&Bvh, samples:usize) -> Distances {
+    let distance = |p:&Vector3<f64>| other.closest(p).map_or(f64::INFINITY, |(_, d)| d.sqrt());
+    let points = spread(triangles, samples);
+    let area: Vec<f64> = points.par_iter().map(distance).collect();
+    let vertices: Vec<f64> = triangles.par_iter().flat_map_iter(|t| t.iter().map(distance)).collect();
+    // Summed in order, so that the result does not depend on the threads
+    let n = area.len().max(1) as f64;
+    Distances {
+        hausdorff: area.iter().chain(&vertices).fold(0.0, |max, d| d.max(max)),
+        mean: area.iter().sum::<f64>() / n,
+        rms: (area.iter().map(|d| d * d).sum::<f64>() / n).sqrt(),
+    }
+}
+
+// About samples points spread over the triangles by area, stratified along the total area and placed in each
+// triangle by a fixed sequence, so that the same meshes always give the same points
+fn spread(triangles:&[[Vector3<f64>; 3]], samples:usize) -> Vec<Vector3<f64>> {
+    let area: Vec<f64> = triangles.iter().map(|[a, b, c]| (b - a).cross(&(c - a)).norm() / 2.0).collect();
+    let total: f64 = area.iter().sum();
+    if total <= 0.0 || samples == 0 {
+        return Vec::new();
+    }
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 11) as f64 / (1u64 << 53) as f64
+    };
+    let mut points = Vec::with_capacity(samples);
+    let mut reached = 0.0;
+    let mut next = total / samples as f64 / 2.0;
+    for (t, a) in triangles.iter().zip(&area) {
+        reached += a;
+        while next < reached {
+            let (mut u, mut v) = (random(), random());
+            if u + v > 1.0 {
+                (u, v) = (1.0 - u, 1.0 - v);
+            }
+            points.push(t[0] + (t[1] - t[0]) * u + (t[2] - t[0]) * v);
+            next += total / samples as f64;
+        }
+    }
+    points
+}
+
+// Triangle primitives of a file in their mesh space, strips and fans turned into lists
+fn primitives(json:&mut Value, binary_chunk:&mut Vec<u8>) -> io::Result<Vec<Primitive>> {
+    let mut primitives = Vec::new();
+    let mesh_num = json["meshes"].as_array().map_or(0, |meshes| meshes.len());
+    for mesh in 0..mesh_num {
+        let primitive_num = json["meshes"][mesh]["primitives"].as_array().map_or(0, |primitives| primitives.len());
+        for primitive in 0..primitive_num {
+            triangulate(json, binary_chunk, mesh, primitive)?;
+            if json["meshes"][mesh]["primitives"][primitive]["mode"].as_u64().unwrap_or(4) != 4 {
+                continue;
+            }
+            let (attributes, _) = unpack_gltf(json, binary_chunk, mesh, primitive);
+            let (index_list, position_list) = (attributes.index_list, attributes.position_list);
+            // A mesh outside of the scenes is taken as it is
+            let mut instances = mesh_instances(json, mesh as u64);
+            if instances.is_empty() {
+                instances.push(Matrix4::identity());
+            }
+            primitives.push(Primitive { at: (mesh, primitive), index_list, position_list, instances });
+        }
+    }
+    Ok(primitives)
+}
+
+// Colour of a distance from 0 to the largest one: blue, green then red
+fn heat(t:f64) -> [f32; 3] {
+    let t = t.clamp(0.0, 1.0) as f32;
+    match t < 0.5 {
+        true => [0.0, t * 2.0, 1.0 - t * 2.0],
+        false => [t * 2.0 - 1.0, 2.0 - t * 2.0, 0.0],
+    }
+}
+
+// Add the distance of every vertex of the decimated primitives to the original surface as their COLOR_0, the
+// farthest instance of a vertex setting its colour
+fn write_heatmap(path:&str, mut json:Value, mut binary_chunk:Vec<u8>, decimated:&[Primitive], original:&Bvh)
+                 -> io::Result<()> {
+    let errors: Vec<HashMap<u32, f64>> = decimated.iter().map(|primitive| {
+        primitive.position_list.par_iter().map(|(i, p)| {
+            let error = primitive.instances.iter()
+                .map(|m| original.closest(&world(m, p)).map_or(0.0, |(_, d)| d.sqrt())).fold(0.0, f64::max);
+            (*i, error)
+        }).collect()
+    }).collect();
+    let largest = errors.iter().flat_map(|e| e.values()).fold(0.0, |max, e| e.max(max));
+    for (primitive, errors) in decimated.iter().zip(&errors) {
+        let (mesh, p) = primitive.at;
+        let position = json["meshes"][mesh]["primitives"][p]["attributes"]["POSITION"].as_u64().unwrap() as usize;
+        let count = json["accessors"][position]["count"].as_u64().unwrap() as u32;
+        let data: Vec<u8> = (0..count).flat_map(|i| {
+            let t = if largest > 0.0 { errors.get(&i).copied().unwrap_or(0.0) / largest } else { 0.0 };
+            heat(t).into_iter().flat_map(|c| c.to_le_bytes())
+        }).collect();
+        let view = new_buffer_view(&mut json);
+        append_view(&mut json, &mut binary_chunk, view, data, None);
+        json["bufferViews"][view as usize]["target"] = json!(34962);
+        let accessors = json["accessors"].as_array_mut().unwrap();
+        accessors.push(json!({"bufferView": view, "componentType": 5126, "count": count, "type": "VEC3"}));
+        json["meshes"][mesh]["primitives"][p]["attributes"]["COLOR_0"] = json!(accessors.len() - 1);
+    }
+    write_glb(json, &binary_chunk, false, &HashMap::new(), path)?;
+    info!(target: "write", "Heatmap written to {}, red at {}", path, largest);
+    Ok(())
+}
+
+// The compare command: distances printed in world units, and the heatmap
+pub fn run(args:&CompareArgs) -> io::Result<()> {
+    let (mut original_json, mut original_binary) = read_glb(Path::new(&args.original))?;
+    let (mut json, mut binary_chunk) = read_glb(Path::new(&args.decimated))?;
+    let original = primitives(&mut original_json, &mut original_binary)?;
+    let decimated = primitives(&mut json, &mut binary_chunk)?;
+    let original_triangles: Vec<[Vector3<f64>; 3]> = original.iter().flat_map(|p| p.triangles()).collect();
+    let decimated_triangles: Vec<[Vector3<f64>; 3]> = decimated.iter().flat_map(|p| p.triangles()).collect();
+    info!(target: "decimate", "{} original and {} decimated triangles in the scenes", original_triangles.len(),
+            decimated_triangles.len());
+    let comparison = compare(&original_triangles, &decimated_triangles, args.samples);
+    println!("{:<21}  {:>12}  {:>12}  {:>12}", "", "Hausdorff", "Mean", "RMS");
+    for (name, d) in [("Original to decimated", comparison.forward), ("Decimated to original", comparison.backward),
+                      ("Symmetric", comparison.symmetric)] {
+        println!("{:<21}  {:>12.6e}  {:>12.6e}  {:>12.6e}", name, d.hausdorff, d.mean, d.rms);
+    }
+    if let Some(path) = &args.heatmap {
+        write_heatmap(path, json, binary_chunk, &decimated, &Bvh::new(original_triangles))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,414 @@
+// Attribute decoders: traversal order, portable integer values and their transforms
+use super::buffer::{from_symbol, DecoderBuffer};
+use super::corner_table::{CornerTable, INVALID};
+use super::prediction::{compute_original_values, decode_prediction_data, is_mesh_method, MeshData,
+                        Octahedron, Positions, Transform, PREDICTION_NONE};
+use super::rans::decode_symbols;
+use super::Connectivity;
+
+pub const POSITION: u8 = 0;
+pub const NORMAL: u8 = 1;
+pub const TEX_COORD: u8 = 3;
+const NAMED_ATTRIBUTES_COUNT: u8 = 5;
+
+pub const DECODER_GENERIC: u8 = 0;
+pub const DECODER_INTEGER: u8 = 1;
+pub const DECODER_QUANTIZATION: u8 = 2;
+pub const DECODER_NORMALS: u8 = 3;
+
+const MESH_VERTEX_ATTRIBUTE: u8 = 0;
+const MESH_CORNER_ATTRIBUTE: u8 = 1;
+const TRAVERSAL_DEPTH_FIRST: u8 = 0;
+const TRAVERSAL_PREDICTION_DEGREE: u8 = 1;
+
+pub const DT_FLOAT32: u8 = 9;
+
+pub struct Attribute {
+    pub attribute_type: u8,
+    pub data_type: u8,
+    pub num_components: usize,
+    pub unique_id: u32,
+    decoder_type: u8,
+    // Decoded values, one entry per attribute value
+    pub values: Vec<f64>,
+    // Integer values before dequantization, used to predict other attributes
+    portable: Vec<i32>,
+    pub point_to_value: Vec<u32>,
+}
+
+// Order in which a decoder visits the attribute values
+struct Sequence {
+    point_ids: Vec<u32>,
+    data_to_corner: Vec<u32>,
+    vertex_to_data: Vec<i32>,
+}
+
+pub fn data_type_size(data_type:u8) -> usize {
+    match data_type {
+        1 | 2 | 11 => 1,
+        3 | 4 => 2,
+        7 | 8 | 10 => 8,
+        _ => 4,
+    }
+}
+
+pub fn decode(buffer:&mut DecoderBuffer, connectivity:&mut Connectivity) -> Result<Vec<Attribute>, String> {
+    let num_decoders = buffer.u8()? as usize;
+
+    // Edgebreaker decoders say which connectivity they traverse and how
+    let mut headers = Vec::with_capacity(num_decoders);
+    if connectivity.table.is_some() {
+        for i in 0..num_decoders {
+            let att_data_id = buffer.i8()?;
+            let decoder_type = buffer.u8()?;
+            let traversal = buffer.u8()?;
+            if att_data_id >= 0 {
+                let data = connectivity.attribute_data.get_mut(att_data_id as usize)
+                    .ok_or("Invalid Draco attribute connectivity id")?;
+                data.decoder_id = i as i32;
+                if decoder_type == MESH_VERTEX_ATTRIBUTE {
+                    data.connectivity_used = false;
+                }
+            }
+            match (decoder_type, traversal) {
+                (MESH_VERTEX_ATTRIBUTE, TRAVERSAL_DEPTH_FIRST | TRAVERSAL_PREDICTION_DEGREE) => {}
+                (MESH_CORNER_ATTRIBUTE, TRAVERSAL_DEPTH_FIRST) if att_data_id >= 0 => {}
+                _ => return Err(String::from("Unsupported Draco attribute traversal")),
+            }
+            headers.push((att_data_id, decoder_type, traversal));
+        }
+    }
+
+    let mut decoders: Vec<Vec<Attribute>> = Vec::with_capacity(num_decoders);
+    for _ in 0..num_decoders {
+        let num_attributes = buffer.varint32()? as usize;
+        if num_attributes == 0 {
+            return Err(String::from("Draco attribute decoder without attributes"));
+        }
+        let mut attributes = Vec::with_capacity(num_attributes);
+        for _ in 0..num_attributes {
+            let attribute_type = buffer.u8()?;
+            let data_type = buffer.u8()?;
+            let num_components = buffer.u8()? as usize;
+            // The normalized flag is carried by the glTF accessor
+            buffer.u8()?;
+            if attribute_type >= NAMED_ATTRIBUTES_COUNT || data_type == 0 || data_type > 11 || num_components == 0 {
+                return Err(String::from("Invalid Draco attribute descriptor"));
+            }
+            let unique_id = buffer.varint32()?;
+            attributes.push(Attribute {
+                attribute_type,
+                data_type,
+                num_components,
+                unique_id,
+                decoder_type: 0,
+                values: Vec::new(),
+                portable: Vec::new(),
+                point_to_value: Vec::new(),
+            });
+        }
+        for attribute in attributes.iter_mut() {
+            attribute.decoder_type = buffer.u8()?;
+            match attribute.decoder_type {
+                DECODER_GENERIC | DECODER_INTEGER => {}
+                DECODER_QUANTIZATION if attribute.data_type == DT_FLOAT32 => {}
+                DECODER_NORMALS if attribute.data_type == DT_FLOAT32 && attribute.num_components == 3 => {}
+                _ => return Err(format!("Unsupported Draco attribute decoder {}", attribute.decoder_type)),
+            }
+        }
+        decoders.push(attributes);
+    }
+
+    let mut decoded: Vec<Attribute> = Vec::new();
+    for (i, mut attributes) in decoders.into_iter().enumerate() {
+        let table = match (&connectivity.table, headers.get(i)) {
+            (Some(base), Some((att_data_id, decoder_type, _))) => {
+                if *decoder_type == MESH_CORNER_ATTRIBUTE {
+                    Some(&connectivity.attribute_data[*att_data_id as usize].table)
+                } else {
+                    Some(base)
+                }
+            }
+            _ => None,
+        };
+        let sequence = match table {
+            Some(table) => traverse(table, &connectivity.faces, headers[i].2)?,
+            None => Sequence {
+                point_ids: (0..connectivity.num_points as u32).collect(),
+                data_to_corner: Vec::new(),
+                vertex_to_data: Vec::new(),
+            },
+        };
+
+        // Every point gets the value of the vertex of one of its corners
+        let point_to_value: Vec<u32> = match table {
+            Some(table) => {
+                let mut map = vec![0u32; connectivity.num_points];
+                for (f, face) in connectivity.faces.iter().enumerate() {
+                    for (k, point) in face.iter().enumerate() {
+                        let vertex = table.vertex((3 * f + k) as u32);
+                        let value = if vertex == INVALID { -1 } else { sequence.vertex_to_data[vertex as usize] };
+                        if value < 0 || value as usize >= connectivity.num_points || *point as usize >= map.len() {
+                            return Err(String::from("Invalid Draco attribute mapping"));
+                        }
+                        map[*point as usize] = value as u32;
+                    }
+                }
+                map
+            }
+            None => (0..connectivity.num_points as u32).collect(),
+        };
+
+        let mesh = table.map(|table| MeshData {
+            table,
+            data_to_corner: &sequence.data_to_corner,
+            vertex_to_data: &sequence.vertex_to_data,
+        });
+        for a in 0..attributes.len() {
+            let (before, rest) = attributes.split_at_mut(a);
+            rest[0].point_to_value = point_to_value.clone();
+            let position = decoded.iter().chain(before.iter()).find(|att| att.attribute_type == POSITION);
+            let positions = position.map(|att| Positions {
+                values: &att.portable,
+                point_to_value: &att.point_to_value,
+            });
+            decode_portable(buffer, &mut rest[0], &sequence.point_ids, mesh.as_ref(), positions.as_ref())?;
+        }
+        for attribute in attributes.iter_mut() {
+            decode_transform(buffer, attribute)?;
+        }
+        decoded.extend(attributes);
+    }
+    Ok(decoded)
+}
+
+fn decode_portable(buffer:&mut DecoderBuffer, attribute:&mut Attribute, point_ids:&[u32], mesh:Option<&MeshData>,
+                    positions:Option<&Positions>) -> Result<(), String> {
+    let entries = point_ids.len();
+    if attribute.decoder_type == DECODER_GENERIC {
+        let size = data_type_size(attribute.data_type);
+        let mut values = Vec::with_capacity(entries * attribute.num_components);
+        for _ in 0..entries * attribute.num_components {
+            values.push(raw_value(buffer.bytes(size)?, attribute.data_type));
+        }
+        attribute.portable = values.iter().map(|x| *x as i32).collect();
+        attribute.values = values;
+        return Ok(());
+    }
+
+    let n = if attribute.decoder_type == DECODER_NORMALS { 2 } else { attribute.num_components };
+    let method = buffer.i8()?;
+    let transform_type = if method != PREDICTION_NONE { buffer.i8()? } else { -1 };
+    let num_values = entries * n;
+    let symbols = if buffer.u8()? > 0 {
+        decode_symbols(buffer, num_values, n)?
+    } else {
+        let num_bytes = buffer.u8()? as usize;
+        if num_bytes == 0 || num_bytes > 4 {
+            return Err(format!("Invalid Draco raw value size {}", num_bytes));
+        }
+        let mut symbols = Vec::with_capacity(num_values);
+        for _ in 0..num_values {
+            symbols.push(buffer.uint(num_bytes)?);
+        }
+        symbols
+    };
+    let positive = method != PREDICTION_NONE && Transform::corrections_positive(transform_type);
+    let corrections: Vec<i32> = if positive {
+        symbols.iter().map(|s| *s as i32).collect()
+    } else {
+        symbols.iter().map(|s| from_symbol(*s)).collect()
+    };
+    attribute.portable = if method == PREDICTION_NONE {
+        corrections
+    } else {
+        let has_mesh = mesh.is_some() && is_mesh_method(method);
+        let num_corners = mesh.map_or(0, |mesh| mesh.table.num_corners());
+        let (mut scheme, transform, mut flips) = decode_prediction_data(method, transform_type, has_mesh, num_corners, buffer)?;
+        let mesh = if has_mesh { mesh } else { None };
+        compute_original_values(&mut scheme, &transform, flips.as_mut(), &corrections, n, mesh, point_ids, positions)?
+    };
+    Ok(())
+}
+
+// Data needed to go from portable integers back to the attribute values
+fn decode_transform(buffer:&mut DecoderBuffer, attribute:&mut Attribute) -> Result<(), String> {
+    match attribute.decoder_type {
+        DECODER_INTEGER => {
+            attribute.values = attribute.portable.iter().map(|x| *x as f64).collect();
+        }
+        DECODER_QUANTIZATION => {
+            let n = attribute.num_components;
+            let mut min = Vec::with_capacity(n);
+            for _ in 0..n {
+                min.push(buffer.f32()?);
+            }
+            let range = buffer.f32()?;
+            let bits = buffer.u8()? as u32;
+            if !(1..=30).contains(&bits) {
+                return Err(format!("Invalid Draco quantization bits {}", bits));
+            }
+            let delta = range / ((1u32 << bits) - 1) as f32;
+            attribute.values = attribute.portable.iter().enumerate()
+                .map(|(i, q)| (*q as f32 * delta + min[i % n]) as f64).collect();
+        }
+        DECODER_NORMALS => {
+            let octahedron = Octahedron::new(buffer.u8()? as u32)?;
+            let mut values = Vec::with_capacity(attribute.portable.len() / 2 * 3);
+            for st in attribute.portable.chunks(2) {
+                values.extend(octahedron.unit_vector(st[0], st[1]).iter().map(|x| *x as f64));
+            }
+            attribute.values = values;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn raw_value(bytes:&[u8], data_type:u8) -> f64 {
+    match data_type {
+        1 => bytes[0] as i8 as f64,
+        2 | 11 => bytes[0] as f64,
+        3 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        4 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        5 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        6 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        7 => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        8 => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        10 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        _ => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+    }
+}
+
+// Walks the faces in order and numbers the vertices as they are first reached
+fn traverse(table:&CornerTable, faces:&[[u32; 3]], method:u8) -> Result<Sequence, String> {
+    let mut sequence = Sequence {
+        point_ids: Vec::with_capacity(table.num_vertices()),
+        data_to_corner: Vec::with_capacity(table.num_vertices()),
+        vertex_to_data: vec![-1; table.num_vertices()],
+    };
+    let mut face_visited = vec![false; table.num_faces()];
+    let mut vertex_visited = vec![false; table.num_vertices()];
+    let mut degree = vec![0u32; table.num_vertices()];
+    let visit = |sequence:&mut Sequence, vertex_visited:&mut Vec<bool>, v:u32, c:u32| -> Result<(), String> {
+        if v == INVALID {
+            return Err(String::from("Invalid Draco traversal vertex"));
+        }
+        if !vertex_visited[v as usize] {
+            vertex_visited[v as usize] = true;
+            sequence.point_ids.push(faces[c as usize / 3][c as usize % 3]);
+            sequence.vertex_to_data[v as usize] = sequence.data_to_corner.len() as i32;
+            sequence.data_to_corner.push(c);
+        }
+        Ok(())
+    };
+    let is_face_visited = |face_visited:&Vec<bool>, c:u32| c == INVALID || face_visited[c as usize / 3];
+
+    for f in 0..table.num_faces() as u32 {
+        let start = 3 * f;
+        if face_visited[f as usize] {
+            continue;
+        }
+        visit(&mut sequence, &mut vertex_visited, table.vertex(table.next(start)), table.next(start))?;
+        visit(&mut sequence, &mut vertex_visited, table.vertex(table.previous(start)), table.previous(start))?;
+        if method == TRAVERSAL_PREDICTION_DEGREE {
+            visit(&mut sequence, &mut vertex_visited, table.vertex(start), start)?;
+            let mut stacks: [Vec<u32>; 3] = [vec![start], Vec::new(), Vec::new()];
+            let mut best = 0;
+            loop {
+                let mut next = None;
+                for (i, stack) in stacks.iter_mut().enumerate().skip(best) {
+                    if let Some(c) = stack.pop() {
+                        best = i;
+                        next = Some(c);
+                        break;
+                    }
+                }
+                let mut corner = match next {
+                    Some(c) => c,
+                    None => break,
+                };
+                if face_visited[corner as usize / 3] {
+                    continue;
+                }
+                loop {
+                    face_visited[corner as usize / 3] = true;
+                    visit(&mut sequence, &mut vertex_visited, table.vertex(corner), corner)?;
+                    let right = table.right_corner(corner);
+                    let left = table.left_corner(corner);
+                    let right_visited = is_face_visited(&face_visited, right);
+                    let left_visited = is_face_visited(&face_visited, left);
+                    let mut priority = |c:u32| -> usize {
+                        let tip = table.vertex(c) as usize;
+                        if vertex_visited[tip] {
+                            return 0;
+                        }
+                        degree[tip] += 1;
+                        if degree[tip] > 1 { 1 } else { 2 }
+                    };
+                    if !left_visited {
+                        let p = priority(left);
+                        if right_visited && p <= best {
+                            corner = left;
+                            continue;
+                        }
+                        stacks[p].push(left);
+                        best = best.min(p);
+                    }
+                    if !right_visited {
+                        let p = priority(right);
+                        if p <= best {
+                            corner = right;
+                            continue;
+                        }
+                        stacks[p].push(right);
+                        best = best.min(p);
+                    }
+                    break;
+                }
+            }
+        } else {
+            let mut stack = vec![start];
+            while let Some(&top) = stack.last() {
+                let mut corner = top;
+                if is_face_visited(&face_visited, corner) {
+                    stack.pop();
+                    continue;
+                }
+                loop {
+                    face_visited[corner as usize / 3] = true;
+                    let v = table.vertex(corner);
+                    if v == INVALID {
+                        return Err(String::from("Invalid Draco traversal vertex"));
+                    }
+                    if !vertex_visited[v as usize] {
+                        let on_boundary = table.is_on_boundary(v);
+                        visit(&mut sequence, &mut vertex_visited, v, corner)?;
+                        if !on_boundary {
+                            corner = table.right_corner(corner);
+                            continue;
+                        }
+                    }
+                    let right = table.right_corner(corner);
+                    let left = table.left_corner(corner);
+                    let right_visited = is_face_visited(&face_visited, right);
+                    let left_visited = is_face_visited(&face_visited, left);
+                    if right_visited {
+                        if left_visited {
+                            stack.pop();
+                            break;
+                        }
+                        corner = left;
+                    } else if left_visited {
+                        corner = right;
+                    } else {
+                        *stack.last_mut().unwrap() = left;
+                        stack.push(right);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(sequence)
+}
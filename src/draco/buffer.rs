@@ -0,0 +1,148 @@
+// Little-endian byte reader and writer used by the Draco bitstream
+
+pub struct DecoderBuffer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DecoderBuffer<'a> {
+    pub fn new(data:&'a [u8]) -> Self {
+        DecoderBuffer {
+            data,
+            pos: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    // Everything that has not been read yet
+    pub fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    pub fn skip(&mut self, n:usize) -> Result<(), String> {
+        self.bytes(n).map(|_| ())
+    }
+
+    pub fn bytes(&mut self, n:usize) -> Result<&'a [u8], String> {
+        if self.remaining() < n {
+            return Err(format!("Draco buffer ended at {} while reading {} bytes", self.pos, n));
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn i8(&mut self) -> Result<i8, String> {
+        Ok(self.u8()? as i8)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub fn i32(&mut self) -> Result<i32, String> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    // Unsigned integer of 1 to 4 bytes
+    pub fn uint(&mut self, n:usize) -> Result<u32, String> {
+        let b = self.bytes(n)?;
+        let mut value = 0u32;
+        for (i, byte) in b.iter().enumerate() {
+            value |= (*byte as u32) << (8 * i);
+        }
+        Ok(value)
+    }
+
+    // LEB128 encoded unsigned integer
+    pub fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(String::from("Draco varint is too long"));
+            }
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    pub fn varint32(&mut self) -> Result<u32, String> {
+        let value = self.varint()?;
+        u32::try_from(value).map_err(|_| format!("Draco varint {} does not fit in 32 bits", value))
+    }
+}
+
+#[derive(Default)]
+pub struct EncoderBuffer {
+    pub data: Vec<u8>,
+}
+
+impl EncoderBuffer {
+    pub fn u8(&mut self, value:u8) {
+        self.data.push(value);
+    }
+
+    pub fn u16(&mut self, value:u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value:u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value:f32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn varint(&mut self, mut value:u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.data.push(byte);
+                return;
+            }
+            self.data.push(byte | 0x80);
+        }
+    }
+}
+
+// Signed values are stored as symbols with the sign in the lowest bit
+pub fn to_symbol(value:i32) -> u32 {
+    if value >= 0 {
+        (value as u32) << 1
+    } else {
+        ((-(value + 1)) as u32) << 1 | 1
+    }
+}
+
+pub fn from_symbol(symbol:u32) -> i32 {
+    let value = (symbol >> 1) as i32;
+    if symbol & 1 == 1 {
+        -value - 1
+    } else {
+        value
+    }
+}
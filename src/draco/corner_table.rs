@@ -0,0 +1,172 @@
+// Corner table connectivity, corners 3f, 3f+1, 3f+2 belong to face f
+pub const INVALID: u32 = u32::MAX;
+
+#[derive(Clone, Default)]
+pub struct CornerTable {
+    corner_to_vertex: Vec<u32>,
+    opposite_corners: Vec<u32>,
+    vertex_corners: Vec<u32>,
+}
+
+impl CornerTable {
+    pub fn new(num_faces:usize) -> Self {
+        CornerTable {
+            corner_to_vertex: vec![INVALID; num_faces * 3],
+            opposite_corners: vec![INVALID; num_faces * 3],
+            vertex_corners: Vec::new(),
+        }
+    }
+
+    pub fn num_faces(&self) -> usize {
+        self.corner_to_vertex.len() / 3
+    }
+
+    pub fn num_corners(&self) -> usize {
+        self.corner_to_vertex.len()
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.vertex_corners.len()
+    }
+
+    pub fn next(&self, c:u32) -> u32 {
+        if c == INVALID {
+            INVALID
+        } else if c % 3 == 2 {
+            c - 2
+        } else {
+            c + 1
+        }
+    }
+
+    pub fn previous(&self, c:u32) -> u32 {
+        if c == INVALID {
+            INVALID
+        } else if c.is_multiple_of(3) {
+            c + 2
+        } else {
+            c - 1
+        }
+    }
+
+    pub fn opposite(&self, c:u32) -> u32 {
+        if c == INVALID {
+            INVALID
+        } else {
+            self.opposite_corners[c as usize]
+        }
+    }
+
+    pub fn vertex(&self, c:u32) -> u32 {
+        if c == INVALID {
+            INVALID
+        } else {
+            self.corner_to_vertex[c as usize]
+        }
+    }
+
+    pub fn left_most_corner(&self, v:u32) -> u32 {
+        if v == INVALID {
+            INVALID
+        } else {
+            self.vertex_corners[v as usize]
+        }
+    }
+
+    pub fn swing_left(&self, c:u32) -> u32 {
+        self.next(self.opposite(self.next(c)))
+    }
+
+    pub fn swing_right(&self, c:u32) -> u32 {
+        self.previous(self.opposite(self.previous(c)))
+    }
+
+    pub fn right_corner(&self, c:u32) -> u32 {
+        self.opposite(self.previous(c))
+    }
+
+    pub fn left_corner(&self, c:u32) -> u32 {
+        self.opposite(self.next(c))
+    }
+
+    pub fn is_on_boundary(&self, v:u32) -> bool {
+        let c = self.left_most_corner(v);
+        c == INVALID || self.swing_left(c) == INVALID
+    }
+
+    pub fn set_opposite(&mut self, c0:u32, c1:u32) {
+        self.opposite_corners[c0 as usize] = c1;
+        self.opposite_corners[c1 as usize] = c0;
+    }
+
+    pub fn map_corner_to_vertex(&mut self, c:u32, v:u32) {
+        self.corner_to_vertex[c as usize] = v;
+    }
+
+    pub fn set_left_most_corner(&mut self, v:u32, c:u32) {
+        self.vertex_corners[v as usize] = c;
+    }
+
+    pub fn add_new_vertex(&mut self) -> u32 {
+        self.vertex_corners.push(INVALID);
+        self.vertex_corners.len() as u32 - 1
+    }
+
+    pub fn make_vertex_isolated(&mut self, v:u32) {
+        self.vertex_corners[v as usize] = INVALID;
+    }
+
+    // Copy of this table where attribute seams cut the connectivity
+    pub fn cut_along_seams(&self, seam_corners:&[u32]) -> Result<(CornerTable, Vec<bool>), String> {
+        let mut edge_on_seam = vec![false; self.num_corners()];
+        let mut vertex_on_seam = vec![false; self.num_vertices()];
+        for c in seam_corners {
+            let c = *c;
+            edge_on_seam[c as usize] = true;
+            vertex_on_seam[self.vertex(self.next(c)) as usize] = true;
+            vertex_on_seam[self.vertex(self.previous(c)) as usize] = true;
+            let opp = self.opposite(c);
+            if opp != INVALID {
+                edge_on_seam[opp as usize] = true;
+                vertex_on_seam[self.vertex(self.next(opp)) as usize] = true;
+                vertex_on_seam[self.vertex(self.previous(opp)) as usize] = true;
+            }
+        }
+        let mut table = CornerTable::new(self.num_faces());
+        for (c, on_seam) in edge_on_seam.iter().enumerate() {
+            if !on_seam {
+                table.opposite_corners[c] = self.opposite_corners[c];
+            }
+        }
+        for v in 0..self.num_vertices() as u32 {
+            let c = self.left_most_corner(v);
+            if c == INVALID {
+                continue;
+            }
+            let mut first = c;
+            if vertex_on_seam[v as usize] {
+                let mut act = table.swing_left(first);
+                while act != INVALID {
+                    if act == c {
+                        return Err(String::from("Circular Draco attribute seam"));
+                    }
+                    first = act;
+                    act = table.swing_left(act);
+                }
+            }
+            let mut vert = table.add_new_vertex();
+            table.corner_to_vertex[first as usize] = vert;
+            table.vertex_corners[vert as usize] = first;
+            let mut act = self.swing_right(first);
+            while act != INVALID && act != first {
+                if edge_on_seam[self.next(act) as usize] {
+                    vert = table.add_new_vertex();
+                    table.vertex_corners[vert as usize] = act;
+                }
+                table.corner_to_vertex[act as usize] = vert;
+                act = self.swing_right(act);
+            }
+        }
+        Ok((table, vertex_on_seam))
+    }
+}
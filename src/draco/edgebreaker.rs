@@ -0,0 +1,535 @@
+// Edgebreaker connectivity decoding (standard and valence traversals)
+use std::collections::HashMap;
+use super::buffer::DecoderBuffer;
+use super::corner_table::{CornerTable, INVALID};
+use super::rans::{decode_symbols, BitReader, RAnsBitDecoder};
+use super::Connectivity;
+
+const TOPOLOGY_C: u8 = 0;
+const TOPOLOGY_S: u8 = 1;
+const TOPOLOGY_L: u8 = 2;
+const TOPOLOGY_R: u8 = 3;
+const TOPOLOGY_E: u8 = 4;
+
+const TRAVERSAL_STANDARD: u8 = 0;
+const TRAVERSAL_VALENCE: u8 = 2;
+
+// Connectivity of one non-position attribute, cut along its seams
+pub struct AttributeConnectivity {
+    pub seam_corners: Vec<u32>,
+    pub table: CornerTable,
+    pub vertex_on_seam: Vec<bool>,
+    // Attribute decoder using this data and whether it traverses the cut table
+    pub decoder_id: i32,
+    pub connectivity_used: bool,
+}
+
+struct TopologySplit {
+    source_symbol: u32,
+    split_symbol: u32,
+    source_edge: u32,
+}
+
+// Where the traversal symbols come from
+enum Symbols<'a> {
+    Standard(BitReader<'a>),
+    Valence {
+        contexts: Vec<Vec<u32>>,
+        counters: Vec<i64>,
+        active: Option<usize>,
+        valences: Vec<i32>,
+    },
+}
+
+impl<'a> Symbols<'a> {
+    fn next(&mut self) -> Result<u8, String> {
+        match self {
+            Symbols::Standard(bits) => {
+                if bits.bits(1)? == 0 {
+                    return Ok(TOPOLOGY_C);
+                }
+                Ok(match bits.bits(2)? {
+                    0 => TOPOLOGY_S,
+                    1 => TOPOLOGY_L,
+                    2 => TOPOLOGY_R,
+                    _ => TOPOLOGY_E,
+                })
+            }
+            Symbols::Valence { contexts, counters, active, .. } => match active {
+                // The first symbol is always E
+                None => Ok(TOPOLOGY_E),
+                Some(context) => {
+                    counters[*context] -= 1;
+                    let counter = counters[*context];
+                    if counter < 0 {
+                        return Err(String::from("Draco valence context ran out of symbols"));
+                    }
+                    let symbol = contexts[*context][counter as usize];
+                    if symbol > TOPOLOGY_E as u32 {
+                        return Err(format!("Invalid Draco valence symbol {}", symbol));
+                    }
+                    Ok(symbol as u8)
+                }
+            },
+        }
+    }
+
+    // Valence traversal picks the context of the next symbol from the vertex valences
+    fn new_active_corner(&mut self, table:&CornerTable, corner:u32, symbol:u8) {
+        if let Symbols::Valence { active, valences, .. } = self {
+            let next = table.vertex(table.next(corner)) as usize;
+            let prev = table.vertex(table.previous(corner)) as usize;
+            let tip = table.vertex(corner) as usize;
+            match symbol {
+                TOPOLOGY_C | TOPOLOGY_S => {
+                    valences[next] += 1;
+                    valences[prev] += 1;
+                }
+                TOPOLOGY_R => {
+                    valences[tip] += 1;
+                    valences[next] += 1;
+                    valences[prev] += 2;
+                }
+                TOPOLOGY_L => {
+                    valences[tip] += 1;
+                    valences[next] += 2;
+                    valences[prev] += 1;
+                }
+                _ => {
+                    valences[tip] += 2;
+                    valences[next] += 2;
+                    valences[prev] += 2;
+                }
+            }
+            *active = Some((valences[next].clamp(2, 7) - 2) as usize);
+        }
+    }
+
+    fn merge_vertices(&mut self, dest:u32, source:u32) {
+        if let Symbols::Valence { valences, .. } = self {
+            valences[dest as usize] += valences[source as usize];
+        }
+    }
+}
+
+pub fn decode(buffer:&mut DecoderBuffer, traversal_type:u8) -> Result<Connectivity, String> {
+    let num_encoded_vertices = buffer.varint32()? as usize;
+    let num_faces = buffer.varint32()? as usize;
+    let num_attribute_data = buffer.u8()? as usize;
+    let num_symbols = buffer.varint32()? as usize;
+    if num_faces < num_symbols || num_faces > num_symbols + num_symbols / 3 {
+        return Err(String::from("Invalid Draco edgebreaker face count"));
+    }
+    let num_split_symbols = buffer.varint32()? as usize;
+    if num_split_symbols > num_symbols {
+        return Err(String::from("Invalid Draco edgebreaker split symbol count"));
+    }
+    let connectivity_size = buffer.varint32()? as usize;
+    if connectivity_size == 0 || connectivity_size > buffer.remaining() {
+        return Err(String::from("Invalid Draco edgebreaker connectivity size"));
+    }
+
+    // Topology split events are stored right after the traversal data
+    let mut events = DecoderBuffer::new(&buffer.rest()[connectivity_size..]);
+    let num_splits = events.varint32()? as usize;
+    if num_splits > num_faces {
+        return Err(String::from("Invalid Draco topology split count"));
+    }
+    let mut splits = Vec::with_capacity(num_splits);
+    let mut last_source = 0;
+    for _ in 0..num_splits {
+        let source_symbol = events.varint32()? + last_source;
+        let delta = events.varint32()?;
+        if delta > source_symbol {
+            return Err(String::from("Invalid Draco topology split"));
+        }
+        splits.push(TopologySplit {
+            source_symbol,
+            split_symbol: source_symbol - delta,
+            source_edge: 0,
+        });
+        last_source = source_symbol;
+    }
+    if num_splits > 0 {
+        let rest = events.rest();
+        let mut bits = BitReader::new(rest);
+        for split in splits.iter_mut() {
+            split.source_edge = bits.bits(1)?;
+        }
+        events.skip(bits.bytes_read())?;
+    }
+    let split_bytes = buffer.rest().len() - connectivity_size - events.remaining();
+
+    // Traversal symbols, start face configurations and attribute seams
+    let max_vertices = num_encoded_vertices + num_split_symbols;
+    let mut symbols = match traversal_type {
+        TRAVERSAL_STANDARD => {
+            let size = buffer.varint()? as usize;
+            Symbols::Standard(BitReader::new(buffer.bytes(size)?))
+        }
+        TRAVERSAL_VALENCE => Symbols::Valence {
+            contexts: Vec::new(),
+            counters: Vec::new(),
+            active: None,
+            valences: vec![0; max_vertices],
+        },
+        _ => return Err(format!("Unsupported Draco edgebreaker traversal {}", traversal_type)),
+    };
+    let mut start_faces = RAnsBitDecoder::start(buffer)?;
+    let mut seam_decoders = Vec::with_capacity(num_attribute_data);
+    for _ in 0..num_attribute_data {
+        seam_decoders.push(RAnsBitDecoder::start(buffer)?);
+    }
+    if let Symbols::Valence { contexts, counters, .. } = &mut symbols {
+        let valence_splits = buffer.varint32()? as usize;
+        if valence_splits >= max_vertices.max(1) {
+            return Err(String::from("Invalid Draco valence split count"));
+        }
+        if buffer.i8()? != 0 {
+            return Err(String::from("Unsupported Draco valence mode"));
+        }
+        for _ in 2..=7 {
+            let num = buffer.varint32()? as usize;
+            if num > num_faces {
+                return Err(String::from("Invalid Draco valence context size"));
+            }
+            contexts.push(decode_symbols(buffer, num, 1)?);
+            counters.push(num as i64);
+        }
+    }
+
+    let mut table = CornerTable::new(num_faces);
+    let mut is_vert_hole = vec![true; max_vertices];
+    let num_vertices = decode_traversal(&mut table, &mut symbols, &mut start_faces, &mut splits,
+                                        &mut is_vert_hole, num_symbols, max_vertices, num_attribute_data == 0)?;
+    buffer.skip(split_bytes)?;
+
+    // Seams of the non-position attributes, boundary edges are always seams
+    let mut attribute_data: Vec<AttributeConnectivity> = (0..num_attribute_data).map(|_| AttributeConnectivity {
+        seam_corners: Vec::new(),
+        table: CornerTable::default(),
+        vertex_on_seam: Vec::new(),
+        decoder_id: -1,
+        connectivity_used: true,
+    }).collect();
+    if num_attribute_data > 0 {
+        for f in 0..num_faces as u32 {
+            let corner = 3 * f;
+            for c in [corner, table.next(corner), table.previous(corner)] {
+                let opp = table.opposite(c);
+                if opp == INVALID {
+                    for data in attribute_data.iter_mut() {
+                        data.seam_corners.push(c);
+                    }
+                    continue;
+                }
+                if opp / 3 < f {
+                    continue;
+                }
+                for (data, decoder) in attribute_data.iter_mut().zip(seam_decoders.iter_mut()) {
+                    if decoder.bit() {
+                        data.seam_corners.push(c);
+                    }
+                }
+            }
+        }
+    }
+    for data in attribute_data.iter_mut() {
+        let (cut, vertex_on_seam) = table.cut_along_seams(&data.seam_corners)?;
+        data.table = cut;
+        data.vertex_on_seam = vertex_on_seam;
+    }
+
+    let (faces, num_points) = assign_points(&table, &attribute_data, &is_vert_hole, num_vertices)?;
+    Ok(Connectivity {
+        faces,
+        num_points,
+        table: Some(table),
+        attribute_data,
+    })
+}
+
+// Rebuilds the corner table from the symbols, in reverse order of the encoder
+#[allow(clippy::too_many_arguments)]
+fn decode_traversal(table:&mut CornerTable, symbols:&mut Symbols, start_faces:&mut RAnsBitDecoder,
+                    splits:&mut Vec<TopologySplit>, is_vert_hole:&mut [bool], num_symbols:usize,
+                    max_vertices:usize, remove_invalid_vertices:bool) -> Result<usize, String> {
+    let corrupt = || String::from("Corrupted Draco edgebreaker connectivity");
+    let mut active_corners: Vec<u32> = Vec::new();
+    let mut split_corners: HashMap<usize, u32> = HashMap::new();
+    let mut invalid_vertices = Vec::new();
+    let mut num_faces = 0;
+    for symbol_id in 0..num_symbols {
+        let corner = 3 * num_faces as u32;
+        num_faces += 1;
+        let symbol = symbols.next()?;
+        let mut check_split = false;
+        match symbol {
+            TOPOLOGY_C => {
+                let corner_a = *active_corners.last().ok_or_else(corrupt)?;
+                let vertex_x = table.vertex(table.next(corner_a));
+                let corner_b = table.next(table.left_most_corner(vertex_x));
+                if corner_a == corner_b || table.opposite(corner_a) != INVALID || table.opposite(corner_b) != INVALID {
+                    return Err(corrupt());
+                }
+                table.set_opposite(corner_a, corner + 1);
+                table.set_opposite(corner_b, corner + 2);
+                let vert_a_prev = table.vertex(table.previous(corner_a));
+                let vert_b_next = table.vertex(table.next(corner_b));
+                if vertex_x == vert_a_prev || vertex_x == vert_b_next {
+                    return Err(corrupt());
+                }
+                table.map_corner_to_vertex(corner, vertex_x);
+                table.map_corner_to_vertex(corner + 1, vert_b_next);
+                table.map_corner_to_vertex(corner + 2, vert_a_prev);
+                table.set_left_most_corner(vert_a_prev, corner + 2);
+                is_vert_hole[vertex_x as usize] = false;
+                *active_corners.last_mut().unwrap() = corner;
+            }
+            TOPOLOGY_R | TOPOLOGY_L => {
+                let corner_a = *active_corners.last().ok_or_else(corrupt)?;
+                if table.opposite(corner_a) != INVALID {
+                    return Err(corrupt());
+                }
+                let (opp_corner, corner_l, corner_r) = if symbol == TOPOLOGY_R {
+                    (corner + 2, corner + 1, corner)
+                } else {
+                    (corner + 1, corner, corner + 2)
+                };
+                table.set_opposite(opp_corner, corner_a);
+                let new_vertex = table.add_new_vertex();
+                if table.num_vertices() > max_vertices {
+                    return Err(corrupt());
+                }
+                table.map_corner_to_vertex(opp_corner, new_vertex);
+                table.set_left_most_corner(new_vertex, opp_corner);
+                let vertex_r = table.vertex(table.previous(corner_a));
+                table.map_corner_to_vertex(corner_r, vertex_r);
+                table.set_left_most_corner(vertex_r, corner_r);
+                table.map_corner_to_vertex(corner_l, table.vertex(table.next(corner_a)));
+                *active_corners.last_mut().unwrap() = corner;
+                check_split = true;
+            }
+            TOPOLOGY_S => {
+                let corner_b = active_corners.pop().ok_or_else(corrupt)?;
+                if let Some(split_corner) = split_corners.get(&symbol_id) {
+                    active_corners.push(*split_corner);
+                }
+                let corner_a = *active_corners.last().ok_or_else(corrupt)?;
+                if corner_a == corner_b || table.opposite(corner_a) != INVALID || table.opposite(corner_b) != INVALID {
+                    return Err(corrupt());
+                }
+                table.set_opposite(corner_a, corner + 2);
+                table.set_opposite(corner_b, corner + 1);
+                let vertex_p = table.vertex(table.previous(corner_a));
+                table.map_corner_to_vertex(corner, vertex_p);
+                table.map_corner_to_vertex(corner + 1, table.vertex(table.next(corner_a)));
+                let vert_b_prev = table.vertex(table.previous(corner_b));
+                table.map_corner_to_vertex(corner + 2, vert_b_prev);
+                table.set_left_most_corner(vert_b_prev, corner + 2);
+                let mut corner_n = table.next(corner_b);
+                let vertex_n = table.vertex(corner_n);
+                symbols.merge_vertices(vertex_p, vertex_n);
+                table.set_left_most_corner(vertex_p, table.left_most_corner(vertex_n));
+                let first_corner = corner_n;
+                while corner_n != INVALID {
+                    table.map_corner_to_vertex(corner_n, vertex_p);
+                    corner_n = table.swing_left(corner_n);
+                    if corner_n == first_corner {
+                        return Err(corrupt());
+                    }
+                }
+                table.make_vertex_isolated(vertex_n);
+                if remove_invalid_vertices {
+                    invalid_vertices.push(vertex_n);
+                }
+                *active_corners.last_mut().unwrap() = corner;
+            }
+            _ => {
+                let first_vertex = table.add_new_vertex();
+                table.add_new_vertex();
+                table.add_new_vertex();
+                if table.num_vertices() > max_vertices {
+                    return Err(corrupt());
+                }
+                for k in 0..3 {
+                    table.map_corner_to_vertex(corner + k, first_vertex + k);
+                    table.set_left_most_corner(first_vertex + k, corner + k);
+                }
+                active_corners.push(corner);
+                check_split = true;
+            }
+        }
+        symbols.new_active_corner(table, *active_corners.last().unwrap(), symbol);
+
+        if check_split {
+            // Faces that are the source of a topology split open an extra active edge
+            let encoder_symbol_id = (num_symbols - symbol_id - 1) as u32;
+            while let Some(split) = splits.last() {
+                if split.source_symbol > encoder_symbol_id {
+                    return Err(corrupt());
+                }
+                if split.source_symbol != encoder_symbol_id {
+                    break;
+                }
+                let top = *active_corners.last().unwrap();
+                let new_active = if split.source_edge == 1 { table.next(top) } else { table.previous(top) };
+                let decoder_split_id = num_symbols.checked_sub(split.split_symbol as usize + 1).ok_or_else(corrupt)?;
+                split_corners.insert(decoder_split_id, new_active);
+                splits.pop();
+            }
+        }
+    }
+    if table.num_vertices() > max_vertices {
+        return Err(corrupt());
+    }
+
+    // Start faces close the remaining active edges
+    while let Some(corner) = active_corners.pop() {
+        if start_faces.bit() {
+            if num_faces >= table.num_faces() {
+                return Err(corrupt());
+            }
+            let vert_n = table.vertex(table.next(corner));
+            let corner_b = table.next(table.left_most_corner(vert_n));
+            let vert_x = table.vertex(table.next(corner_b));
+            let corner_c = table.next(table.left_most_corner(vert_x));
+            if corner == corner_b || corner == corner_c || corner_b == corner_c ||
+                table.opposite(corner) != INVALID || table.opposite(corner_b) != INVALID ||
+                table.opposite(corner_c) != INVALID {
+                return Err(corrupt());
+            }
+            let vert_p = table.vertex(table.next(corner_c));
+            let new_corner = 3 * num_faces as u32;
+            num_faces += 1;
+            table.set_opposite(new_corner, corner);
+            table.set_opposite(new_corner + 1, corner_b);
+            table.set_opposite(new_corner + 2, corner_c);
+            table.map_corner_to_vertex(new_corner, vert_x);
+            table.map_corner_to_vertex(new_corner + 1, vert_p);
+            table.map_corner_to_vertex(new_corner + 2, vert_n);
+            for k in 0..3 {
+                is_vert_hole[table.vertex(new_corner + k) as usize] = false;
+            }
+        }
+    }
+    if num_faces != table.num_faces() {
+        return Err(corrupt());
+    }
+
+    // Move the last valid vertices into the holes left by merged ones
+    let mut num_vertices = table.num_vertices();
+    for invalid in invalid_vertices {
+        let mut src = num_vertices as u32 - 1;
+        while table.left_most_corner(src) == INVALID {
+            num_vertices -= 1;
+            src = num_vertices as u32 - 1;
+        }
+        if src < invalid {
+            continue;
+        }
+        for c in vertex_corners(table, src) {
+            if table.vertex(c) != src {
+                return Err(corrupt());
+            }
+            table.map_corner_to_vertex(c, invalid);
+        }
+        table.set_left_most_corner(invalid, table.left_most_corner(src));
+        table.make_vertex_isolated(src);
+        is_vert_hole[invalid as usize] = is_vert_hole[src as usize];
+        is_vert_hole[src as usize] = false;
+        num_vertices -= 1;
+    }
+    Ok(num_vertices)
+}
+
+// Corners around a vertex, swinging left from its left-most corner then right on open boundaries
+pub fn vertex_corners(table:&CornerTable, v:u32) -> Vec<u32> {
+    corners_around(table, table.left_most_corner(v))
+}
+
+pub fn corners_around(table:&CornerTable, start:u32) -> Vec<u32> {
+    let mut corners = Vec::new();
+    if start == INVALID {
+        return corners;
+    }
+    corners.push(start);
+    let mut c = table.swing_left(start);
+    while c != INVALID && c != start {
+        corners.push(c);
+        c = table.swing_left(c);
+    }
+    if c == INVALID {
+        c = table.swing_right(start);
+        while c != INVALID {
+            corners.push(c);
+            c = table.swing_right(c);
+        }
+    }
+    corners
+}
+
+// Points are vertices split wherever any attribute has a seam
+fn assign_points(table:&CornerTable, attribute_data:&[AttributeConnectivity], is_vert_hole:&[bool],
+                    num_vertices:usize) -> Result<(Vec<[u32; 3]>, usize), String> {
+    let num_faces = table.num_faces();
+    let mut faces = Vec::with_capacity(num_faces);
+    if attribute_data.is_empty() {
+        for f in 0..num_faces as u32 {
+            faces.push([table.vertex(3 * f), table.vertex(3 * f + 1), table.vertex(3 * f + 2)]);
+        }
+        return Ok((faces, num_vertices));
+    }
+    let mut num_points = 0u32;
+    let mut corner_to_point = vec![0u32; table.num_corners()];
+    for v in 0..table.num_vertices() as u32 {
+        let c = table.left_most_corner(v);
+        if c == INVALID {
+            continue;
+        }
+        let mut first = c;
+        if !is_vert_hole[v as usize] {
+            for data in attribute_data {
+                if !data.vertex_on_seam[table.vertex(c) as usize] {
+                    continue;
+                }
+                let vert = data.table.vertex(c);
+                let mut act = table.swing_right(c);
+                let mut found = false;
+                while act != c {
+                    if act == INVALID {
+                        return Err(String::from("Corrupted Draco attribute seams"));
+                    }
+                    if data.table.vertex(act) != vert {
+                        first = act;
+                        found = true;
+                        break;
+                    }
+                    act = table.swing_right(act);
+                }
+                if found {
+                    break;
+                }
+            }
+        }
+        corner_to_point[first as usize] = num_points;
+        num_points += 1;
+        let mut prev = first;
+        let mut c = table.swing_right(first);
+        while c != INVALID && c != first {
+            let seam = attribute_data.iter().any(|data| data.table.vertex(c) != data.table.vertex(prev));
+            if seam {
+                corner_to_point[c as usize] = num_points;
+                num_points += 1;
+            } else {
+                corner_to_point[c as usize] = corner_to_point[prev as usize];
+            }
+            prev = c;
+            c = table.swing_right(c);
+        }
+    }
+    for f in 0..num_faces {
+        faces.push([corner_to_point[3 * f], corner_to_point[3 * f + 1], corner_to_point[3 * f + 2]]);
+    }
+    Ok((faces, num_points as usize))
+}
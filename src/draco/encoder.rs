@@ -0,0 +1,169 @@
+// Sequential Draco encoder: quantized attributes with delta prediction, compressed indices
+use super::attributes::{DECODER_NORMALS, DECODER_QUANTIZATION, DT_FLOAT32, NORMAL};
+use super::buffer::{to_symbol, EncoderBuffer};
+use super::prediction::{Octahedron, PREDICTION_DIFFERENCE, PREDICTION_NONE, TRANSFORM_WRAP};
+use super::rans::encode_symbols;
+
+pub struct EncodeAttribute {
+    pub attribute_type: u8,
+    pub unique_id: u32,
+    pub num_components: usize,
+    // Values laid out per point
+    pub values: Vec<f32>,
+    pub quantization_bits: u32,
+}
+
+pub fn encode(faces:&[[u32; 3]], num_points:usize, attributes:&[EncodeAttribute]) -> Result<Vec<u8>, String> {
+    let mut buffer = EncoderBuffer::default();
+    buffer.data.extend_from_slice(b"DRACO");
+    buffer.u8(2);
+    buffer.u8(2);
+    buffer.u8(super::ENCODER_TRIANGULAR_MESH);
+    buffer.u8(super::METHOD_SEQUENTIAL);
+    buffer.u16(0);
+
+    // Connectivity
+    buffer.varint(faces.len() as u64);
+    buffer.varint(num_points as u64);
+    let mut last = 0i64;
+    let mut symbols = Vec::with_capacity(faces.len() * 3);
+    for index in faces.iter().flatten() {
+        let diff = *index as i64 - last;
+        symbols.push(if diff < 0 { ((-diff) as u32) << 1 | 1 } else { (diff as u32) << 1 });
+        last = *index as i64;
+    }
+    let mut compressed = EncoderBuffer::default();
+    if encode_symbols(&mut compressed, &symbols) {
+        buffer.u8(0);
+        buffer.data.extend_from_slice(&compressed.data);
+    } else {
+        buffer.u8(1);
+        for index in faces.iter().flatten() {
+            if num_points < 256 {
+                buffer.u8(*index as u8);
+            } else if num_points < 1 << 16 {
+                buffer.u16(*index as u16);
+            } else if num_points < 1 << 21 {
+                buffer.varint(*index as u64);
+            } else {
+                buffer.u32(*index);
+            }
+        }
+    }
+
+    // A single attributes decoder for every attribute
+    buffer.u8(1);
+    buffer.varint(attributes.len() as u64);
+    for attribute in attributes {
+        if attribute.values.len() != num_points * attribute.num_components {
+            return Err(format!("Draco attribute {} does not have a value per point", attribute.unique_id));
+        }
+        buffer.u8(attribute.attribute_type);
+        buffer.u8(DT_FLOAT32);
+        buffer.u8(attribute.num_components as u8);
+        buffer.u8(0);
+        buffer.varint(attribute.unique_id as u64);
+    }
+    for attribute in attributes {
+        buffer.u8(if is_normal(attribute) { DECODER_NORMALS } else { DECODER_QUANTIZATION });
+    }
+
+    let mut transforms = EncoderBuffer::default();
+    for attribute in attributes {
+        if is_normal(attribute) {
+            encode_normals(&mut buffer, attribute)?;
+            transforms.u8(attribute.quantization_bits as u8);
+        } else {
+            let (min, range) = encode_quantized(&mut buffer, attribute)?;
+            for value in min {
+                transforms.f32(value);
+            }
+            transforms.f32(range);
+            transforms.u8(attribute.quantization_bits as u8);
+        }
+    }
+    buffer.data.extend_from_slice(&transforms.data);
+    Ok(buffer.data)
+}
+
+fn is_normal(attribute:&EncodeAttribute) -> bool {
+    attribute.attribute_type == NORMAL && attribute.num_components == 3
+}
+
+fn encode_normals(buffer:&mut EncoderBuffer, attribute:&EncodeAttribute) -> Result<(), String> {
+    let octahedron = Octahedron::new(attribute.quantization_bits)?;
+    let mut values = Vec::with_capacity(attribute.values.len() / 3 * 2);
+    for normal in attribute.values.chunks(3) {
+        let (s, t) = octahedron.octahedral_coords([normal[0], normal[1], normal[2]]);
+        values.push(s);
+        values.push(t);
+    }
+    buffer.u8(PREDICTION_NONE as u8);
+    write_symbols(buffer, &values.iter().map(|x| to_symbol(*x)).collect::<Vec<u32>>());
+    Ok(())
+}
+
+// Quantizes the attribute and writes the wrapped differences between consecutive values
+fn encode_quantized(buffer:&mut EncoderBuffer, attribute:&EncodeAttribute) -> Result<(Vec<f32>, f32), String> {
+    let bits = attribute.quantization_bits;
+    if !(1..=30).contains(&bits) {
+        return Err(format!("Invalid Draco quantization bits {}", bits));
+    }
+    let n = attribute.num_components;
+    let mut min = vec![f32::MAX; n];
+    let mut max = vec![f32::MIN; n];
+    for (i, value) in attribute.values.iter().enumerate() {
+        min[i % n] = min[i % n].min(*value);
+        max[i % n] = max[i % n].max(*value);
+    }
+    if attribute.values.is_empty() {
+        min = vec![0.0; n];
+        max = vec![0.0; n];
+    }
+    let mut range = (0..n).map(|k| max[k] - min[k]).fold(0.0f32, f32::max);
+    if range == 0.0 {
+        range = 1.0;
+    }
+    let max_quantized_value = ((1u32 << bits) - 1) as f32;
+    let inverse_delta = max_quantized_value / range;
+    let quantized: Vec<i32> = attribute.values.iter().enumerate()
+        .map(|(i, value)| ((value - min[i % n]) * inverse_delta + 0.5).floor() as i32).collect();
+
+    let min_value = quantized.iter().copied().min().unwrap_or(0);
+    let max_value = quantized.iter().copied().max().unwrap_or(0);
+    let max_dif = 1 + max_value - min_value;
+    let max_correction = if max_dif % 2 == 0 { max_dif / 2 - 1 } else { max_dif / 2 };
+    let min_correction = -(max_dif / 2);
+    let mut symbols = Vec::with_capacity(quantized.len());
+    for (i, value) in quantized.iter().enumerate() {
+        let predicted = if i < n { min_value.max(0).min(max_value) } else { quantized[i - n] };
+        let mut correction = value - predicted;
+        if correction < min_correction {
+            correction += max_dif;
+        } else if correction > max_correction {
+            correction -= max_dif;
+        }
+        symbols.push(to_symbol(correction));
+    }
+
+    buffer.u8(PREDICTION_DIFFERENCE as u8);
+    buffer.u8(TRANSFORM_WRAP as u8);
+    write_symbols(buffer, &symbols);
+    buffer.u32(min_value as u32);
+    buffer.u32(max_value as u32);
+    Ok((min, range))
+}
+
+fn write_symbols(buffer:&mut EncoderBuffer, symbols:&[u32]) {
+    let mut compressed = EncoderBuffer::default();
+    if encode_symbols(&mut compressed, symbols) {
+        buffer.u8(1);
+        buffer.data.extend_from_slice(&compressed.data);
+    } else {
+        buffer.u8(0);
+        buffer.u8(4);
+        for symbol in symbols {
+            buffer.u32(*symbol);
+        }
+    }
+}
@@ -0,0 +1,568 @@
+// Draco mesh compression (KHR_draco_mesh_compression), decoder and a sequential encoder
+mod attributes;
+mod buffer;
+mod corner_table;
+mod edgebreaker;
+mod encoder;
+mod prediction;
+mod rans;
+
+use buffer::DecoderBuffer;
+use corner_table::CornerTable;
+use edgebreaker::AttributeConnectivity;
+use rans::decode_symbols;
+
+pub use attributes::{Attribute, NORMAL, POSITION, TEX_COORD};
+pub use encoder::{encode, EncodeAttribute};
+
+const ENCODER_POINT_CLOUD: u8 = 0;
+const ENCODER_TRIANGULAR_MESH: u8 = 1;
+const METHOD_SEQUENTIAL: u8 = 0;
+const METHOD_EDGEBREAKER: u8 = 1;
+const METADATA_FLAG: u16 = 0x8000;
+
+// Faces over point ids, and the connectivity the attribute decoders traverse
+pub struct Connectivity {
+    pub faces: Vec<[u32; 3]>,
+    pub num_points: usize,
+    pub table: Option<CornerTable>,
+    pub attribute_data: Vec<AttributeConnectivity>,
+}
+
+pub struct Mesh {
+    pub faces: Vec<[u32; 3]>,
+    pub num_points: usize,
+    pub attributes: Vec<Attribute>,
+}
+
+impl Mesh {
+    pub fn attribute(&self, unique_id:u32) -> Option<&Attribute> {
+        self.attributes.iter().find(|attribute| attribute.unique_id == unique_id)
+    }
+}
+
+impl Attribute {
+    // Values laid out per point, like a glTF accessor
+    pub fn point_values(&self) -> Vec<f64> {
+        let n = self.num_components;
+        let mut values = Vec::with_capacity(self.point_to_value.len() * n);
+        for value in &self.point_to_value {
+            let i = *value as usize * n;
+            values.extend_from_slice(&self.values[i..i+n]);
+        }
+        values
+    }
+}
+
+pub fn decode(data:&[u8]) -> Result<Mesh, String> {
+    let mut buffer = DecoderBuffer::new(data);
+    if buffer.bytes(5)? != b"DRACO" {
+        return Err(String::from("Not a Draco buffer"));
+    }
+    let major = buffer.u8()?;
+    let minor = buffer.u8()?;
+    if (major, minor) != (2, 2) {
+        return Err(format!("Unsupported Draco bitstream version {}.{}", major, minor));
+    }
+    let encoder_type = buffer.u8()?;
+    let method = buffer.u8()?;
+    let flags = buffer.u16()?;
+    if encoder_type != ENCODER_TRIANGULAR_MESH {
+        let kind = if encoder_type == ENCODER_POINT_CLOUD { "point cloud" } else { "unknown geometry" };
+        return Err(format!("Draco {} is not a triangle mesh", kind));
+    }
+    if flags & METADATA_FLAG != 0 {
+        skip_metadata(&mut buffer)?;
+    }
+    let mut connectivity = match method {
+        METHOD_SEQUENTIAL => decode_sequential(&mut buffer)?,
+        METHOD_EDGEBREAKER => {
+            let traversal = buffer.u8()?;
+            edgebreaker::decode(&mut buffer, traversal)?
+        }
+        _ => return Err(format!("Unknown Draco encoding method {}", method)),
+    };
+    let attributes = attributes::decode(&mut buffer, &mut connectivity)?;
+    Ok(Mesh {
+        faces: connectivity.faces,
+        num_points: connectivity.num_points,
+        attributes,
+    })
+}
+
+fn skip_metadata(buffer:&mut DecoderBuffer) -> Result<(), String> {
+    let num_attribute_metadata = buffer.varint32()?;
+    for _ in 0..num_attribute_metadata {
+        buffer.varint32()?;
+        skip_metadata_entries(buffer, 0)?;
+    }
+    skip_metadata_entries(buffer, 0)
+}
+
+fn skip_metadata_entries(buffer:&mut DecoderBuffer, depth:u32) -> Result<(), String> {
+    if depth > 64 {
+        return Err(String::from("Draco metadata is nested too deeply"));
+    }
+    let num_entries = buffer.varint32()?;
+    for _ in 0..num_entries {
+        let name_length = buffer.u8()? as usize;
+        buffer.skip(name_length)?;
+        let data_size = buffer.varint32()? as usize;
+        buffer.skip(data_size)?;
+    }
+    let num_sub_metadata = buffer.varint32()?;
+    for _ in 0..num_sub_metadata {
+        let name_length = buffer.u8()? as usize;
+        buffer.skip(name_length)?;
+        skip_metadata_entries(buffer, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn decode_sequential(buffer:&mut DecoderBuffer) -> Result<Connectivity, String> {
+    let num_faces = buffer.varint32()? as usize;
+    let num_points = buffer.varint32()? as usize;
+    if num_faces > buffer.remaining() * 8 {
+        return Err(String::from("Invalid Draco face count"));
+    }
+    let mut faces = Vec::with_capacity(num_faces);
+    if buffer.u8()? == 0 {
+        // Differences to the previous index, sign in the lowest bit
+        let symbols = decode_symbols(buffer, num_faces * 3, 1)?;
+        let mut last = 0i64;
+        for face in symbols.chunks(3) {
+            let mut out = [0u32; 3];
+            for (k, symbol) in face.iter().enumerate() {
+                let diff = (symbol >> 1) as i64;
+                last += if symbol & 1 == 1 { -diff } else { diff };
+                if last < 0 || last >= num_points as i64 {
+                    return Err(String::from("Invalid Draco index"));
+                }
+                out[k] = last as u32;
+            }
+            faces.push(out);
+        }
+    } else {
+        for _ in 0..num_faces {
+            let mut face = [0u32; 3];
+            for index in face.iter_mut() {
+                *index = if num_points < 256 {
+                    buffer.u8()? as u32
+                } else if num_points < 1 << 16 {
+                    buffer.u16()? as u32
+                } else if num_points < 1 << 21 {
+                    buffer.varint32()?
+                } else {
+                    buffer.u32()?
+                };
+                if *index as usize >= num_points {
+                    return Err(String::from("Invalid Draco index"));
+                }
+            }
+            faces.push(face);
+        }
+    }
+    Ok(Connectivity {
+        faces,
+        num_points,
+        table: None,
+        attribute_data: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attributes::{DECODER_INTEGER, DECODER_NORMALS};
+    use prediction::{MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM, MESH_PREDICTION_GEOMETRIC_NORMAL,
+                     MESH_PREDICTION_MULTI_PARALLELOGRAM, MESH_PREDICTION_PARALLELOGRAM,
+                     MESH_PREDICTION_TEX_COORDS_PORTABLE, PREDICTION_DIFFERENCE, PREDICTION_NONE,
+                     TRANSFORM_NORMAL_OCTAHEDRON, TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED, TRANSFORM_WRAP};
+
+    // Header of a Draco 2.2 triangle mesh without metadata
+    fn header(method:u8) -> Vec<u8> {
+        let mut data = b"DRACO".to_vec();
+        data.extend_from_slice(&[2, 2, ENCODER_TRIANGULAR_MESH, method, 0, 0]);
+        data
+    }
+
+    fn floats(values:&[f32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_a_sequential_mesh_with_raw_indices() {
+        // Two faces over four points, quantized positions and plain float texture coordinates
+        let mut data = header(METHOD_SEQUENTIAL);
+        data.extend_from_slice(&[2, 4, 1, 0, 1, 2, 0, 2, 3]);
+        data.extend_from_slice(&[1, 2, POSITION, 9, 3, 0, 0, TEX_COORD, 9, 2, 0, 1, 2, 0]);
+        // Positions: no prediction, one raw byte per symbol, the quantized values 0 to 3 as symbols
+        data.extend_from_slice(&[0xfe, 0, 1]);
+        data.extend_from_slice(&[0, 0, 0, 6, 0, 0, 6, 6, 0, 0, 6, 2]);
+        data.extend(floats(&[0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]));
+        // Dequantization: minimum, range and bits, a step of 1
+        data.extend(floats(&[-1.0, 0.0, 10.0, 3.0]));
+        data.push(2);
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.num_points, 4);
+        assert_eq!(mesh.attribute(0).unwrap().point_values(),
+                   vec![-1.0, 0.0, 10.0, 2.0, 0.0, 10.0, 2.0, 3.0, 10.0, -1.0, 3.0, 11.0]);
+        assert_eq!(mesh.attribute(1).unwrap().point_values(), vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn decodes_an_edgebreaker_triangle() {
+        // Standard traversal of a single E symbol, no start face and no split
+        let mut data = header(METHOD_EDGEBREAKER);
+        data.push(0);
+        data.extend_from_slice(&[3, 1, 0, 1, 0, 5]);
+        // Symbol bits, start faces as an ans stream giving a single 0 bit, split count
+        data.extend_from_slice(&[1, 0x07, 0xff, 1, 1, 0]);
+        // One vertex attribute decoder with the float positions in depth first order
+        data.extend_from_slice(&[1, 0xff, 0, 0, 1, POSITION, 9, 3, 0, 0, 0]);
+        data.extend(floats(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0]));
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+        assert_eq!(mesh.num_points, 3);
+        // The traversal reaches the second and third corners before the first one
+        assert_eq!(mesh.attribute(0).unwrap().point_values(), vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn encoded_meshes_decode_within_their_quantization() {
+        // A bumpy 6 by 6 grid with its normals and texture coordinates
+        let n = 6;
+        let mut faces = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let i = y * n + x;
+                faces.extend([[i, i + 1, i + n + 1], [i, i + n + 1, i + n]]);
+            }
+        }
+        let (mut positions, mut normals, mut texcoords) = (Vec::new(), Vec::new(), Vec::new());
+        for i in 0..n * n {
+            let (u, v) = ((i % n) as f32 / (n - 1) as f32, (i / n) as f32 / (n - 1) as f32);
+            positions.extend([u * 4.0 - 2.0, v * 4.0 - 2.0, (u * 5.0).sin() * (v * 3.0).cos()]);
+            let normal = [-(u * 5.0).cos(), (v * 3.0).sin(), 1.0];
+            let length = normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+            normals.extend(normal.map(|x| x / length));
+            texcoords.extend([u, 1.0 - v]);
+        }
+        let attribute = |attribute_type, unique_id, num_components, values:&Vec<f32>, quantization_bits| EncodeAttribute {
+            attribute_type,
+            unique_id,
+            num_components,
+            values: values.clone(),
+            quantization_bits,
+        };
+        let attributes = [attribute(POSITION, 0, 3, &positions, 14), attribute(NORMAL, 1, 3, &normals, 10),
+                          attribute(TEX_COORD, 2, 2, &texcoords, 12)];
+        let mesh = decode(&encode(&faces, (n * n) as usize, &attributes).unwrap()).unwrap();
+
+        assert_eq!(mesh.faces, faces);
+        assert_eq!(mesh.num_points, (n * n) as usize);
+        // A quantization step over the largest range, and about the size of an octahedral cell for the normals
+        for (id, values, tolerance) in [(0, &positions, 4.0 / 16383.0), (1, &normals, 0.01), (2, &texcoords, 1.0 / 4095.0)] {
+            let decoded = mesh.attribute(id).unwrap().point_values();
+            assert_eq!(decoded.len(), values.len());
+            for (decoded, value) in decoded.iter().zip(values) {
+                assert!((decoded - *value as f64).abs() <= tolerance, "attribute {}: {} for {}", id, decoded, value);
+            }
+        }
+    }
+
+    const DT_INT32: u8 = 5;
+
+    // A fan of six triangles closed around point 1, the points numbered as the traversal creates them
+    const FAN_FACES: [[u32; 3]; 6] = [[0, 1, 2], [2, 1, 3], [3, 1, 4], [4, 1, 5], [5, 1, 6], [1, 0, 6]];
+    const FAN: [[i32; 3]; 7] = [[2, 0, 1], [0, 0, 3], [2, 2, 0], [0, 2, 2], [-2, 2, 1], [-2, -2, 0], [0, -2, 2]];
+    // Points in the order the depth first traversal reaches them, the center first then around the fan
+    const FAN_ORDER: [usize; 7] = [1, 2, 0, 6, 5, 4, 3];
+
+    // Bits of the standard traversal, 0 for C and 1 followed by two bits for the other symbols
+    fn standard_symbols(symbols:&str) -> Vec<u8> {
+        let mut bits = Vec::new();
+        for symbol in symbols.chars() {
+            match symbol {
+                'C' => bits.push(0),
+                'S' => bits.extend([1, 0, 0]),
+                'L' => bits.extend([1, 1, 0]),
+                'R' => bits.extend([1, 0, 1]),
+                _ => bits.extend([1, 1, 1]),
+            }
+        }
+        let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+        for (i, bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= bit << (i % 8);
+        }
+        bytes
+    }
+
+    // An ans bit stream written like the reference encoder does, from the last bit to the first
+    fn ans_bits(prob_zero:u8, bits:&[bool]) -> Vec<u8> {
+        let (p0, p1) = (prob_zero as u32, 256 - prob_zero as u32);
+        let mut state = 4096u32;
+        let mut stream = Vec::new();
+        for bit in bits.iter().rev() {
+            let size = if *bit { p1 } else { p0 };
+            if state >= 4096 * size {
+                stream.push(state as u8);
+                state /= 256;
+            }
+            state = state / size * 256 + state % size + if *bit { 0 } else { p1 };
+        }
+        let rest = state - 4096;
+        if rest < 1 << 6 {
+            stream.push(rest as u8);
+        } else if rest < 1 << 14 {
+            stream.extend_from_slice(&((1 << 14) + rest as u16).to_le_bytes());
+        } else {
+            stream.extend_from_slice(&((2 << 22) + rest).to_le_bytes()[..3]);
+        }
+        let mut data = vec![prob_zero, stream.len() as u8];
+        data.extend(stream);
+        data
+    }
+
+    // Raw rANS coding of a single symbol, its probability taking the whole 12 bits of precision
+    fn one_symbol(symbol:u8) -> Vec<u8> {
+        let mut data = vec![1, 3, symbol + 1];
+        if symbol > 0 {
+            // The smaller symbols are skipped with a zero probability
+            data.push(((symbol - 1) << 2) | 3);
+        }
+        // 4096 as 0 and one extra byte of 64, then a stream of one byte leaving the state at its lowest
+        data.extend_from_slice(&[1, 64, 1, 0]);
+        data
+    }
+
+    // Symbols stored without entropy coding, one byte each
+    fn raw_bytes(symbols:&[u32]) -> Vec<u8> {
+        let mut data = vec![0, 1];
+        data.extend(symbols.iter().map(|s| *s as u8));
+        data
+    }
+
+    // Edgebreaker connectivity of the fan from the symbols ERRRRC: E starts the first triangle, each R adds a point
+    // on the right of the active edge and C closes the fan on the first point
+    fn fan(valence:bool) -> Vec<u8> {
+        let mut traversal = Vec::new();
+        if valence {
+            traversal.extend(ans_bits(128, &[false]));
+            // No split and the 2-7 mode, then one list of symbols per valence of the point ahead of the active
+            // edge: the center has a valence of 2 after E and each R adds one, so the C comes with a valence of 6
+            traversal.extend_from_slice(&[0, 0]);
+            for symbol in [Some(3), Some(3), Some(3), Some(3), Some(0), None] {
+                match symbol {
+                    Some(symbol) => {
+                        traversal.push(1);
+                        traversal.extend(one_symbol(symbol));
+                    }
+                    None => traversal.push(0),
+                }
+            }
+        } else {
+            let bits = standard_symbols("ERRRRC");
+            traversal.push(bits.len() as u8);
+            traversal.extend(bits);
+            traversal.extend(ans_bits(128, &[false]));
+        }
+        // Traversal, encoded vertices, faces, attribute data, symbols, split symbols and the size of what follows
+        let mut data = vec![if valence { 2 } else { 0 }, 7, 6, 0, 6, 0, traversal.len() as u8];
+        data.extend(traversal);
+        // No topology split
+        data.push(0);
+        data
+    }
+
+    // Corrections of the wrap transform against the clamped predictions, folded into the range like the reference
+    // encoder does
+    fn wrap_corrections(values:&[i32], preds:&[i32], min:i32, max:i32) -> Vec<u32> {
+        let max_dif = 1 + max - min;
+        let max_correction = if max_dif % 2 == 0 { max_dif / 2 - 1 } else { max_dif / 2 };
+        values.iter().zip(preds).map(|(value, pred)| {
+            let mut corr = value - pred.clamp(&min, &max);
+            if corr < -(max_dif / 2) {
+                corr += max_dif;
+            } else if corr > max_correction {
+                corr -= max_dif;
+            }
+            buffer::to_symbol(corr)
+        }).collect()
+    }
+
+    fn parallelogram(next:usize, prev:usize, opposite:usize) -> [i32; 3] {
+        [0, 1, 2].map(|c| FAN[next][c] + FAN[prev][c] - FAN[opposite][c])
+    }
+
+    // The fan with its positions predicted by a mesh scheme, the predictions given in traversal order
+    fn predicted_fan(method:i8, preds:&[[i32; 3]], side_data:&[u8]) -> Vec<u8> {
+        let values: Vec<i32> = FAN_ORDER.iter().flat_map(|p| FAN[*p]).collect();
+        let preds: Vec<i32> = preds.iter().flatten().copied().collect();
+        let (min, max) = (*values.iter().min().unwrap(), *values.iter().max().unwrap());
+        let mut data = header(METHOD_EDGEBREAKER);
+        data.extend(fan(false));
+        data.extend_from_slice(&[1, 0xff, 0, 0, 1, POSITION, DT_INT32, 3, 0, 0, DECODER_INTEGER, method as u8,
+                                 TRANSFORM_WRAP as u8]);
+        data.extend(raw_bytes(&wrap_corrections(&values, &preds, min, max)));
+        data.extend_from_slice(side_data);
+        data.extend(min.to_le_bytes());
+        data.extend(max.to_le_bytes());
+        data
+    }
+
+    fn fan_positions() -> Vec<f64> {
+        FAN.iter().flatten().map(|x| *x as f64).collect()
+    }
+
+    #[test]
+    fn decodes_a_fan_in_the_standard_and_valence_traversals() {
+        for valence in [false, true] {
+            let mut data = header(METHOD_EDGEBREAKER);
+            data.extend(fan(valence));
+            // Integer positions without prediction, in traversal order
+            data.extend_from_slice(&[1, 0xff, 0, 0, 1, POSITION, DT_INT32, 3, 0, 0, DECODER_INTEGER, PREDICTION_NONE as u8]);
+            let symbols: Vec<u32> = FAN_ORDER.iter().flat_map(|p| FAN[*p]).map(buffer::to_symbol).collect();
+            data.extend(raw_bytes(&symbols));
+
+            let mesh = decode(&data).unwrap();
+            assert_eq!(mesh.faces, FAN_FACES, "valence {}", valence);
+            assert_eq!(mesh.num_points, 7);
+            assert_eq!(mesh.attribute(0).unwrap().point_values(), fan_positions(), "valence {}", valence);
+        }
+    }
+
+    #[test]
+    fn decodes_parallelogram_predictions() {
+        // The first points have no decoded triangle across their edge and take the previous value, the others are
+        // predicted from the triangle before them around the center
+        let preds = [[0; 3], FAN[1], FAN[2], parallelogram(0, 1, 2), parallelogram(6, 1, 0), parallelogram(5, 1, 6),
+                     parallelogram(4, 1, 5)];
+        let mesh = decode(&predicted_fan(MESH_PREDICTION_PARALLELOGRAM, &preds, &[])).unwrap();
+        assert_eq!(mesh.faces, FAN_FACES);
+        assert_eq!(mesh.attribute(0).unwrap().point_values(), fan_positions());
+    }
+
+    #[test]
+    fn decodes_multi_parallelogram_predictions() {
+        // The last point closes the fan and is also across the edge 1-2 of the first triangle, the two
+        // predictions are averaged
+        let (a, b) = (parallelogram(4, 1, 5), parallelogram(1, 2, 0));
+        let preds = [[0; 3], FAN[1], FAN[2], parallelogram(0, 1, 2), parallelogram(6, 1, 0), parallelogram(5, 1, 6),
+                     [0, 1, 2].map(|c| (a[c] + b[c]) / 2)];
+        let mesh = decode(&predicted_fan(MESH_PREDICTION_MULTI_PARALLELOGRAM, &preds, &[])).unwrap();
+        assert_eq!(mesh.attribute(0).unwrap().point_values(), fan_positions());
+    }
+
+    #[test]
+    fn decodes_constrained_multi_parallelogram_predictions() {
+        // Crease flags by number of parallelograms: the second of the points with one marks a crease and falls
+        // back on the previous value, the last point keeps the first of its two
+        let mut side_data = vec![3];
+        side_data.extend(ans_bits(128, &[false, true, false]));
+        side_data.push(2);
+        side_data.extend(ans_bits(128, &[false, true]));
+        side_data.extend_from_slice(&[0, 0]);
+        let preds = [[0; 3], FAN[1], FAN[2], parallelogram(0, 1, 2), FAN[6], parallelogram(5, 1, 6),
+                     parallelogram(4, 1, 5)];
+        let mesh = decode(&predicted_fan(MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM, &preds, &side_data)).unwrap();
+        assert_eq!(mesh.attribute(0).unwrap().point_values(), fan_positions());
+    }
+
+    // The fan flattened on z = 0 with its integer positions, followed by the descriptor of a second attribute
+    fn flat_fan(descriptor:[u8; 5], decoder_type:u8) -> Vec<u8> {
+        let mut data = header(METHOD_EDGEBREAKER);
+        data.extend(fan(false));
+        data.extend_from_slice(&[1, 0xff, 0, 0, 2, POSITION, DT_INT32, 3, 0, 0]);
+        data.extend_from_slice(&descriptor);
+        data.extend_from_slice(&[DECODER_INTEGER, decoder_type, PREDICTION_NONE as u8]);
+        let symbols: Vec<u32> = FAN_ORDER.iter().flat_map(|p| [FAN[*p][0], FAN[*p][1], 0]).map(buffer::to_symbol).collect();
+        data.extend(raw_bytes(&symbols));
+        data
+    }
+
+    #[test]
+    fn decodes_portable_texture_coordinate_predictions() {
+        // Texture coordinates follow the flat positions, but for point 3 mirrored over the edge 1-4 it shares with
+        // the last triangle. The first point has no prediction and the second takes the first value, the others
+        // are predicted exactly from the two points of their triangle decoded before them.
+        let uv: Vec<[i32; 2]> = (0..7).map(|p| if p == 3 { [0, 2] } else { [FAN[p][0] + 2, FAN[p][1] + 2] }).collect();
+        let values: Vec<i32> = FAN_ORDER.iter().flat_map(|p| uv[*p]).collect();
+        let mut preds = vec![0, 0];
+        preds.extend(uv[1]);
+        preds.extend(values[4..].iter().copied());
+        let mut data = flat_fan([TEX_COORD, DT_INT32, 2, 0, 1], DECODER_INTEGER);
+        data.extend_from_slice(&[MESH_PREDICTION_TEX_COORDS_PORTABLE as u8, TRANSFORM_WRAP as u8]);
+        data.extend(raw_bytes(&wrap_corrections(&values, &preds, 0, 4)));
+        // The orientations are taken from the last, a 0 bit flips the previous one starting from true: point 3 is
+        // the only one whose prediction needs the other side of its edge
+        data.extend(5i32.to_le_bytes());
+        data.extend(ans_bits(128, &[false, false, true, true, true]));
+        data.extend(0i32.to_le_bytes());
+        data.extend(4i32.to_le_bytes());
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.attribute(1).unwrap().point_values(),
+                   uv.iter().flatten().map(|x| *x as f64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn decodes_geometric_normal_predictions() {
+        // The fan winds clockwise seen from +z, so every predicted normal is -z until its flip bit turns it
+        // over. Rotated into the bottom left quadrant, either pole sits at (-7, 0) from the center of the 4 bit
+        // octahedron, a correction of 7 in s moves it to the center, +x, and adding 7 in t turns +z into +y.
+        let flips = [true, false, false, true, true, true, false];
+        let corrections: [[u32; 2]; 7] = [[0, 0], [0, 0], [7, 0], [0, 0], [7, 7], [0, 0], [0, 0]];
+        let mut data = flat_fan([NORMAL, attributes::DT_FLOAT32, 3, 0, 1], DECODER_NORMALS);
+        data.extend_from_slice(&[MESH_PREDICTION_GEOMETRIC_NORMAL as u8, TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED as u8]);
+        data.extend(raw_bytes(&corrections.concat()));
+        data.extend(15i32.to_le_bytes());
+        data.extend(7i32.to_le_bytes());
+        data.extend(ans_bits(128, &flips));
+        data.push(4);
+
+        let mesh = decode(&data).unwrap();
+        let mut expected = vec![0.0; 21];
+        for (entry, point) in FAN_ORDER.iter().enumerate() {
+            let normal = match (corrections[entry], flips[entry]) {
+                ([7, 0], _) => [1.0, 0.0, 0.0],
+                ([7, 7], true) => [0.0, 1.0, 0.0],
+                (_, true) => [0.0, 0.0, 1.0],
+                (_, false) => [0.0, 0.0, -1.0],
+            };
+            expected[point * 3..point * 3 + 3].copy_from_slice(&normal);
+        }
+        assert_eq!(mesh.attribute(1).unwrap().point_values(), expected);
+    }
+
+    #[test]
+    fn decodes_octahedral_normals() {
+        // Differences of 4 bit octahedral coordinates, from (0, 0) for the first one: +x is the center (7, 7),
+        // +y (14, 7), -x the corner (14, 14), -z (7, 0) and -y (0, 7). Predictions out of the central diamond
+        // are reflected into it with the value, and negative corrections are made positive modulo 15.
+        let mut data = header(METHOD_SEQUENTIAL);
+        data.extend_from_slice(&[2, 5, 1, 0, 1, 2, 2, 3, 4]);
+        data.extend_from_slice(&[1, 1, NORMAL, attributes::DT_FLOAT32, 3, 0, 0, DECODER_NORMALS]);
+        data.extend_from_slice(&[PREDICTION_DIFFERENCE as u8, TRANSFORM_NORMAL_OCTAHEDRON as u8]);
+        data.extend(raw_bytes(&[7, 7, 7, 0, 0, 7, 0, 8, 8, 7]));
+        data.extend(15i32.to_le_bytes());
+        data.push(4);
+
+        let mesh = decode(&data).unwrap();
+        assert_eq!(mesh.attribute(0).unwrap().point_values(),
+                   vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn truncated_streams_are_errors() {
+        let preds = [[0; 3]; 7];
+        let data = predicted_fan(MESH_PREDICTION_PARALLELOGRAM, &preds, &[]);
+        assert!(decode(&data).is_ok());
+        for length in 0..data.len() {
+            assert!(decode(&data[..length]).is_err(), "{} of {} bytes", length, data.len());
+        }
+    }
+}
@@ -0,0 +1,638 @@
+// Prediction schemes and their correction transforms for integer attribute values
+use super::buffer::DecoderBuffer;
+use super::corner_table::{CornerTable, INVALID};
+use super::edgebreaker::corners_around;
+use super::rans::RAnsBitDecoder;
+
+pub const PREDICTION_NONE: i8 = -2;
+pub const PREDICTION_DIFFERENCE: i8 = 0;
+pub const MESH_PREDICTION_PARALLELOGRAM: i8 = 1;
+pub const MESH_PREDICTION_MULTI_PARALLELOGRAM: i8 = 2;
+pub const MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM: i8 = 4;
+pub const MESH_PREDICTION_TEX_COORDS_PORTABLE: i8 = 5;
+pub const MESH_PREDICTION_GEOMETRIC_NORMAL: i8 = 6;
+
+pub const TRANSFORM_WRAP: i8 = 1;
+pub const TRANSFORM_NORMAL_OCTAHEDRON: i8 = 2;
+pub const TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED: i8 = 3;
+
+const MAX_PARALLELOGRAMS: usize = 4;
+
+// Quantized octahedral coordinates of unit vectors
+#[derive(Clone, Copy, Default)]
+pub struct Octahedron {
+    max_quantized_value: i32,
+    max_value: i32,
+    center_value: i32,
+}
+
+impl Octahedron {
+    pub fn new(quantization_bits:u32) -> Result<Self, String> {
+        if !(2..=30).contains(&quantization_bits) {
+            return Err(format!("Invalid Draco octahedral quantization {}", quantization_bits));
+        }
+        let max_quantized_value = (1 << quantization_bits) - 1;
+        Ok(Octahedron {
+            max_quantized_value,
+            max_value: max_quantized_value - 1,
+            center_value: (max_quantized_value - 1) / 2,
+        })
+    }
+
+    fn from_max_quantized_value(max_quantized_value:i32) -> Result<Self, String> {
+        if max_quantized_value <= 0 || max_quantized_value % 2 == 0 {
+            return Err(format!("Invalid Draco octahedral range {}", max_quantized_value));
+        }
+        Octahedron::new(32 - (max_quantized_value as u32).leading_zeros())
+    }
+
+    pub fn unit_vector(&self, s:i32, t:i32) -> [f32; 3] {
+        let scale = 2.0 / self.max_value as f32;
+        let mut y = s as f32 * scale - 1.0;
+        let mut z = t as f32 * scale - 1.0;
+        let x = 1.0 - y.abs() - z.abs();
+        let x_offset = (-x).max(0.0);
+        y += if y < 0.0 { x_offset } else { -x_offset };
+        z += if z < 0.0 { x_offset } else { -x_offset };
+        let norm_squared = x * x + y * y + z * z;
+        if norm_squared < 1e-6 {
+            [0.0, 0.0, 0.0]
+        } else {
+            let d = 1.0 / norm_squared.sqrt();
+            [x * d, y * d, z * d]
+        }
+    }
+
+    pub fn octahedral_coords(&self, vector:[f32; 3]) -> (i32, i32) {
+        let abs_sum = vector.iter().map(|x| (*x as f64).abs()).sum::<f64>();
+        let scaled = if abs_sum > 1e-6 {
+            [vector[0] as f64 / abs_sum, vector[1] as f64 / abs_sum, vector[2] as f64 / abs_sum]
+        } else {
+            [1.0, 0.0, 0.0]
+        };
+        let center = self.center_value;
+        let mut int_vec = [(scaled[0] * center as f64 + 0.5).floor() as i32,
+                            (scaled[1] * center as f64 + 0.5).floor() as i32, 0];
+        int_vec[2] = center - int_vec[0].abs() - int_vec[1].abs();
+        if int_vec[2] < 0 {
+            if int_vec[1] > 0 {
+                int_vec[1] += int_vec[2];
+            } else {
+                int_vec[1] -= int_vec[2];
+            }
+            int_vec[2] = 0;
+        }
+        if scaled[2] < 0.0 {
+            int_vec[2] = -int_vec[2];
+        }
+        self.integer_vector_to_coords(int_vec)
+    }
+
+    fn integer_vector_to_coords(&self, v:[i32; 3]) -> (i32, i32) {
+        let (mut s, mut t) = if v[0] >= 0 {
+            (v[1] + self.center_value, v[2] + self.center_value)
+        } else {
+            (if v[1] < 0 { v[2].abs() } else { self.max_value - v[2].abs() },
+                if v[2] < 0 { v[1].abs() } else { self.max_value - v[1].abs() })
+        };
+        // Canonical form of the points shared by several octahedron faces
+        let (max, center) = (self.max_value, self.center_value);
+        if (s == 0 && (t == 0 || t == max)) || (s == max && t == 0) {
+            s = max;
+            t = max;
+        } else if s == 0 && t > center {
+            t = center - (t - center);
+        } else if s == max && t < center {
+            t = center + (center - t);
+        } else if t == max && s < center {
+            s = center + (center - s);
+        } else if t == 0 && s > center {
+            s = center - (s - center);
+        }
+        (s, t)
+    }
+
+    fn canonicalize_vector(&self, v:&mut [i64; 3]) {
+        let abs_sum = v[0].abs() + v[1].abs() + v[2].abs();
+        let center = self.center_value as i64;
+        if abs_sum == 0 {
+            v[0] = center;
+        } else {
+            v[0] = v[0] * center / abs_sum;
+            v[1] = v[1] * center / abs_sum;
+            let rest = center - v[0].abs() - v[1].abs();
+            v[2] = if v[2] >= 0 { rest } else { -rest };
+        }
+    }
+
+    fn is_in_diamond(&self, s:i32, t:i32) -> bool {
+        s.abs() + t.abs() <= self.center_value
+    }
+
+    fn invert_diamond(&self, s:&mut i32, t:&mut i32) {
+        let (sign_s, sign_t) = if *s >= 0 && *t >= 0 {
+            (1, 1)
+        } else if *s <= 0 && *t <= 0 {
+            (-1, -1)
+        } else {
+            (if *s > 0 { 1 } else { -1 }, if *t > 0 { 1 } else { -1 })
+        };
+        let corner_s = (sign_s * self.center_value) as u32;
+        let corner_t = (sign_t * self.center_value) as u32;
+        let mut us = (*s as u32).wrapping_add(*s as u32).wrapping_sub(corner_s);
+        let mut ut = (*t as u32).wrapping_add(*t as u32).wrapping_sub(corner_t);
+        if sign_s * sign_t >= 0 {
+            let temp = us;
+            us = ut.wrapping_neg();
+            ut = temp.wrapping_neg();
+        } else {
+            std::mem::swap(&mut us, &mut ut);
+        }
+        *s = (us.wrapping_add(corner_s) as i32) / 2;
+        *t = (ut.wrapping_add(corner_t) as i32) / 2;
+    }
+
+    fn mod_max(&self, x:i32) -> i32 {
+        if x > self.center_value {
+            x - self.max_quantized_value
+        } else if x < -self.center_value {
+            x + self.max_quantized_value
+        } else {
+            x
+        }
+    }
+}
+
+pub enum Transform {
+    Wrap { min: i32, max: i32, max_dif: i32 },
+    Octahedron(Octahedron),
+    Canonicalized(Octahedron),
+}
+
+impl Transform {
+    pub fn decode(transform_type:i8, buffer:&mut DecoderBuffer) -> Result<Self, String> {
+        match transform_type {
+            TRANSFORM_NORMAL_OCTAHEDRON => {
+                let max_quantized_value = buffer.i32()?;
+                Ok(Transform::Octahedron(Octahedron::from_max_quantized_value(max_quantized_value)?))
+            }
+            TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED => {
+                let max_quantized_value = buffer.i32()?;
+                let _center_value = buffer.i32()?;
+                Ok(Transform::Canonicalized(Octahedron::from_max_quantized_value(max_quantized_value)?))
+            }
+            _ => {
+                let min = buffer.i32()?;
+                let max = buffer.i32()?;
+                let dif = max as i64 - min as i64;
+                if dif < 0 || dif >= i32::MAX as i64 {
+                    return Err(String::from("Invalid Draco wrap transform"));
+                }
+                Ok(Transform::Wrap { min, max, max_dif: 1 + dif as i32 })
+            }
+        }
+    }
+
+    pub fn corrections_positive(transform_type:i8) -> bool {
+        transform_type == TRANSFORM_NORMAL_OCTAHEDRON || transform_type == TRANSFORM_NORMAL_OCTAHEDRON_CANONICALIZED
+    }
+
+    fn octahedron(&self) -> Option<Octahedron> {
+        match self {
+            Transform::Octahedron(o) | Transform::Canonicalized(o) => Some(*o),
+            Transform::Wrap { .. } => None,
+        }
+    }
+
+    fn original(&self, pred:&[i32], corr:&[i32], out:&mut [i32]) {
+        match self {
+            Transform::Wrap { min, max, max_dif } => {
+                for i in 0..out.len() {
+                    let p = pred[i].clamp(*min, *max);
+                    let mut value = p.wrapping_add(corr[i]);
+                    if value > *max {
+                        value = value.wrapping_sub(*max_dif);
+                    } else if value < *min {
+                        value = value.wrapping_add(*max_dif);
+                    }
+                    out[i] = value;
+                }
+            }
+            Transform::Octahedron(o) => {
+                let c = o.center_value;
+                let (mut s, mut t) = (pred[0] - c, pred[1] - c);
+                let in_diamond = o.is_in_diamond(s, t);
+                if !in_diamond {
+                    o.invert_diamond(&mut s, &mut t);
+                }
+                let mut os = o.mod_max(s.wrapping_add(corr[0]));
+                let mut ot = o.mod_max(t.wrapping_add(corr[1]));
+                if !in_diamond {
+                    o.invert_diamond(&mut os, &mut ot);
+                }
+                out[0] = os + c;
+                out[1] = ot + c;
+            }
+            Transform::Canonicalized(o) => {
+                let c = o.center_value;
+                let (mut s, mut t) = (pred[0] - c, pred[1] - c);
+                let in_diamond = o.is_in_diamond(s, t);
+                if !in_diamond {
+                    o.invert_diamond(&mut s, &mut t);
+                }
+                let in_bottom_left = (s == 0 && t == 0) || (s < 0 && t <= 0);
+                let rotation = if s == 0 {
+                    if t == 0 { 0 } else if t > 0 { 3 } else { 1 }
+                } else if s > 0 {
+                    if t >= 0 { 2 } else { 1 }
+                } else if t <= 0 { 0 } else { 3 };
+                if !in_bottom_left {
+                    (s, t) = rotate(s, t, rotation);
+                }
+                let (mut os, mut ot) = (o.mod_max(s.wrapping_add(corr[0])), o.mod_max(t.wrapping_add(corr[1])));
+                if !in_bottom_left {
+                    (os, ot) = rotate(os, ot, (4 - rotation) % 4);
+                }
+                if !in_diamond {
+                    o.invert_diamond(&mut os, &mut ot);
+                }
+                out[0] = os + c;
+                out[1] = ot + c;
+            }
+        }
+    }
+}
+
+fn rotate(s:i32, t:i32, rotation:i32) -> (i32, i32) {
+    match rotation {
+        1 => (t, -s),
+        2 => (-s, -t),
+        3 => (-t, s),
+        _ => (s, t),
+    }
+}
+
+// Connectivity seen by a mesh prediction scheme
+pub struct MeshData<'a> {
+    pub table: &'a CornerTable,
+    pub data_to_corner: &'a [u32],
+    pub vertex_to_data: &'a [i32],
+}
+
+impl<'a> MeshData<'a> {
+    fn data_of(&self, c:u32) -> i64 {
+        match self.table.vertex(c) {
+            INVALID => i64::MAX,
+            v => self.vertex_to_data[v as usize] as i64,
+        }
+    }
+}
+
+// Portable position values the normal and texture coordinate predictions rely on
+pub struct Positions<'a> {
+    pub values: &'a [i32],
+    pub point_to_value: &'a [u32],
+}
+
+impl<'a> Positions<'a> {
+    fn at_entry(&self, point_ids:&[u32], entry:usize) -> [i64; 3] {
+        let v = self.point_to_value[point_ids[entry] as usize] as usize * 3;
+        [self.values[v] as i64, self.values[v+1] as i64, self.values[v+2] as i64]
+    }
+}
+
+pub enum Scheme {
+    Difference,
+    Parallelogram,
+    MultiParallelogram,
+    ConstrainedMultiParallelogram(Vec<Vec<bool>>),
+    TexCoordsPortable(Vec<bool>),
+    GeometricNormal,
+}
+
+// Reads the side data of a scheme, and the transform that goes with it
+pub fn decode_prediction_data<'a>(method:i8, transform_type:i8, has_mesh:bool, num_corners:usize,
+                                buffer:&mut DecoderBuffer<'a>) -> Result<(Scheme, Transform, Option<RAnsBitDecoder<'a>>), String> {
+    let scheme = match method {
+        _ if !has_mesh => Scheme::Difference,
+        PREDICTION_DIFFERENCE => Scheme::Difference,
+        MESH_PREDICTION_PARALLELOGRAM => Scheme::Parallelogram,
+        MESH_PREDICTION_MULTI_PARALLELOGRAM => Scheme::MultiParallelogram,
+        MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM => {
+            let mut creases = Vec::with_capacity(MAX_PARALLELOGRAMS);
+            for _ in 0..MAX_PARALLELOGRAMS {
+                let num_flags = buffer.varint32()? as usize;
+                if num_flags > num_corners {
+                    return Err(String::from("Invalid Draco crease flags"));
+                }
+                let mut flags = Vec::with_capacity(num_flags);
+                if num_flags > 0 {
+                    let mut decoder = RAnsBitDecoder::start(buffer)?;
+                    for _ in 0..num_flags {
+                        flags.push(decoder.bit());
+                    }
+                }
+                creases.push(flags);
+            }
+            Scheme::ConstrainedMultiParallelogram(creases)
+        }
+        MESH_PREDICTION_TEX_COORDS_PORTABLE => {
+            let num_orientations = buffer.i32()?;
+            if num_orientations < 0 {
+                return Err(String::from("Invalid Draco texture coordinate orientations"));
+            }
+            let mut orientations = Vec::with_capacity(num_orientations as usize);
+            let mut last = true;
+            let mut decoder = RAnsBitDecoder::start(buffer)?;
+            for _ in 0..num_orientations {
+                if !decoder.bit() {
+                    last = !last;
+                }
+                orientations.push(last);
+            }
+            Scheme::TexCoordsPortable(orientations)
+        }
+        MESH_PREDICTION_GEOMETRIC_NORMAL => {
+            let transform = Transform::decode(transform_type, buffer)?;
+            let flips = RAnsBitDecoder::start(buffer)?;
+            return Ok((Scheme::GeometricNormal, transform, Some(flips)));
+        }
+        _ => return Err(format!("Unsupported Draco prediction scheme {}", method)),
+    };
+    let transform = Transform::decode(transform_type, buffer)?;
+    Ok((scheme, transform, None))
+}
+
+// Mesh prediction schemes fall back to the difference scheme without edgebreaker connectivity
+pub fn is_mesh_method(method:i8) -> bool {
+    matches!(method, MESH_PREDICTION_PARALLELOGRAM | MESH_PREDICTION_MULTI_PARALLELOGRAM |
+                    MESH_PREDICTION_CONSTRAINED_MULTI_PARALLELOGRAM | MESH_PREDICTION_TEX_COORDS_PORTABLE |
+                    MESH_PREDICTION_GEOMETRIC_NORMAL)
+}
+
+// Undoes the prediction, corrections are given in entry order
+#[allow(clippy::too_many_arguments)]
+pub fn compute_original_values(scheme:&mut Scheme, transform:&Transform, flips:Option<&mut RAnsBitDecoder>,
+                                corr:&[i32], n:usize, mesh:Option<&MeshData>, point_ids:&[u32],
+                                positions:Option<&Positions>) -> Result<Vec<i32>, String> {
+    let mut out = vec![0i32; corr.len()];
+    if corr.is_empty() {
+        return Ok(out);
+    }
+    let zero = vec![0i32; n];
+    let mesh = match mesh {
+        Some(mesh) if !matches!(scheme, Scheme::Difference) => mesh,
+        _ => {
+            transform.original(&zero, &corr[..n], &mut out[..n]);
+            for i in (n..corr.len()).step_by(n) {
+                let (done, rest) = out.split_at_mut(i);
+                transform.original(&done[i-n..], &corr[i..i+n], &mut rest[..n]);
+            }
+            return Ok(out);
+        }
+    };
+    let entries = mesh.data_to_corner.len();
+    if entries * n != corr.len() {
+        return Err(String::from("Draco prediction does not match the attribute size"));
+    }
+    let table = mesh.table;
+    match scheme {
+        Scheme::Difference => unreachable!(),
+        Scheme::Parallelogram => {
+            transform.original(&zero, &corr[..n], &mut out[..n]);
+            let mut pred = vec![0i32; n];
+            for p in 1..entries {
+                let corner = mesh.data_to_corner[p];
+                let (done, rest) = out.split_at_mut(p * n);
+                if parallelogram(mesh, p, corner, done, n, &mut pred) {
+                    transform.original(&pred, &corr[p*n..(p+1)*n], &mut rest[..n]);
+                } else {
+                    transform.original(&done[(p-1)*n..], &corr[p*n..(p+1)*n], &mut rest[..n]);
+                }
+            }
+        }
+        Scheme::MultiParallelogram => {
+            transform.original(&zero, &corr[..n], &mut out[..n]);
+            let mut pred = vec![0i32; n];
+            let mut sum = vec![0i32; n];
+            for p in 1..entries {
+                let start = mesh.data_to_corner[p];
+                let (done, rest) = out.split_at_mut(p * n);
+                sum.iter_mut().for_each(|x| *x = 0);
+                let mut count = 0;
+                let mut corner = start;
+                while corner != INVALID {
+                    if parallelogram(mesh, p, corner, done, n, &mut pred) {
+                        for c in 0..n {
+                            sum[c] = sum[c].wrapping_add(pred[c]);
+                        }
+                        count += 1;
+                    }
+                    corner = table.swing_right(corner);
+                    if corner == start {
+                        corner = INVALID;
+                    }
+                }
+                if count == 0 {
+                    transform.original(&done[(p-1)*n..], &corr[p*n..(p+1)*n], &mut rest[..n]);
+                } else {
+                    sum.iter_mut().for_each(|x| *x /= count);
+                    transform.original(&sum, &corr[p*n..(p+1)*n], &mut rest[..n]);
+                }
+            }
+        }
+        Scheme::ConstrainedMultiParallelogram(creases) => {
+            transform.original(&zero, &corr[..n], &mut out[..n]);
+            let mut preds = vec![vec![0i32; n]; MAX_PARALLELOGRAMS];
+            let mut crease_pos = [0usize; MAX_PARALLELOGRAMS];
+            let mut sum = vec![0i32; n];
+            for p in 1..entries {
+                let start = mesh.data_to_corner[p];
+                let (done, rest) = out.split_at_mut(p * n);
+                let mut num = 0;
+                let mut corner = start;
+                let mut first_pass = true;
+                while corner != INVALID {
+                    if parallelogram(mesh, p, corner, done, n, &mut preds[num]) {
+                        num += 1;
+                        if num == MAX_PARALLELOGRAMS {
+                            break;
+                        }
+                    }
+                    corner = if first_pass { table.swing_left(corner) } else { table.swing_right(corner) };
+                    if corner == start {
+                        break;
+                    }
+                    if corner == INVALID && first_pass {
+                        first_pass = false;
+                        corner = table.swing_right(start);
+                    }
+                }
+                let mut used = 0;
+                sum.iter_mut().for_each(|x| *x = 0);
+                for pred in preds.iter().take(num) {
+                    let context = num - 1;
+                    let flag = *creases[context].get(crease_pos[context])
+                        .ok_or("Draco crease flags ran out")?;
+                    crease_pos[context] += 1;
+                    if !flag {
+                        used += 1;
+                        for c in 0..n {
+                            sum[c] = sum[c].wrapping_add(pred[c]);
+                        }
+                    }
+                }
+                if used == 0 {
+                    transform.original(&done[(p-1)*n..], &corr[p*n..(p+1)*n], &mut rest[..n]);
+                } else {
+                    sum.iter_mut().for_each(|x| *x /= used);
+                    transform.original(&sum, &corr[p*n..(p+1)*n], &mut rest[..n]);
+                }
+            }
+        }
+        Scheme::TexCoordsPortable(orientations) => {
+            let positions = positions.ok_or("Draco texture coordinate prediction needs positions")?;
+            if n != 2 {
+                return Err(String::from("Draco texture coordinate prediction needs 2 components"));
+            }
+            for p in 0..entries {
+                let corner = mesh.data_to_corner[p];
+                let pred = tex_coord_prediction(mesh, positions, point_ids, orientations, corner, &out, p)?;
+                transform.original(&pred, &corr[p*2..p*2+2], &mut out[p*2..p*2+2]);
+            }
+        }
+        Scheme::GeometricNormal => {
+            let positions = positions.ok_or("Draco normal prediction needs positions")?;
+            let octahedron = transform.octahedron().ok_or("Draco normal prediction needs an octahedron transform")?;
+            let flips = flips.ok_or("Draco normal prediction flips are missing")?;
+            for p in 0..entries {
+                let corner = mesh.data_to_corner[p];
+                let mut normal = normal_prediction(mesh, positions, point_ids, corner);
+                octahedron.canonicalize_vector(&mut normal);
+                if flips.bit() {
+                    normal = [-normal[0], -normal[1], -normal[2]];
+                }
+                let (s, t) = octahedron.integer_vector_to_coords([normal[0] as i32, normal[1] as i32, normal[2] as i32]);
+                transform.original(&[s, t], &corr[p*2..p*2+2], &mut out[p*2..p*2+2]);
+            }
+        }
+    }
+    Ok(out)
+}
+
+// Predicts the entry at the tip of corner from the triangle on the other side of its edge
+fn parallelogram(mesh:&MeshData, entry:usize, corner:u32, data:&[i32], n:usize, pred:&mut [i32]) -> bool {
+    let table = mesh.table;
+    let opp = table.opposite(corner);
+    if opp == INVALID {
+        return false;
+    }
+    let vert_opp = mesh.data_of(opp);
+    let vert_next = mesh.data_of(table.next(opp));
+    let vert_prev = mesh.data_of(table.previous(opp));
+    let entry = entry as i64;
+    if vert_opp < entry && vert_next < entry && vert_prev < entry {
+        let (o, x, y) = (vert_opp as usize * n, vert_next as usize * n, vert_prev as usize * n);
+        for c in 0..n {
+            pred[c] = (data[x+c] as i64 + data[y+c] as i64 - data[o+c] as i64) as i32;
+        }
+        return true;
+    }
+    false
+}
+
+fn int_sqrt(number:u64) -> u64 {
+    if number == 0 {
+        return 0;
+    }
+    let mut act = number;
+    let mut root = 1u64;
+    while act >= 2 {
+        root *= 2;
+        act /= 4;
+    }
+    loop {
+        root = (root + number / root) / 2;
+        if root.checked_mul(root).is_some_and(|square| square <= number) {
+            return root;
+        }
+    }
+}
+
+fn tex_coord_prediction(mesh:&MeshData, positions:&Positions, point_ids:&[u32], orientations:&mut Vec<bool>,
+                        corner:u32, data:&[i32], entry:usize) -> Result<[i32; 2], String> {
+    let table = mesh.table;
+    let next_entry = mesh.data_of(table.next(corner));
+    let prev_entry = mesh.data_of(table.previous(corner));
+    let e = entry as i64;
+    let uv = |id:i64| [data[id as usize * 2] as i64, data[id as usize * 2 + 1] as i64];
+    if prev_entry < e && next_entry < e {
+        let n_uv = uv(next_entry);
+        let p_uv = uv(prev_entry);
+        if p_uv == n_uv {
+            return Ok([p_uv[0] as i32, p_uv[1] as i32]);
+        }
+        let tip = positions.at_entry(point_ids, entry);
+        let next = positions.at_entry(point_ids, next_entry as usize);
+        let prev = positions.at_entry(point_ids, prev_entry as usize);
+        let pn = [prev[0] - next[0], prev[1] - next[1], prev[2] - next[2]];
+        let pn_norm2 = pn[0] * pn[0] + pn[1] * pn[1] + pn[2] * pn[2];
+        if pn_norm2 != 0 {
+            let cn = [tip[0] - next[0], tip[1] - next[1], tip[2] - next[2]];
+            let cn_dot_pn = pn[0] * cn[0] + pn[1] * cn[1] + pn[2] * cn[2];
+            let pn_uv = [p_uv[0] - n_uv[0], p_uv[1] - n_uv[1]];
+            let x_uv = [n_uv[0].wrapping_mul(pn_norm2).wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[0])),
+                        n_uv[1].wrapping_mul(pn_norm2).wrapping_add(cn_dot_pn.wrapping_mul(pn_uv[1]))];
+            let x_pos = [next[0] + cn_dot_pn * pn[0] / pn_norm2,
+                        next[1] + cn_dot_pn * pn[1] / pn_norm2,
+                        next[2] + cn_dot_pn * pn[2] / pn_norm2];
+            let cx = [tip[0] - x_pos[0], tip[1] - x_pos[1], tip[2] - x_pos[2]];
+            let cx_norm2 = (cx[0] * cx[0] + cx[1] * cx[1] + cx[2] * cx[2]) as u64;
+            let norm = int_sqrt(cx_norm2.wrapping_mul(pn_norm2 as u64)) as i64;
+            let cx_uv = [pn_uv[1].wrapping_mul(norm), (-pn_uv[0]).wrapping_mul(norm)];
+            let orientation = orientations.pop().ok_or("Draco texture coordinate orientations ran out")?;
+            let predicted = if orientation {
+                [(x_uv[0] as u64).wrapping_add(cx_uv[0] as u64) as i64 / pn_norm2,
+                    (x_uv[1] as u64).wrapping_add(cx_uv[1] as u64) as i64 / pn_norm2]
+            } else {
+                [(x_uv[0] as u64).wrapping_sub(cx_uv[0] as u64) as i64 / pn_norm2,
+                    (x_uv[1] as u64).wrapping_sub(cx_uv[1] as u64) as i64 / pn_norm2]
+            };
+            return Ok([predicted[0] as i32, predicted[1] as i32]);
+        }
+    }
+    // Not enough neighbours, fall back to delta coding
+    let source = if next_entry < e {
+        next_entry
+    } else if entry > 0 {
+        e - 1
+    } else {
+        return Ok([0, 0]);
+    };
+    Ok([data[source as usize * 2], data[source as usize * 2 + 1]])
+}
+
+// Area weighted normal of the faces around the corner's vertex
+fn normal_prediction(mesh:&MeshData, positions:&Positions, point_ids:&[u32], corner:u32) -> [i64; 3] {
+    let table = mesh.table;
+    let position = |c:u32| positions.at_entry(point_ids, mesh.data_of(c) as usize);
+    let center = position(corner);
+    let mut normal = [0i64; 3];
+    for c in corners_around(table, corner) {
+        let next = position(table.next(c));
+        let prev = position(table.previous(c));
+        let a = [next[0] - center[0], next[1] - center[1], next[2] - center[2]];
+        let b = [prev[0] - center[0], prev[1] - center[1], prev[2] - center[2]];
+        let cross = [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+        for k in 0..3 {
+            normal[k] = normal[k].wrapping_add(cross[k]);
+        }
+    }
+    let upper_bound = 1i64 << 29;
+    let abs_sum = normal[0].abs() + normal[1].abs() + normal[2].abs();
+    if abs_sum > upper_bound {
+        let quotient = abs_sum / upper_bound;
+        normal = [normal[0] / quotient, normal[1] / quotient, normal[2] / quotient];
+    }
+    normal
+}
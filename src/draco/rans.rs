@@ -0,0 +1,324 @@
+// Asymmetric numeral system coders used for Draco symbols and connectivity bits
+use super::buffer::{DecoderBuffer, EncoderBuffer};
+
+const ANS_L_BASE: u32 = 4096;
+const ANS_IO_BASE: u32 = 256;
+const ANS_P8_PRECISION: u32 = 256;
+
+const SYMBOL_CODING_TAGGED: u8 = 0;
+const SYMBOL_CODING_RAW: u8 = 1;
+const MAX_RAW_BIT_LENGTH: u32 = 18;
+
+// Plain bit reader, least significant bit first
+pub struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(data:&'a [u8]) -> Self {
+        BitReader {
+            data,
+            bit: 0,
+        }
+    }
+
+    pub fn bits(&mut self, n:u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..n {
+            let byte = *self.data.get(self.bit >> 3).ok_or("Draco bit stream ended early")?;
+            value |= (((byte >> (self.bit & 7)) & 1) as u32) << i;
+            self.bit += 1;
+        }
+        Ok(value)
+    }
+
+    pub fn bytes_read(&self) -> usize {
+        self.bit.div_ceil(8)
+    }
+}
+
+// Reads the trailing state of an ans stream, the stream itself is consumed backwards
+fn read_init(buf:&[u8], l_base:u32, allow_32:bool) -> Result<(usize, u32), String> {
+    let n = buf.len();
+    if n < 1 {
+        return Err(String::from("Empty Draco ans stream"));
+    }
+    let (offset, state) = match buf[n-1] >> 6 {
+        0 => (n - 1, (buf[n-1] & 0x3f) as u32),
+        1 if n >= 2 => (n - 2, u16::from_le_bytes([buf[n-2], buf[n-1]]) as u32 & 0x3fff),
+        2 if n >= 3 => (n - 3, u32::from_le_bytes([buf[n-3], buf[n-2], buf[n-1], 0]) & 0x3f_ffff),
+        3 if n >= 4 && allow_32 => (n - 4, u32::from_le_bytes([buf[n-4], buf[n-3], buf[n-2], buf[n-1]]) & 0x3fff_ffff),
+        _ => return Err(String::from("Invalid Draco ans stream")),
+    };
+    let state = state + l_base;
+    if state as u64 >= l_base as u64 * ANS_IO_BASE as u64 {
+        return Err(String::from("Invalid Draco ans state"));
+    }
+    Ok((offset, state))
+}
+
+// Binary decoder with a fixed probability of zero
+pub struct RAnsBitDecoder<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    state: u32,
+    prob_zero: u8,
+}
+
+impl<'a> RAnsBitDecoder<'a> {
+    pub fn start(buffer:&mut DecoderBuffer<'a>) -> Result<Self, String> {
+        let prob_zero = buffer.u8()?;
+        let size = buffer.varint32()? as usize;
+        let buf = buffer.bytes(size)?;
+        let (offset, state) = read_init(buf, ANS_L_BASE, false)?;
+        Ok(RAnsBitDecoder {
+            buf,
+            offset,
+            state,
+            prob_zero,
+        })
+    }
+
+    pub fn bit(&mut self) -> bool {
+        let p = ANS_P8_PRECISION - self.prob_zero as u32;
+        if self.state < ANS_L_BASE && self.offset > 0 {
+            self.offset -= 1;
+            self.state = self.state * ANS_IO_BASE + self.buf[self.offset] as u32;
+        }
+        let x = self.state;
+        let quot = x / ANS_P8_PRECISION;
+        let rem = x % ANS_P8_PRECISION;
+        let xn = quot * p;
+        if rem < p {
+            self.state = xn + rem;
+            true
+        } else {
+            self.state = x - xn - p;
+            false
+        }
+    }
+}
+
+// Bits of precision used by the symbol coder for a given bit length of the alphabet
+fn precision_bits(symbols_bit_length:u32) -> u32 {
+    ((3 * symbols_bit_length) / 2).clamp(12, 20)
+}
+
+struct RAnsSymbolDecoder<'a> {
+    buf: &'a [u8],
+    offset: usize,
+    state: u32,
+    precision: u32,
+    probs: Vec<u32>,
+    cum_probs: Vec<u32>,
+    lut: Vec<u32>,
+}
+
+impl<'a> RAnsSymbolDecoder<'a> {
+    fn create(buffer:&mut DecoderBuffer<'a>, symbols_bit_length:u32) -> Result<Self, String> {
+        let precision = 1 << precision_bits(symbols_bit_length);
+        let num_symbols = buffer.varint32()? as usize;
+        let mut probs = vec![0u32; num_symbols];
+        let mut i = 0;
+        while i < num_symbols {
+            let prob_data = buffer.u8()?;
+            let token = prob_data & 3;
+            if token == 3 {
+                let offset = (prob_data >> 2) as usize;
+                if i + offset >= num_symbols {
+                    return Err(String::from("Invalid Draco probability table"));
+                }
+                i += offset + 1;
+            } else {
+                let mut prob = (prob_data >> 2) as u32;
+                for b in 0..token as u32 {
+                    prob |= (buffer.u8()? as u32) << (8 * (b + 1) - 2);
+                }
+                probs[i] = prob;
+                i += 1;
+            }
+        }
+        let mut cum_probs = Vec::with_capacity(num_symbols);
+        let mut lut = Vec::new();
+        let mut cum = 0;
+        for (s, prob) in probs.iter().enumerate() {
+            cum_probs.push(cum);
+            cum += prob;
+            if cum > precision {
+                return Err(String::from("Draco probability table overflows"));
+            }
+            lut.resize(cum as usize, s as u32);
+        }
+        if num_symbols > 0 && cum != precision {
+            return Err(String::from("Draco probability table does not sum up"));
+        }
+        Ok(RAnsSymbolDecoder {
+            buf: &[],
+            offset: 0,
+            state: 0,
+            precision,
+            probs,
+            cum_probs,
+            lut,
+        })
+    }
+
+    fn start(&mut self, buffer:&mut DecoderBuffer<'a>) -> Result<(), String> {
+        let size = buffer.varint()? as usize;
+        self.buf = buffer.bytes(size)?;
+        let (offset, state) = read_init(self.buf, self.precision * 4, true)?;
+        self.offset = offset;
+        self.state = state;
+        Ok(())
+    }
+
+    fn symbol(&mut self) -> u32 {
+        let l_base = self.precision * 4;
+        while self.state < l_base && self.offset > 0 {
+            self.offset -= 1;
+            self.state = self.state * ANS_IO_BASE + self.buf[self.offset] as u32;
+        }
+        let quo = self.state / self.precision;
+        let rem = self.state % self.precision;
+        let s = self.lut[rem as usize] as usize;
+        self.state = quo * self.probs[s] + rem - self.cum_probs[s];
+        s as u32
+    }
+}
+
+// Entropy coded list of unsigned values, either tagged by bit length or coded directly
+pub fn decode_symbols(buffer:&mut DecoderBuffer, num_values:usize, num_components:usize) -> Result<Vec<u32>, String> {
+    let mut values = Vec::with_capacity(num_values);
+    if num_values == 0 {
+        return Ok(values);
+    }
+    match buffer.u8()? {
+        SYMBOL_CODING_TAGGED => {
+            let mut tags = RAnsSymbolDecoder::create(buffer, 5)?;
+            if tags.probs.is_empty() {
+                return Err(String::from("Draco tag table is empty"));
+            }
+            tags.start(buffer)?;
+            let rest = buffer.bytes(buffer.remaining())?;
+            let mut bits = BitReader::new(rest);
+            while values.len() < num_values {
+                let bit_length = tags.symbol();
+                for _ in 0..num_components.max(1) {
+                    values.push(bits.bits(bit_length)?);
+                }
+            }
+            values.truncate(num_values);
+            *buffer = DecoderBuffer::new(&rest[bits.bytes_read()..]);
+        }
+        SYMBOL_CODING_RAW => {
+            let max_bit_length = buffer.u8()? as u32;
+            if max_bit_length == 0 || max_bit_length > MAX_RAW_BIT_LENGTH {
+                return Err(format!("Invalid Draco symbol bit length {}", max_bit_length));
+            }
+            let mut decoder = RAnsSymbolDecoder::create(buffer, max_bit_length)?;
+            if decoder.probs.is_empty() {
+                return Err(String::from("Draco symbol table is empty"));
+            }
+            decoder.start(buffer)?;
+            for _ in 0..num_values {
+                values.push(decoder.symbol());
+            }
+        }
+        scheme => return Err(format!("Unknown Draco symbol coding {}", scheme)),
+    }
+    Ok(values)
+}
+
+// Counterpart of decode_symbols, always with the raw scheme
+pub fn encode_symbols(buffer:&mut EncoderBuffer, values:&[u32]) -> bool {
+    let max_value = values.iter().copied().max().unwrap_or(0);
+    let num_symbols = max_value as usize + 1;
+    let bit_length = 32 - (num_symbols as u32).leading_zeros();
+    if bit_length > MAX_RAW_BIT_LENGTH {
+        return false;
+    }
+    let precision_bits = precision_bits(bit_length);
+    let precision = 1u32 << precision_bits;
+
+    // Scale the frequencies to the precision, keeping every used symbol at one or more
+    let mut freq = vec![0u64; num_symbols];
+    for value in values {
+        freq[*value as usize] += 1;
+    }
+    let total = values.len() as u64;
+    let mut probs: Vec<u32> = freq.iter().map(|f| if *f == 0 { 0 } else {
+        ((f * precision as u64) / total).max(1) as u32
+    }).collect();
+    let mut sum: i64 = probs.iter().map(|p| *p as i64).sum();
+    while sum != precision as i64 {
+        let (s, _) = probs.iter().enumerate().max_by_key(|(_, p)| **p).unwrap();
+        if sum > precision as i64 {
+            let cut = (sum - precision as i64).min(probs[s] as i64 - 1);
+            probs[s] -= cut as u32;
+            sum -= cut;
+            if cut == 0 {
+                return false;
+            }
+        } else {
+            probs[s] += (precision as i64 - sum) as u32;
+            sum = precision as i64;
+        }
+    }
+    if probs.iter().any(|p| *p >= 1 << 22) {
+        return false;
+    }
+    let mut cum_probs = vec![0u32; num_symbols];
+    for s in 1..num_symbols {
+        cum_probs[s] = cum_probs[s-1] + probs[s-1];
+    }
+
+    buffer.u8(SYMBOL_CODING_RAW);
+    buffer.u8(bit_length as u8);
+    buffer.varint(num_symbols as u64);
+    let mut s = 0;
+    while s < num_symbols {
+        let prob = probs[s];
+        if prob == 0 {
+            let mut offset = 0;
+            while offset < 63 && probs[s + offset + 1] == 0 {
+                offset += 1;
+            }
+            buffer.u8(((offset as u8) << 2) | 3);
+            s += offset + 1;
+        } else {
+            let extra = if prob >= 1 << 14 { 2 } else if prob >= 1 << 6 { 1 } else { 0 };
+            buffer.u8(((prob << 2) as u8) | extra);
+            for b in 0..extra as u32 {
+                buffer.u8((prob >> (8 * (b + 1) - 2)) as u8);
+            }
+            s += 1;
+        }
+    }
+
+    // Symbols are written in reverse so the decoder reads them in order
+    let l_base = precision * 4;
+    let mut state = l_base;
+    let mut out = Vec::new();
+    for value in values.iter().rev() {
+        let p = probs[*value as usize];
+        while state as u64 >= (l_base / precision) as u64 * ANS_IO_BASE as u64 * p as u64 {
+            out.push((state % ANS_IO_BASE) as u8);
+            state /= ANS_IO_BASE;
+        }
+        state = (state / p) * precision + state % p + cum_probs[*value as usize];
+    }
+    let state = state - l_base;
+    if state < 1 << 6 {
+        out.push(state as u8);
+    } else if state < 1 << 14 {
+        out.extend_from_slice(&((1 << 14) + state as u16).to_le_bytes());
+    } else if state < 1 << 22 {
+        out.extend_from_slice(&((2 << 22) + state).to_le_bytes()[..3]);
+    } else {
+        out.extend_from_slice(&((3 << 30) + state).to_le_bytes());
+    }
+    buffer.varint(out.len() as u64);
+    buffer.data.extend_from_slice(&out);
+    true
+}
@@ -0,0 +1,2955 @@
+// Decimation of glTF binary files by edge collapses, for the command line in main.rs and for other programs: the
+// cost metric and the placement of the collapses plug in through decimate, which reports its progress on the way.
+mod bvh;
+mod cluster;
+pub mod compare;
+mod draco;
+mod meshopt;
+mod metric;
+mod partition;
+mod pointcloud;
+mod polyline;
+mod progress;
+mod stream;
+mod symmetry;
+
+pub use metric::{CostMetric, Midpoint, Optimal, Placement, PlacementPolicy, PreserveVolume, Quadric};
+pub use pointcloud::Sampling;
+pub use progress::{Cancel, Progress, Update};
+pub use symmetry::Symmetry;
+
+use std::path::Path;
+use clap::Parser;
+use std::fs::File;
+use std::io::{self, Write, Read};
+use serde_json::{json, Value, to_vec, Map};
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector2, Vector3, Vector4};
+use std::collections::{BTreeMap, HashMap, HashSet, BinaryHeap};
+use std::cmp::{Reverse, Ordering};
+use std::time::{Duration, Instant};
+use indexmap::IndexMap;
+use log::{debug, info, trace, warn};
+use rayon::prelude::*;
+
+// An edge and the cost of collapsing it. Edges of equal cost are ordered by their vertices,
+// so that the collapses come in the same order on every run.
+#[derive(Debug)]
+struct Remove(u32, u32, f32);
+
+impl PartialEq for Remove {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Remove {}
+
+impl PartialOrd for Remove {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Remove {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.2.total_cmp(&other.2).then((self.0, self.1).cmp(&(other.0, other.1)))
+    }
+}
+
+// Component type of an accessor, and whether its integers are normalized
+#[derive(Debug, Clone)]
+struct Prim {
+    component_type: u32,
+    normalized: bool,
+}
+
+impl Prim {
+    fn new(component_type:u32, normalized:bool) -> Self {
+        Prim {
+            component_type,
+            normalized,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Vertex {
+    edge_set: Vec<Vector2<u32>>,
+    face_set: Vec<Vector3<u32>>,
+    // State of the cost metric, the quadric of the built-in one
+    q_matrix: Vec<f64>,
+    // One state per morph target, built from the morphed positions
+    target_q: Vec<Vec<f64>>,
+}
+
+impl Vertex {
+    fn new(edge_set:Vec<Vector2<u32>>, face_set:Vec<Vector3<u32>>,
+             q_matrix:Vec<f64>, target_q:Vec<Vec<f64>>) -> Self {
+        Vertex {
+            edge_set,
+            face_set,
+            q_matrix,
+            target_q,
+        }
+    }
+}
+
+// Morph target deltas of a primitive, keyed by vertex index like the base attributes
+#[derive(Debug, Clone)]
+struct Target {
+    position: HashMap<u32, Vector3<f32>>,
+    normal: HashMap<u32, Vector3<f32>>,
+    tangent: HashMap<u32, Vector3<f32>>,
+}
+
+impl Target {
+    fn new(position:HashMap<u32, Vector3<f32>>, normal:HashMap<u32, Vector3<f32>>,
+            tangent:HashMap<u32, Vector3<f32>>) -> Self {
+        Target {
+            position,
+            normal,
+            tangent,
+        }
+    }
+}
+
+// Vertex data of a primitive keyed by vertex index, as unpack_gltf reads it
+#[derive(Debug, Clone)]
+struct Attributes {
+    index_list: Vec<u32>,
+    normal_list: HashMap<u32, Vector3<f32>>,
+    position_list: HashMap<u32, Vector3<f32>>,
+    tangent_list: HashMap<u32, Vector4<f32>>,
+    texcoord_0_list: HashMap<u32, Vector2<f32>>,
+    target_list: Vec<Target>,
+}
+
+/// Stop criterion of the decimation, the limit giving its amount
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Method {
+    /// Keep a ratio of the triangles of each primitive
+    Percent,
+    /// Keep a number of triangles in each primitive
+    Max,
+    /// Keep a number of triangles in the whole scene, shared out by area
+    Budget,
+    /// Cluster the vertices on a grid of cells of a size in world units
+    Cluster,
+    /// Cluster the vertices on the grid that keeps about a number of triangles in each primitive
+    ClusterCount,
+}
+
+/// Decimate the mesh of a glTF binary file into output.glb, or measure the distance between two of them with
+/// `compare original.glb decimated.glb`
+#[derive(Parser, Debug, Clone)]
+pub struct Args {
+    /// Path to the .glb file
+    pub file_path: String,
+    /// Stop criterion, by edge collapses or vertex clustering
+    #[arg(value_enum)]
+    pub method: Method,
+    /// Ratio of triangles to keep for "percent", number of triangles to keep in each primitive for "max"
+    /// and "cluster-count", number of triangles for the whole scene for "budget", cell size in world units for "cluster"
+    pub limit: f64,
+    /// Where to write the decimated file
+    #[arg(short, long, default_value = "output.glb")]
+    pub output: String,
+    /// Stop once the error would exceed this distance, in world units
+    #[arg(long)]
+    pub max_error: Option<f32>,
+    /// Keep the vertices on the open borders of the meshes
+    #[arg(long)]
+    pub lock_boundary: bool,
+    /// Where collapsed vertices go
+    #[arg(long, value_enum, default_value_t = Placement::Midpoint)]
+    pub placement: Placement,
+    /// Keep the edges whose faces meet at more than this angle, in degrees, as sharp as they are
+    #[arg(long)]
+    pub crease_angle: Option<f32>,
+    /// Collapse mirrored edges together across a plane through the center of the mesh
+    #[arg(long, value_enum)]
+    pub symmetry: Option<Symmetry>,
+    /// Attribute painting how much to protect each vertex, with an optional channel ("COLOR_0.a"), which every
+    /// triangle primitive must have. A weight w makes collapses 1/(1-w) times costlier, vertices of weight 1 are
+    /// locked. Without it, the _DECIMATE_WEIGHT attribute of the primitives that have one.
+    #[arg(long)]
+    pub weight: Option<String>,
+    /// Sampling of POINTS primitives
+    #[arg(long, value_enum, default_value_t = Sampling::Voxel)]
+    pub points: Sampling,
+    /// Write morph target deltas as sparse accessors when it is smaller
+    #[arg(long)]
+    pub sparse: bool,
+    /// Quantize positions to 16 bits, normals to 8-bit snorm and UVs to 16 bits (KHR_mesh_quantization)
+    #[arg(long)]
+    pub quantize: bool,
+    /// Compress the written bufferViews with EXT_meshopt_compression, behind a fallback buffer
+    #[arg(long)]
+    pub meshopt: bool,
+    /// Compress the primitive with KHR_draco_mesh_compression
+    #[arg(long)]
+    pub draco: bool,
+    /// Decimate a memory-mapped .glb in spatial chunks with locked borders, writing the output as it goes. The chunks
+    /// are written uncompressed, without quantization or sparse targets
+    #[arg(long, conflicts_with_all = ["quantize", "meshopt", "draco", "sparse"])]
+    pub stream: bool,
+    /// Decimate the triangles in spatial chunks in parallel with their shared vertices locked, then the borders.
+    /// Symmetry needs the whole mesh and is not available with it
+    #[arg(long, conflicts_with = "symmetry")]
+    pub partition: bool,
+    /// Triangles in a streamed or partitioned chunk, about, for a surface: the grid of chunks has the square root
+    /// of the triangles over this number of cells along each axis, and a surface crosses about the square of that
+    #[arg(long, default_value_t = 20000)]
+    pub chunk_triangles: usize,
+    /// Write a JSON report of every primitive to this file: counts, errors, rejected collapses, borders and timings
+    #[arg(long)]
+    pub report: Option<String>,
+    /// Worker threads, one per core with 0
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+    /// Log what is done with -v, the details with -vv and the data itself with -vvv. RUST_LOG can pick the targets
+    /// "parse", "decimate" and "write" instead
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Draco quantization bits for positions
+    #[arg(long, default_value_t = 14)]
+    pub draco_position_bits: u32,
+    /// Draco quantization bits for normals
+    #[arg(long, default_value_t = 10)]
+    pub draco_normal_bits: u32,
+    /// Draco quantization bits for texture coordinates
+    #[arg(long, default_value_t = 12)]
+    pub draco_texcoord_bits: u32,
+}
+
+impl Args {
+    /// Options of the command line, with the built-in quadric metric
+    pub fn options(&self) -> Options<'static> {
+        Options {
+            method: self.method,
+            limit: self.limit,
+            output: self.output.clone(),
+            max_error: self.max_error,
+            lock_boundary: self.lock_boundary,
+            metric: &Quadric,
+            placement: self.placement.policy(),
+            crease_angle: self.crease_angle,
+            symmetry: self.symmetry,
+            weight: self.weight.clone(),
+            points: self.points,
+            sparse: self.sparse,
+            quantize: self.quantize,
+            meshopt: self.meshopt,
+            draco: self.draco.then_some(DracoBits {
+                position: self.draco_position_bits,
+                normal: self.draco_normal_bits,
+                texcoord: self.draco_texcoord_bits,
+            }),
+            stream: self.stream,
+            partition: self.partition,
+            chunk_triangles: self.chunk_triangles,
+            report: self.report.clone(),
+        }
+    }
+}
+
+/// How decimate treats a file, the options of the command line for other programs. The metric ranks the
+/// collapses and the placement puts the collapsed vertices, meshes may still choose another placement in their extras.
+#[derive(Clone)]
+pub struct Options<'a> {
+    pub method: Method,
+    pub limit: f64,
+    pub output: String,
+    pub max_error: Option<f32>,
+    pub lock_boundary: bool,
+    pub metric: &'a dyn CostMetric,
+    pub placement: &'a dyn PlacementPolicy,
+    pub crease_angle: Option<f32>,
+    pub symmetry: Option<Symmetry>,
+    pub weight: Option<String>,
+    pub points: Sampling,
+    pub sparse: bool,
+    pub quantize: bool,
+    pub meshopt: bool,
+    pub draco: Option<DracoBits>,
+    pub stream: bool,
+    pub partition: bool,
+    pub chunk_triangles: usize,
+    pub report: Option<String>,
+}
+
+impl Options<'_> {
+    /// The defaults of the command line for a method and its limit, written to output.glb
+    pub fn new(method:Method, limit:f64) -> Self {
+        Options {
+            method,
+            limit,
+            output: String::from("output.glb"),
+            max_error: None,
+            lock_boundary: false,
+            metric: &Quadric,
+            placement: &Midpoint,
+            crease_angle: None,
+            symmetry: None,
+            weight: None,
+            points: Sampling::Voxel,
+            sparse: false,
+            quantize: false,
+            meshopt: false,
+            draco: None,
+            stream: false,
+            partition: false,
+            chunk_triangles: 20000,
+            report: None,
+        }
+    }
+}
+
+// Attribute read for the weights when --weight does not name one
+const WEIGHT: &str = "_DECIMATE_WEIGHT";
+
+// Vertices closer than this, in world units, can be merged without sharing an edge
+const PROXIMITY: f32 = 0.01;
+
+// How much more moving off a crease costs than moving off the faces along it
+const CREASE_WEIGHT: f32 = 100.0;
+
+// How a mesh is decimated: the command line options, overridden by the "decimate" extras of the mesh and of its nodes
+#[derive(Clone, Copy, Debug)]
+struct Settings<'a> {
+    skip: bool,
+    method: Method,
+    limit: f64,
+    lock_boundary: bool,
+    max_error: Option<f32>,
+    crease_angle: Option<f32>,
+    metric: &'a dyn CostMetric,
+    placement: &'a dyn PlacementPolicy,
+    progress: &'a Progress,
+}
+
+/// Quantization bits of the attributes written into the Draco buffer
+#[derive(Clone, Copy, Debug)]
+pub struct DracoBits {
+    pub position: u32,
+    pub normal: u32,
+    pub texcoord: u32,
+}
+
+/// Decimate the glTF binary file at path into options.output. The progress is reported as the collapses go, and a
+/// cancelled progress writes the collapses done so far.
+pub fn decimate(path:&Path, options:&Options, progress:&Progress) -> io::Result<()> {
+    // Exam file format
+    match path.extension().and_then(|f| f.to_str()) {
+        Some("gltf" | "glb") => {}
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a valid glb file", path.display()))),
+    }
+    let defaults = Settings {
+        skip: false,
+        method: options.method,
+        limit: options.limit,
+        lock_boundary: options.lock_boundary,
+        max_error: options.max_error,
+        crease_angle: options.crease_angle,
+        metric: options.metric,
+        placement: options.placement,
+        progress,
+    };
+    if options.partition && options.symmetry.is_some() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "Symmetry needs the whole mesh and is not supported with --partition"));
+    }
+    if options.stream {
+        return stream::decimate(path, options, defaults);
+    }
+    decimation_gltf(path, options, defaults)
+}
+
+fn decimation_gltf(path:&Path, options:&Options, defaults:Settings) -> io::Result<()> {
+    // Unpack the data into json and binary chunks
+    let (mut json, mut binary_chunk) = read_glb(path)?;
+    check_accessors(&json, &binary_chunk)?;
+    let mut primitives = Vec::new();
+    for (m, mesh) in json["meshes"].as_array().into_iter().flatten().enumerate() {
+        for p in 0..mesh["primitives"].as_array().map_or(0, |primitives| primitives.len()) {
+            primitives.push((m, p));
+        }
+    }
+    for (mesh, primitive) in &primitives {
+        triangulate(&mut json, &mut binary_chunk, *mesh, *primitive)?;
+    }
+    let mesh_num = json["meshes"].as_array().map_or(0, |meshes| meshes.len());
+    let meshes: Vec<Settings> = (0..mesh_num).map(|mesh| mesh_settings(&json, mesh, defaults)).collect();
+    let mut settings: Vec<Settings> = primitives.iter().map(|(mesh, _)| meshes[*mesh]).collect();
+    // A scene budget is shared out into a number of triangles for each primitive
+    if options.method == Method::Budget {
+        allocate_budget(&json, &binary_chunk, &primitives, &mut settings, options.limit);
+    }
+    let mut filters = HashMap::new();
+    let mut report = Vec::new();
+    let mut work = Vec::new();
+    let mut entries = Vec::new();
+    for (at, settings) in primitives.iter().zip(&settings) {
+        if settings.skip {
+            info!(target: "decimate", "Mesh {} is left as it is", at.0);
+            entries.push(json!({ "mesh": at.0, "primitive": at.1, "skipped": true }));
+            continue;
+        }
+        work.push((*at, settings));
+    }
+    // Primitives are simplified in parallel, and written back one after the other in their order
+    let simplified: Vec<Simplified> = work.par_iter()
+        .map(|(at, settings)| simplify_primitive(&json, &binary_chunk, *at, settings, options)).collect::<io::Result<_>>()?;
+    for ((at, settings), simplified) in work.into_iter().zip(simplified) {
+        let (before, after, error, volume, stats) = write_primitive(&mut json, &mut binary_chunk, at, simplified, options,
+                                                                    &mut filters)?;
+        report.push((at, settings, before, after, error, volume, stats));
+    }
+
+    // Elements are triangles, segments or points depending on the primitive. The table is logged with -v, the
+    // report holds the same numbers for scripts
+    info!(target: "decimate", "Mesh  Primitive  Instances  Before  Target   After   Volume  Error");
+    let mut scene = (0, 0);
+    for ((mesh, primitive), settings, before, after, error, volume, stats) in report {
+        let instances = mesh_instances(&json, mesh as u64).len();
+        let mode = json["meshes"][mesh]["primitives"][primitive]["mode"].as_u64().unwrap_or(4);
+        if mode == 4 {
+            scene = (scene.0 + before * instances, scene.1 + after * instances);
+        }
+        entries.push(report_entry((mesh, primitive), mode, before, after, error, volume, &stats));
+        let target = if settings.method == Method::Percent { format!("{}%", settings.limit * 100.0) } else { format!("{}", settings.limit) };
+        let volume = volume.map_or(String::from("-"), |volume| format!("{:+.2}%", volume * 100.0));
+        info!(target: "decimate", "{:>4}  {:>9}  {:>9}  {:>6}  {:>6}  {:>6}  {:>7}  {}", mesh, primitive, instances, before,
+              target, after, volume, error);
+    }
+    info!(target: "decimate", "Scene triangles: {} -> {}", scene.0, scene.1);
+    write_glb(json, &binary_chunk, options.meshopt, &filters, &options.output)?;
+    if let Some(report) = &options.report {
+        write_report(report, path, options, scene, entries);
+    }
+    Ok(())
+}
+
+// Entry of a decimated primitive in the --report file, its errors in world units and its timings in seconds
+fn report_entry((mesh, primitive):(usize, usize), mode:u64, before:usize, after:usize, error:f32, volume:Option<f64>,
+                stats:&Stats) -> Value {
+    json!({
+        "mesh": mesh,
+        "primitive": primitive,
+        "elements": match mode { 0 => "points", 1 => "lines", _ => "triangles" },
+        "before": before,
+        "after": after,
+        "ratio": if before > 0 { after as f64 / before as f64 } else { 1.0 },
+        "verticesBefore": stats.vertices.0,
+        "verticesAfter": stats.vertices.1,
+        "maxError": error,
+        "meanError": if stats.collapses > 0 { stats.error_sum / stats.collapses as f64 } else { 0.0 },
+        "volumeChange": volume,
+        "collapses": stats.collapses,
+        "rejected": stats.rejected,
+        "boundaryEdgesBefore": stats.boundary.map(|b| b.0),
+        "boundaryEdgesAfter": stats.boundary.map(|b| b.1),
+        "seamEdgesBefore": stats.seams.map(|s| s.0),
+        "seamEdgesAfter": stats.seams.map(|s| s.1),
+        "seconds": {
+            "unpack": stats.unpack.as_secs_f64(),
+            "initialize": stats.initialize.as_secs_f64(),
+            "collapse": stats.collapse.as_secs_f64(),
+            "repack": stats.repack.as_secs_f64(),
+        },
+    })
+}
+
+// Write the report of a run once the output is written, with its size
+fn write_report(path:&str, input:&Path, options:&Options, (before, after):(usize, usize), primitives:Vec<Value>) {
+    let report = json!({
+        "input": input,
+        "output": options.output,
+        "outputBytes": std::fs::metadata(&options.output).map_or(0, |metadata| metadata.len()),
+        "sceneTrianglesBefore": before,
+        "sceneTrianglesAfter": after,
+        "primitives": primitives,
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&report).unwrap()).expect("Failed to write the report");
+    info!(target: "write", "Report written to {}", path);
+}
+
+// Share a scene triangle budget among the triangle primitives in proportion to their world-space area.
+// Primitives that would get more triangles than they have keep them all and the rest is shared again.
+// Points, lines and primitives outside of the scenes are kept as they are; meshes with their own settings
+// take their share of the budget first.
+fn allocate_budget(json:&Value, binary_chunk:&[u8], primitives:&[(usize, usize)], settings:&mut [Settings], budget:f64) {
+    // Triangles and world area of every instanced triangle primitive, counting all of its instances
+    let mut shares = Vec::new();
+    let mut remaining = budget;
+    for (k, (mesh, primitive)) in primitives.iter().enumerate() {
+        let instances = mesh_instances(json, *mesh as u64);
+        let prim = &json["meshes"][*mesh]["primitives"][*primitive];
+        if instances.is_empty() || prim["mode"].as_u64().unwrap_or(4) != 4 {
+            if settings[k].method == Method::Budget {
+                (settings[k].method, settings[k].limit) = (Method::Percent, 1.0);
+            }
+            continue;
+        }
+        let index_list = read_accessor(json, prim["indices"].as_u64().unwrap() as usize, binary_chunk);
+        let values = read_values(json, prim["attributes"]["POSITION"].as_u64().unwrap() as usize, binary_chunk);
+        let p = |i:f64| Vector3::new(values[3*i as usize] as f32, values[3*i as usize+1] as f32, values[3*i as usize+2] as f32);
+        let mut area = 0.0f32;
+        for world in &instances {
+            let linear = world.fixed_view::<3, 3>(0, 0);
+            for t in index_list.chunks_exact(3) {
+                area += (linear * (p(t[1]) - p(t[0]))).cross(&(linear * (p(t[2]) - p(t[0])))).norm() / 2.0;
+            }
+        }
+        let triangles = index_list.len() / 3;
+        let kept = match (settings[k].skip, settings[k].method) {
+            (false, Method::Budget) => {
+                shares.push((k, triangles, instances.len(), area.max(f32::MIN_POSITIVE) as f64));
+                continue;
+            }
+            (false, Method::Percent) => triangles as f64 * settings[k].limit,
+            (false, Method::Max | Method::ClusterCount) => settings[k].limit.min(triangles as f64),
+            _ => triangles as f64,
+        };
+        remaining -= kept * instances.len() as f64;
+    }
+    loop {
+        let total: f64 = shares.iter().map(|share| share.3).sum();
+        let full: Vec<usize> = shares.iter().enumerate()
+            .filter(|(_, share)| remaining * share.3 / total >= (share.1 * share.2) as f64)
+            .map(|(s, _)| s).collect();
+        if full.is_empty() {
+            break;
+        }
+        for s in full.into_iter().rev() {
+            let (k, triangles, instances, _) = shares.remove(s);
+            remaining -= (triangles * instances) as f64;
+            (settings[k].method, settings[k].limit) = (Method::Percent, 1.0);
+        }
+    }
+    let total: f64 = shares.iter().map(|share| share.3).sum();
+    for (k, _, instances, area) in shares {
+        (settings[k].method, settings[k].limit) = (Method::Max, (remaining.max(0.0) * area / total / instances as f64).floor());
+    }
+}
+
+// Settings of a mesh from the command line and the "decimate" extras of the mesh and of the nodes using it,
+// e.g. {"decimate": {"ratio": 0.3, "lockBoundary": true, "placement": "volume"}}, or {"decimate": false} to leave
+// the mesh alone. The placement is one of "midpoint", "optimal" and "volume", as on the command line.
+// When several of them set the same option, the one that keeps the most wins; a node's placement wins over the mesh's.
+fn mesh_settings<'a>(json:&Value, mesh:usize, defaults:Settings<'a>) -> Settings<'a> {
+    let mut settings = defaults;
+    let mut overrides = vec![&json["meshes"][mesh]["extras"]["decimate"]];
+    overrides.extend(json["nodes"].as_array().into_iter().flatten()
+        .filter(|node| node["mesh"].as_u64() == Some(mesh as u64))
+        .map(|node| &node["extras"]["decimate"]));
+    let mut ratio: Option<f64> = None;
+    let mut max_error: Option<f32> = None;
+    let mut lock_boundary = false;
+    let mut placement: Option<Placement> = None;
+    let mut crease_angle: Option<f32> = None;
+    for decimate in overrides {
+        if *decimate == false || decimate["skip"] == true {
+            settings.skip = true;
+        }
+        if let Some(r) = decimate["ratio"].as_f64() {
+            ratio = Some(ratio.map_or(r, |ratio| ratio.max(r)));
+        }
+        if let Some(e) = decimate["maxError"].as_f64() {
+            max_error = Some(max_error.map_or(e as f32, |error| error.min(e as f32)));
+        }
+        if let Some(a) = decimate["creaseAngle"].as_f64() {
+            crease_angle = Some(crease_angle.map_or(a as f32, |angle| angle.min(a as f32)));
+        }
+        lock_boundary = lock_boundary || decimate["lockBoundary"] == true;
+        if let Some(name) = decimate["placement"].as_str() {
+            match <Placement as clap::ValueEnum>::from_str(name, true) {
+                Ok(p) => placement = Some(p),
+                Err(_) => warn!(target: "decimate", "Mesh {}: unknown placement {:?} in extras, expected midpoint, optimal or volume",
+                                mesh, name),
+            }
+        }
+    }
+    if let Some(ratio) = ratio {
+        (settings.method, settings.limit) = (Method::Percent, ratio.clamp(0.0, 1.0));
+    }
+    settings.max_error = max_error.or(settings.max_error);
+    settings.lock_boundary = settings.lock_boundary || lock_boundary;
+    settings.crease_angle = crease_angle.or(settings.crease_angle);
+    if let Some(placement) = placement {
+        settings.placement = placement.policy();
+    }
+    if settings.skip || ratio.is_some() || max_error.is_some() || lock_boundary || placement.is_some() || crease_angle.is_some() {
+        info!(target: "decimate", "Mesh {} settings from extras: {:?}", mesh, settings);
+    }
+    settings
+}
+
+// Decimate one primitive in place and return its triangles before and after, with the error in world units
+// and the relative change of the enclosed volume
+fn decimate_primitive(json:&mut Value, binary_chunk:&mut Vec<u8>, at:(usize, usize), settings:&Settings, options:&Options,
+                        filters:&mut HashMap<usize, &'static str>)
+                        -> io::Result<(usize, usize, f32, Option<f64>, Stats)> {
+    let simplified = simplify_primitive(json, binary_chunk, at, settings, options)?;
+    write_primitive(json, binary_chunk, at, simplified, options, filters)
+}
+
+// A primitive decimated in memory, with its report, before it is written back
+struct Simplified {
+    attributes: Attributes,
+    primitives: HashMap<String, Prim>,
+    before: usize,
+    after: usize,
+    error: f32,
+    volume: Option<f64>,
+    stats: Stats,
+}
+
+// What the decimation of a primitive went through, for --report
+#[derive(Debug, Clone, Default)]
+struct Stats {
+    // Vertices used by the elements before and after, and for whole triangle primitives the open edges of the mesh
+    // and the seams, where the vertices of an edge differ but not its positions
+    vertices: (usize, usize),
+    boundary: Option<(usize, usize)>,
+    seams: Option<(usize, usize)>,
+    // Collapses done with the sum of their errors in world units, and the ones left out by reason
+    collapses: usize,
+    error_sum: f64,
+    rejected: BTreeMap<&'static str, usize>,
+    // Time spent in each phase, summed over the chunks decimated side by side
+    unpack: Duration,
+    initialize: Duration,
+    collapse: Duration,
+    repack: Duration,
+}
+
+impl Stats {
+    // Add the collapses and timings of a chunk of the same primitive
+    fn add(&mut self, other:&Stats) {
+        self.collapses += other.collapses;
+        self.error_sum += other.error_sum;
+        for (reason, count) in &other.rejected {
+            *self.rejected.entry(reason).or_default() += count;
+        }
+        self.unpack += other.unpack;
+        self.initialize += other.initialize;
+        self.collapse += other.collapse;
+        self.repack += other.repack;
+    }
+}
+
+// Open edges of the triangles by position, and the seams: edges open between their vertices but not their positions
+fn borders(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>) -> (usize, usize) {
+    let key = |i:&u32| { let p = position_list[i]; [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()] };
+    let mut by_index: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut by_position: HashMap<([u32; 3], [u32; 3]), usize> = HashMap::new();
+    for t in index_list.chunks_exact(3) {
+        for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+            *by_index.entry((a.min(b), a.max(b))).or_default() += 1;
+            let (ka, kb) = (key(&a), key(&b));
+            *by_position.entry((ka.min(kb), ka.max(kb))).or_default() += 1;
+        }
+    }
+    let position_edge = |(a, b):&(u32, u32)| { let (ka, kb) = (key(a), key(b)); by_position[&(ka.min(kb), ka.max(kb))] };
+    let boundary = by_position.values().filter(|count| **count == 1).count();
+    let seams = by_index.iter().filter(|(edge, count)| **count == 1 && position_edge(edge) > 1).count();
+    (boundary, seams)
+}
+
+// Decimate one primitive without touching the glTF, so that primitives can go through it side by side
+fn simplify_primitive(json:&Value, binary_chunk:&[u8], (mesh, primitive):(usize, usize), settings:&Settings,
+                        options:&Options) -> io::Result<Simplified> {
+    let (method, limit) = (settings.method, settings.limit);
+    let mut stats = Stats::default();
+    let start = Instant::now();
+    // Process the data to return informations in HashMaps (not supporting tangent, TEXCOORD_n, COLOR_n, Joints_n, Weight_n)
+    let (mut attributes, primitives) = unpack_gltf(json, binary_chunk, mesh, primitive);
+    stats.unpack = start.elapsed();
+    let vertices = |index_list:&[u32]| index_list.iter().collect::<HashSet<_>>().len();
+    stats.vertices.0 = vertices(&attributes.index_list);
+    // Distances are given in world units, the largest instance of the mesh sets how far they reach in the mesh
+    let scale = world_scale(json, mesh as u64);
+    let max_error = settings.max_error.map_or(f32::MAX, |error| error / scale);
+    // Points are sampled and lines go through polyline simplification instead of edge collapses
+    let mode = json["meshes"][mesh]["primitives"][primitive]["mode"].as_u64();
+    if method == Method::Cluster && matches!(mode, Some(0 | 1)) {
+        warn!(target: "decimate", "Mesh {} primitive {}: clustering by cell size only applies to triangles, the {} are kept",
+                mesh, primitive, if mode == Some(0) { "points" } else { "lines" });
+    }
+    if mode == Some(0) {
+        let before = attributes.index_list.len();
+        let count = match method {
+            Method::Percent => (before as f64 * limit) as usize,
+            Method::Max | Method::ClusterCount | Method::Budget => limit as usize,
+            Method::Cluster => before,
+        };
+        let start = Instant::now();
+        attributes.index_list = pointcloud::sample(&attributes.index_list, &attributes.position_list, count, options.points);
+        stats.collapse = start.elapsed();
+        stats.vertices.1 = vertices(&attributes.index_list);
+        let after = attributes.index_list.len();
+        return Ok(Simplified { attributes, primitives, before, after, error: 0.0, volume: None, stats });
+    }
+    if mode == Some(1) {
+        let segments = attributes.index_list.len() / 2;
+        let goal = match method {
+            Method::Percent => (segments as f64 * (1.0 - limit)) as usize,
+            Method::Max | Method::ClusterCount | Method::Budget => segments.saturating_sub(limit as usize),
+            Method::Cluster => 0,
+        };
+        let start = Instant::now();
+        let (index_list, error, collapses, error_sum) = polyline::simplify(&attributes.index_list, &attributes.position_list,
+                                                                          goal, max_error);
+        stats.collapse = start.elapsed();
+        (stats.collapses, stats.error_sum) = (collapses, error_sum * scale as f64);
+        attributes.index_list = index_list;
+        stats.vertices.1 = vertices(&attributes.index_list);
+        let after = attributes.index_list.len() / 2;
+        return Ok(Simplified { attributes, primitives, before: segments, after, error: error * scale, volume: None, stats });
+    }
+    let before = attributes.index_list.len() / 3;
+    let volume = signed_volume(&attributes.index_list, &attributes.position_list);
+    let (boundary, seams) = borders(&attributes.index_list, &attributes.position_list);
+    // Painted weights, weight 1 locks the vertex, like the chunk borders of the streaming mode
+    let weight = options.weight.as_deref().unwrap_or(WEIGHT);
+    let weights = vertex_weights(json, binary_chunk, (mesh, primitive), weight, options.weight.is_some(),
+                                    &attributes.position_list)?;
+    let border = vertex_weights(json, binary_chunk, (mesh, primitive), stream::BORDER, false, &attributes.position_list)?;
+    let locked: HashSet<u32> = weights.iter().chain(&border).filter(|(_, w)| **w >= 1.0).map(|(i, _)| *i).collect();
+    // The collapses number the vertices from 0
+    let ids = renumber(&mut attributes);
+    let number = |i:&u32| ids.binary_search(i).ok().map(|k| k as u32);
+    let weights: HashMap<u32, f32> = weights.iter().filter_map(|(i, w)| Some((number(i)?, *w))).collect();
+    let locked: HashSet<u32> = locked.iter().filter_map(number).collect();
+    let error = match method {
+        // Clustering replaces the edge collapses for meshes too large for them
+        Method::Cluster | Method::ClusterCount => {
+            let start = Instant::now();
+            let cell = match method {
+                Method::Cluster => limit as f32 / scale,
+                _ => cluster::cell_for_count(&attributes.index_list, &attributes.position_list, limit as usize, &locked),
+            };
+            let (index_list, error, merged, error_sum) = cluster::cluster(&attributes.index_list, &mut attributes.position_list,
+                                                                          cell, &locked);
+            attributes.index_list = index_list;
+            stats.collapse = start.elapsed();
+            (stats.collapses, stats.error_sum) = (merged, error_sum * scale as f64);
+            error
+        }
+        _ => {
+            let remove = match method {
+                Method::Percent => (before as f64 * (1.0 - limit)) as usize,
+                _ => before.saturating_sub(limit as usize),
+            };
+            let (error, collapses) = match options.partition {
+                true => partition::decimate(&mut attributes, &weights, &locked, settings, options, scale, remove),
+                false => collapse_edges(&mut attributes, &weights, locked, settings, options, scale, remove),
+            };
+            stats.add(&collapses);
+            // The quadric error is a sum of squared distances
+            error.max(0.0).sqrt()
+        }
+    };
+    restore(&mut attributes, &ids);
+    let after = attributes.index_list.len() / 3;
+    let volume = (volume != 0.0).then(|| signed_volume(&attributes.index_list, &attributes.position_list) / volume - 1.0);
+    stats.vertices.1 = vertices(&attributes.index_list);
+    let borders = borders(&attributes.index_list, &attributes.position_list);
+    (stats.boundary, stats.seams) = (Some((boundary, borders.0)), Some((seams, borders.1)));
+    Ok(Simplified { attributes, primitives, before, after, error: error * scale, volume, stats })
+}
+
+// Number the vertices of a primitive from 0 in their order, which a primitive using part of an accessor shared with
+// others does not, and return the original number of every vertex
+fn renumber(attributes:&mut Attributes) -> Vec<u32> {
+    let mut ids: Vec<u32> = attributes.index_list.iter().copied().collect::<HashSet<u32>>().into_iter().collect();
+    ids.sort_unstable();
+    if ids.last().is_none_or(|last| *last as usize == ids.len() - 1) {
+        return ids;
+    }
+    let number = |i:u32| ids.binary_search(&i).unwrap() as u32;
+    attributes.index_list.iter_mut().for_each(|i| *i = number(*i));
+    rekey(attributes, number);
+    ids
+}
+
+// Give the vertices of a primitive their original numbers back
+fn restore(attributes:&mut Attributes, ids:&[u32]) {
+    if ids.last().is_none_or(|last| *last as usize == ids.len() - 1) {
+        return;
+    }
+    attributes.index_list.iter_mut().for_each(|i| *i = ids[*i as usize]);
+    rekey(attributes, |i| ids[i as usize]);
+}
+
+fn rekey(attributes:&mut Attributes, number:impl Fn(u32) -> u32) {
+    fn list<T>(list:&mut HashMap<u32, T>, number:&impl Fn(u32) -> u32) {
+        *list = std::mem::take(list).into_iter().map(|(i, value)| (number(i), value)).collect();
+    }
+    list(&mut attributes.normal_list, &number);
+    list(&mut attributes.position_list, &number);
+    list(&mut attributes.tangent_list, &number);
+    list(&mut attributes.texcoord_0_list, &number);
+    for target in attributes.target_list.iter_mut() {
+        list(&mut target.position, &number);
+        list(&mut target.normal, &number);
+        list(&mut target.tangent, &number);
+    }
+}
+
+// Remove up to remove triangles by edge collapses, cheapest first, and return the largest quadric cost reached
+// with what the collapses went through
+fn collapse_edges(attributes:&mut Attributes, weights:&HashMap<u32, f32>, mut locked:HashSet<u32>, settings:&Settings,
+                    options:&Options, scale:f32, remove:usize) -> (f32, Stats) {
+    let mut stats = Stats::default();
+    let start = Instant::now();
+    let max_error = settings.max_error.map_or(f32::MAX, |error| error / scale);
+    let mut index_list = std::mem::take(&mut attributes.index_list);
+    let mut normal_list = std::mem::take(&mut attributes.normal_list);
+    // The quadrics are built around the center of the bounds, where the coordinates keep their precision
+    let original = std::mem::take(&mut attributes.position_list);
+    let (mut min, mut max) = (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN));
+    for p in original.values() {
+        min = min.inf(p);
+        max = max.sup(p);
+    }
+    let center = if original.is_empty() { Vector3::zeros() } else { (min + max) / 2.0 };
+    let mut position_list: HashMap<u32, Vector3<f32>> = original.iter().map(|(i, p)| (*i, p - center)).collect();
+    let tangent_list = std::mem::take(&mut attributes.tangent_list);
+    let mut texcoord_0_list = std::mem::take(&mut attributes.texcoord_0_list);
+    let mut target_list = std::mem::take(&mut attributes.target_list);
+    let (mut vertex_list, _) = initialize(&index_list, &position_list, &target_list, settings.metric);
+    // Creases add their constraint planes to the quadrics, and the corners where they meet are locked
+    if let Some(angle) = settings.crease_angle {
+        locked.extend(crease_quadrics(&index_list, &position_list, &mut vertex_list, settings.metric, angle));
+    }
+    // Painted weights scale the quadrics before the first costs are computed
+    for (i, w) in weights {
+        if let (true, Some(vertex)) = (*w > 0.0 && *w < 1.0, vertex_list.get_mut(i)) {
+            let factor = 1.0 / (1.0 - *w as f64);
+            vertex.q_matrix.iter_mut().for_each(|q| *q *= factor);
+            vertex.target_q.iter_mut().flatten().for_each(|q| *q *= factor);
+        }
+    }
+    let valid_edge = get_valid_edge(&index_list, &position_list, &vertex_list, &target_list, PROXIMITY / scale,
+                                    settings);
+    let mut remove_list: BinaryHeap<Reverse<Remove>> = BinaryHeap::new();
+    // Iterate over key-value pairs in valid_edge and insert them into remove_list
+    for ((u, v), cost) in valid_edge.iter() {
+        let remove = Remove(*u, *v, *cost);
+        remove_list.push(Reverse(remove));
+    }
+    // Set up stop criteria
+    let mut goal = valid_edge.len().min(remove);
+
+    // Start iteration of vertex removement
+    let mut index_ref = vec![true; position_list.len()];
+
+    // Remove duplicate position vertex, unless a morph target moves them apart
+    let mut duplicates = 0;
+    for i in 0..position_list.len() {
+        if !index_ref[i] {
+            continue;
+        }
+        let end = position_list.len();
+        for (j, kept) in index_ref.iter_mut().enumerate().take(end).skip(i + 1) {
+            let (u, v) = (i as u32, j as u32);
+            if *kept && position_list.get(&u) == position_list.get(&v) && target_list.iter()
+                .all(|t| [&t.position, &t.normal, &t.tangent].iter().all(|list| list.get(&u) == list.get(&v))) {
+                duplicates += 1;
+                position_list.remove(&(j as u32));
+                normal_list.remove(&(j as u32));
+                // The faces of j become faces of i, and bring their quadrics along
+                if let Some(twin) = vertex_list.remove(&(j as u32)) {
+                    let vertex = vertex_list.get_mut(&(i as u32)).unwrap();
+                    vertex.q_matrix = settings.metric.combine(&vertex.q_matrix, &twin.q_matrix);
+                    for (q, k) in vertex.target_q.iter_mut().zip(&twin.target_q) {
+                        *q = settings.metric.combine(q, k);
+                    }
+                }
+                texcoord_0_list.remove(&(j as u32));
+                for target in target_list.iter_mut() {
+                    target.position.remove(&(j as u32));
+                    target.normal.remove(&(j as u32));
+                    target.tangent.remove(&(j as u32));
+                }
+                for index in index_list.iter_mut() {
+                    if *index == j as u32 {
+                        *index = i as u32;
+                    }
+                }
+                *kept = false;
+            }
+        }
+    }
+    info!(target: "decimate", "{} vertices merged with another one at the same position", duplicates);
+
+    // Vertices on an edge used by a single triangle, once the duplicate positions are merged
+    if settings.lock_boundary {
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in index_list.chunks_exact(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let boundary: HashSet<u32> = edges.into_iter().filter(|(_, count)| *count == 1).flat_map(|((a, b), _)| [a, b]).collect();
+        info!(target: "decimate", "{} boundary vertices are locked", boundary.len());
+        locked.extend(boundary);
+    }
+    // Mirrored vertices collapse together so that the decimated mesh keeps the symmetry of the input
+    let mut mirror = match options.symmetry {
+        Some(symmetry) => symmetry::pairs(&position_list, symmetry),
+        None => HashMap::new(),
+    };
+
+    let mut error = 0.0f32;
+    stats.initialize += start.elapsed();
+    let start = Instant::now();
+    settings.progress.start(goal);
+    'collapse: while goal > 0 {
+        if settings.progress.is_cancelled() {
+            info!(target: "decimate", "Cancelled with {} triangles left to remove", goal);
+            break 'collapse;
+        }
+        let left = goal;
+        // Pop until an edge whose both ends are still alive and not too sparse, along with its mirror
+        let (remove, mirrored) = loop {
+            let remove = match remove_list.pop() {
+                Some(remove) => remove.0,
+                None => break 'collapse,
+            };
+            // Why u and v cannot collapse, if they cannot
+            let alive = |u:u32, v:u32| index_ref[u as usize] && index_ref[v as usize];
+            let rejection = |u:u32, v:u32| {
+                if !alive(u, v) {
+                    Some("collapsed")
+                } else if locked.contains(&u) || locked.contains(&v) {
+                    Some("locked")
+                } else if [u, v].iter().any(|i| vertex_list[i].edge_set.len() < 3 || vertex_list[i].face_set.len() < 2) {
+                    Some("topology")
+                } else {
+                    None
+                }
+            };
+            // Entries of collapsed vertices are left in the heap and only dropped here, they are not rejections
+            if !alive(remove.0, remove.1) {
+                continue;
+            }
+            if let Some(reason) = rejection(remove.0, remove.1) {
+                *stats.rejected.entry(reason).or_default() += 1;
+                continue;
+            }
+            let mirrored = match (mirror.get(&remove.0).copied(), mirror.get(&remove.1).copied()) {
+                // On the plane, or across it
+                (Some(a), Some(b)) if (a, b) == (remove.0, remove.1) || (a, b) == (remove.1, remove.0) => None,
+                (Some(a), Some(b)) => {
+                    // Both collapses would move a vertex on the plane off it
+                    if [a, b].iter().any(|m| *m == remove.0 || *m == remove.1) || rejection(a, b).is_some() {
+                        *stats.rejected.entry("symmetry").or_default() += 1;
+                        continue;
+                    }
+                    let edge = |u:u32, v:u32| vertex_list.get(&u).unwrap().edge_set.iter()
+                        .any(|e| (e[0], e[1]) == (u, v) || (e[0], e[1]) == (v, u));
+                    if edge(remove.0, remove.1) && !edge(a, b) {
+                        *stats.rejected.entry("symmetry").or_default() += 1;
+                        continue;
+                    }
+                    Some(Remove(a, b, edge_cost(a, b, &index_list, &position_list, &vertex_list, &target_list, settings)))
+                }
+                _ => None,
+            };
+            break (remove, mirrored);
+        };
+        // The quadric error is a sum of squared distances. The cost in the heap may be older than the quadrics, merged
+        // at duplicate positions and collapses since, so it is measured again where the placement puts the vertex
+        let remove = Remove(remove.0, remove.1, edge_cost(remove.0, remove.1, &index_list, &position_list, &vertex_list,
+                                                          &target_list, settings));
+        let cost = mirrored.as_ref().map_or(remove.2, |mirrored| remove.2.max(mirrored.2));
+        if cost > max_error * max_error {
+            *stats.rejected.entry("maxError").or_default() += 1;
+            info!(target: "decimate", "Error limit reached with {} triangles left to remove", goal);
+            break 'collapse;
+        }
+        match (mirror.get(&remove.0).copied(), mirror.get(&remove.1).copied()) {
+            // An edge across the plane collapses onto it
+            (Some(b), Some(a)) if (a, b) == (remove.0, remove.1) => { mirror.insert(remove.0, remove.0); }
+            (Some(_), Some(_)) | (None, None) => {}
+            // The collapse of an edge with a single mirrored end breaks the pair
+            (a, b) => {
+                for m in [a, b].into_iter().flatten() {
+                    mirror.remove(&m);
+                }
+                mirror.remove(&remove.0);
+            }
+        }
+        // The mirrored edge goes through the same collapse right after
+        for (k, mut remove) in std::iter::once(remove).chain(mirrored).enumerate() {
+            // The mirrored collapse comes after the first one changed the quadrics around it
+            if k > 0 {
+                remove.2 = edge_cost(remove.0, remove.1, &index_list, &position_list, &vertex_list, &target_list, settings);
+            }
+            error = error.max(remove.2);
+            stats.collapses += 1;
+            stats.error_sum += (remove.2.max(0.0).sqrt() * scale) as f64;
+            // vertex
+            let v1 = vertex_list.get(&remove.0).unwrap();
+            let v2 = vertex_list.get(&remove.1).unwrap();
+            let q_matrix = settings.metric.combine(&v1.q_matrix, &v2.q_matrix);
+            let target_q: Vec<Vec<f64>> = v1.target_q.iter().zip(&v2.target_q)
+                .map(|(q1, q2)| settings.metric.combine(q1, q2)).collect();
+            // Placed before the faces around the edge change
+            let new_p = settings.placement.place(remove.0, remove.1, &index_list, &position_list, &q_matrix,
+                                    settings.metric);
+            index_ref[remove.1 as usize] = false;
+            let mut edge_set:Vec<Vector2<u32>> = Vec::new();
+            for edge in &v1.edge_set {
+                if edge[0] == remove.0 {
+                    if edge[1] == remove.1 {
+                        continue;
+                    } else {
+                        edge_set.push(*edge);
+                    }
+                } else if edge[1] == remove.0 {
+                    if edge[0] == remove.1 {
+                        continue;
+                    } else {
+                        edge_set.push(*edge);
+                    }
+                } else if edge[0] == remove.1 {
+                    edge_set.push(Vector2::new(remove.0, edge[1]));
+                } else if edge[1] == remove.1 {
+                    edge_set.push(Vector2::new(edge[0], remove.0));
+                } else {
+                    edge_set.push(*edge);
+                }
+            }
+            for edge in &v2.edge_set {
+                if edge[0] == remove.0 {
+                    if edge[1] == remove.1 {
+                        continue;
+                    } else {
+                        edge_set.push(*edge);
+                    }
+                } else if edge[1] == remove.0 {
+                    if edge[0] == remove.1 {
+                        continue;
+                    } else {
+                        edge_set.push(*edge);
+                    }
+                } else if edge[0] == remove.1 {
+                    edge_set.push(Vector2::new(remove.0, edge[1]));
+                } else if edge[1] == remove.1 {
+                    edge_set.push(Vector2::new(edge[0], remove.0));
+                } else {
+                    edge_set.push(*edge);
+                }
+            }
+            let mut face_set:Vec<Vector3<u32>> = Vec::new();
+            for face in &v1.face_set {
+                if face[0] == remove.1 {
+                    if face[1] == remove.0 || face[2] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(remove.0, face[1], face[2]));
+                    }
+                } else if face[1] == remove.1 {
+                    if face[0] == remove.0 || face[2] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(face[0], remove.0, face[2]));
+                    }
+                } else if face[2] == remove.1 {
+                    if face[0] == remove.0 || face[1] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(face[0], face[1], remove.0));
+                    }
+                } else {
+                    face_set.push(*face);
+                }
+            }
+            for face in &v2.face_set {
+                if face[0] == remove.1 {
+                    if face[1] == remove.0 || face[2] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(remove.0, face[1], face[2]));
+                    }
+                } else if face[1] == remove.1 {
+                    if face[0] == remove.0 || face[2] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(face[0], remove.0, face[2]));
+                    }
+                } else if face[2] == remove.1 {
+                    if face[0] == remove.0 || face[1] == remove.0 {
+                        continue;
+                    } else {
+                        face_set.push(Vector3::new(face[0], face[1], remove.0));
+                    }
+                } else {
+                    face_set.push(*face);
+                }
+            }
+            vertex_list.remove(&remove.0);
+            vertex_list.remove(&remove.1);
+            vertex_list.insert(remove.0, Vertex::new(edge_set, face_set, q_matrix, target_q));
+            // position
+            position_list.remove(&remove.0);
+            position_list.remove(&remove.1);
+            position_list.insert(remove.0, new_p);
+
+            // normal
+            // Correct later
+            merge_attribute(&mut normal_list, remove.0, remove.1);
+            // tangent
+            //let t1 = tangent_list.get(&remove.0).unwrap();
+            //let t2 = tangent_list.get(&remove.1).unwrap();
+            // texcoord_0
+            merge_attribute(&mut texcoord_0_list, remove.0, remove.1);
+            // morph targets follow the base mesh through the collapse
+            for target in target_list.iter_mut() {
+                merge_attribute(&mut target.position, remove.0, remove.1);
+                merge_attribute(&mut target.normal, remove.0, remove.1);
+                merge_attribute(&mut target.tangent, remove.0, remove.1);
+            }
+            // index
+            let mut in_list = Vec::new();
+            for index in index_list.iter_mut() {
+                if *index == remove.1 {
+                    *index = remove.0;
+                }
+            }
+            for triangle in index_list.chunks_exact(3) {
+                // Triangles with two corners on the collapsed vertex are gone
+                if triangle.iter().filter(|i| **i == remove.0).count() >= 2 {
+                    goal = goal.saturating_sub(1);
+                } else {
+                    in_list.extend_from_slice(triangle);
+                }
+            }
+            index_list.clear();
+            index_list = in_list;
+
+            // Update new cost
+            let mut temp_list: BinaryHeap<Reverse<Remove>> = BinaryHeap::new();
+            while let Some(mut value) = remove_list.pop() {
+                if value.0.0 == remove.1 {
+                    if value.0.1 == remove.0 {
+                        continue;
+                    } else {
+                        value.0.0 = remove.0;
+                        if index_ref[value.0.0 as usize] && index_ref[value.0.1 as usize] {
+                            let (u, v) = (value.0.0.min(value.0.1), value.0.0.max(value.0.1));
+                            temp_list.push(Reverse(Remove(u, v, edge_cost(u, v, &index_list, &position_list, &vertex_list, &target_list, settings))));
+                        }
+                    }
+                } else if value.0.1 == remove.1 {
+                    if value.0.0 == remove.0 {
+                        continue;
+                    } else {
+                        value.0.1 = remove.0;
+                        if index_ref[value.0.0 as usize] && index_ref[value.0.1 as usize]  {
+                            let (u, v) = (value.0.0.min(value.0.1), value.0.0.max(value.0.1));
+                            temp_list.push(Reverse(Remove(u, v, edge_cost(u, v, &index_list, &position_list, &vertex_list, &target_list, settings))));
+                        }
+                    }
+                } else if value.0.0 == remove.0 || value.0.1 == remove.0 {
+                    if index_ref[value.0.0 as usize] && index_ref[value.0.1 as usize]  {
+                        let (u, v) = (value.0.0.min(value.0.1), value.0.0.max(value.0.1));
+                        temp_list.push(Reverse(Remove(u, v, edge_cost(u, v, &index_list, &position_list, &vertex_list, &target_list, settings))));
+                    }
+                } else {
+                    if index_ref[value.0.0 as usize] && index_ref[value.0.1 as usize]  {
+                        temp_list.push(value);
+                    }
+                }
+            }
+            remove_list = temp_list;
+        }
+        settings.progress.step(left - goal, error.max(0.0).sqrt() * scale);
+    }
+    settings.progress.finish(goal);
+    stats.collapse += start.elapsed();
+    // Finished decimation
+
+    // Vertices that did not move get back their exact position, which the way to the center and back could round
+    for (i, p) in position_list.iter_mut() {
+        *p = match original.get(i) {
+            Some(o) if *p == o - center => *o,
+            _ => *p + center,
+        };
+    }
+    *attributes = Attributes { index_list, normal_list, position_list, tangent_list, texcoord_0_list, target_list };
+    (error, stats)
+}
+
+// Write a simplified primitive back into the glTF and return its report
+fn write_primitive(json:&mut Value, binary_chunk:&mut Vec<u8>, at:(usize, usize), simplified:Simplified, options:&Options,
+                    filters:&mut HashMap<usize, &'static str>)
+                    -> io::Result<(usize, usize, f32, Option<f64>, Stats)> {
+    // Write the primitive back
+    trace!(target: "write", "{}", serde_json::to_string_pretty(&json).unwrap());
+    let start = Instant::now();
+    own_accessors(json, at);
+    repack_gltf(json, binary_chunk, at, &simplified, options, filters)?;
+    let Simplified { before, after, error, volume, mut stats, .. } = simplified;
+    stats.repack += start.elapsed();
+    Ok((before, after, error, volume, stats))
+}
+
+// Write the decimated primitive into new bufferViews at the end of the binary chunk.
+// Meshopt filters are noted per accessor for write_glb.
+fn repack_gltf(json:&mut Value, binary_chunk:&mut Vec<u8>, (mesh, primitive):(usize, usize), simplified:&Simplified,
+                options:&Options, filters:&mut HashMap<usize, &'static str>) -> io::Result<()> {
+    let Attributes { index_list, normal_list, position_list, texcoord_0_list, target_list, .. } = &simplified.attributes;
+    let primitives = &simplified.primitives;
+    let mut index_ref = vec![true; position_list.keys().max().map_or(0, |i| *i as usize + 1)];
+    let (sparse, quantize, meshopt, draco) = (options.sparse, options.quantize, options.meshopt, options.draco);
+
+    // edit json part
+    let index = if let Some(index) = json["meshes"][mesh]["primitives"][primitive]["indices"].as_i64(){
+        index
+    } else {
+        debug!(target: "write", "No Indices");
+        -1
+    };
+    let normal = if let Some(normal) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["NORMAL"].as_i64(){
+        normal
+    } else {
+        debug!(target: "write", "No NORMAL");
+        -1
+    };
+    let position = if let Some(position) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["POSITION"].as_i64(){
+        position
+    } else {
+        debug!(target: "write", "No POSITION");
+        -1
+    };
+    let texcoord_0 = if let Some(texcoord_0) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["TEXCOORD_0"].as_i64(){
+        texcoord_0
+    } else {
+        debug!(target: "write", "No TEXCOORD_0");
+        -1
+    };
+
+    let indices = primitives.get("indices").unwrap();
+    let triangles = json["meshes"][mesh]["primitives"][primitive]["mode"].as_u64().unwrap_or(4) == 4;
+    if !triangles && draco.is_some() {
+        warn!(target: "write", "Draco only compresses triangles: --draco is ignored for points and lines");
+    }
+    let draco = if triangles { draco } else { None };
+    if quantize && draco.is_some() {
+        warn!(target: "write", "Draco quantizes the attributes itself: --quantize is ignored");
+    }
+    let quantize = quantize && draco.is_none();
+
+    // write the binary part
+    let mut new_index_list: Vec<u32> = Vec::new();
+    let mut new_index = HashMap::new();
+    let mut new_index_ref = HashMap::new();
+    // create the new index reference
+    index_ref.fill(false);
+    for i in index_list {
+        index_ref[*i as usize] = true;
+    }
+    for (k, i) in (0..index_ref.len()).filter(|i| index_ref[*i]).enumerate() {
+        new_index.insert(i, k as u32);
+        new_index_ref.insert(k as u32, i);
+    }
+    for i in index_list {
+        new_index_list.push(new_index[&(*i as usize)]);
+    }
+    let index_number = index_list.iter().collect::<HashSet<_>>().len() as u32;
+    trace!(target: "write", "index list: {}, {:?}", index_list.len(), index_list);
+    trace!(target: "write", "position list: {}, {:?}", position_list.len(), position_list);
+    trace!(target: "write", "new index ref: {:?}", new_index_ref);
+    trace!(target: "write", "new index list: {:?}", new_index_list);
+    trace!(target: "write", "index number: {:?}", index_number);
+
+    // New content of every accessor we rewrite, keyed by its bufferView
+    let mut view_data: HashMap<u32, Vec<u8>> = HashMap::new();
+    debug!(target: "write", "indices to binary");
+    let mut index_data = Vec::new();
+    for i in &new_index_list {
+        match indices.component_type {
+            5121 => index_data.extend_from_slice(&(*i as u8).to_le_bytes()),
+            5123 => index_data.extend_from_slice(&(*i as u16).to_le_bytes()),
+            5125 => index_data.extend_from_slice(&i.to_le_bytes()),
+            5120 => index_data.extend_from_slice(&(*i as i8).to_le_bytes()),
+            5122 => index_data.extend_from_slice(&(*i as i16).to_le_bytes()),
+            component_type => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                        format!("Indices of component type {}", component_type))),
+        }
+    }
+    view_data.insert(rewrite_accessor(json, index, index_list.len() as u32), index_data);
+    // Quantized attributes are only declared once something actually got quantized
+    let mut quantized = false;
+    if normal != -1 {
+        debug!(target: "write", "NORMAL to binary");
+        let view = rewrite_accessor(json, normal, index_number);
+        if quantize {
+            // 8-bit snorm, padded to 4 bytes per element
+            let mut elements = Vec::with_capacity(index_number as usize);
+            for i in 0..index_number {
+                let n = normal_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                let n = if n.norm() > 0.0 { n.normalize() } else { *n };
+                elements.push(vec![(n.x * 127.0).round(), (n.y * 127.0).round(), (n.z * 127.0).round()]);
+            }
+            view_data.insert(view, integer_bytes(&elements, 5120)?);
+            if meshopt {
+                // Stored as octahedral coordinates if the view gets compressed, the decoder rebuilds the snorm vector
+                filters.insert(normal as usize, "OCTAHEDRAL");
+            }
+            set_component_type(json, normal, view, 5120, true, 3);
+            quantized = true;
+        } else {
+            view_data.insert(view, vec3_bytes(normal_list, &new_index_ref, index_number));
+            set_component_type(json, normal, view, 5126, false, 3);
+        }
+    }
+    // Offset and uniform scale of the quantized positions, given back to the nodes
+    let mut position_transform = None;
+    if position != -1 {
+        debug!(target: "write", "POSITION to binary");
+        let view = rewrite_accessor(json, position, index_number);
+        // The dequantization sits on the nodes, which every primitive of the mesh shares
+        let shared = json["meshes"][mesh]["primitives"].as_array().map_or(0, |primitives| primitives.len()) > 1;
+        // and a mesh no node uses would have none to carry it
+        let instanced = json["nodes"].as_array().into_iter().flatten().any(|node| node["mesh"].as_u64() == Some(mesh as u64));
+        let skinned = mesh_is_skinned(json, mesh as u64);
+        if quantize && skinned {
+            info!(target: "write", "Skinned mesh: positions are not quantized");
+        } else if quantize && shared {
+            info!(target: "write", "Mesh with several primitives: positions are not quantized");
+        } else if quantize && !instanced {
+            info!(target: "write", "Mesh without a node: positions are not quantized");
+        }
+        if quantize && !skinned && !shared && instanced {
+            // 16-bit unsigned integers on a uniform grid over the bounding box
+            let mut min = Vector3::repeat(f32::MAX);
+            let mut max = Vector3::repeat(f32::MIN);
+            for i in 0..index_number {
+                let p = position_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                min = min.inf(p);
+                max = max.sup(p);
+            }
+            let extent = (max - min).max();
+            let scale = if extent > 0.0 { extent / 65535.0 } else { 1.0 };
+            let mut grid_list = HashMap::new();
+            let mut elements = Vec::with_capacity(index_number as usize);
+            for i in 0..index_number {
+                let old = *new_index_ref.get(&i).unwrap() as u32;
+                let q = ((position_list.get(&old).unwrap() - min) / scale).map(|x| x.round());
+                grid_list.insert(old, q);
+                elements.push(vec![q.x, q.y, q.z]);
+            }
+            view_data.insert(view, integer_bytes(&elements, 5123)?);
+            set_component_type(json, position, view, 5123, false, 3);
+            set_min_max(json, position, &grid_list, &new_index_ref, index_number);
+            position_transform = Some((min, scale));
+            quantized = true;
+        } else {
+            view_data.insert(view, vec3_bytes(position_list, &new_index_ref, index_number));
+            set_component_type(json, position, view, 5126, false, 3);
+            set_min_max(json, position, position_list, &new_index_ref, index_number);
+        }
+    }
+    if texcoord_0 != -1 {
+        debug!(target: "write", "TEXCOORD to binary");
+        let view = rewrite_accessor(json, texcoord_0, index_number);
+        let mut in_range = true;
+        for i in 0..index_number {
+            let tex0 = texcoord_0_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+            in_range = in_range && tex0.min() >= 0.0 && tex0.max() <= 1.0;
+        }
+        if quantize && !in_range {
+            info!(target: "write", "TEXCOORD_0 outside of [0, 1]: kept as f32");
+        }
+        if quantize && in_range {
+            // 16-bit unorm
+            let mut elements = Vec::with_capacity(index_number as usize);
+            for i in 0..index_number {
+                let tex0 = texcoord_0_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                elements.push(vec![(tex0.x * 65535.0).round(), (tex0.y * 65535.0).round()]);
+            }
+            view_data.insert(view, integer_bytes(&elements, 5123)?);
+            set_component_type(json, texcoord_0, view, 5123, true, 2);
+            quantized = true;
+        } else {
+            let mut data = Vec::new();
+            for i in 0..index_number {
+                let tex0 = texcoord_0_list.get(&(*new_index_ref.get(&i).unwrap() as u32));
+                data.extend_from_slice(&tex0.unwrap().x.to_le_bytes());
+                data.extend_from_slice(&tex0.unwrap().y.to_le_bytes());
+            }
+            view_data.insert(view, data);
+            set_component_type(json, texcoord_0, view, 5126, false, 2);
+        }
+    }
+    // Other attributes (tangents, colors, extra UV sets, skinning) are carried over as stored
+    let attributes = json["meshes"][mesh]["primitives"][primitive]["attributes"].as_object().cloned().unwrap_or_default();
+    for (name, accessor) in attributes {
+        let accessor = accessor.as_i64().unwrap();
+        if [normal, position, texcoord_0].contains(&accessor) {
+            continue;
+        }
+        debug!(target: "write", "{} to binary", name);
+        let values = read_accessor(json, accessor as usize, binary_chunk);
+        let component_type = json["accessors"][accessor as usize]["componentType"].as_u64().unwrap() as u32;
+        let normalized = json["accessors"][accessor as usize]["normalized"].as_bool().unwrap_or(false);
+        let n = type_size(json["accessors"][accessor as usize]["type"].as_str().unwrap());
+        let elements: Vec<Vec<f32>> = (0..index_number).map(|i| {
+            let x = *new_index_ref.get(&i).unwrap() * n;
+            values[x..x+n].iter().map(|c| *c as f32).collect()
+        }).collect();
+        let view = rewrite_accessor(json, accessor, index_number);
+        if component_type == 5126 {
+            view_data.insert(view, elements.iter().flatten().flat_map(|c| c.to_le_bytes()).collect());
+        } else {
+            view_data.insert(view, integer_bytes(&elements, component_type)?);
+        }
+        set_component_type(json, accessor, view, component_type, normalized, n);
+    }
+    // Morph targets share the vertex order of the base attributes
+    if let Some(targets) = json["meshes"][mesh]["primitives"][primitive]["targets"].as_array().cloned() {
+        for (t, target) in targets.iter().enumerate() {
+            let lists = [("POSITION", &target_list[t].position),
+                        ("NORMAL", &target_list[t].normal),
+                        ("TANGENT", &target_list[t].tangent)];
+            for (name, list) in lists {
+                if let Some(accessor) = target[name].as_i64() {
+                    debug!(target: "write", "targets[{}].{} to binary", t, name);
+                    // Position deltas live in the same quantized space as the base positions
+                    let mut grid_list = HashMap::new();
+                    let list = match position_transform {
+                        Some((_, scale)) if name == "POSITION" => {
+                            for (i, delta) in list {
+                                grid_list.insert(*i, delta / scale);
+                            }
+                            &grid_list
+                        }
+                        _ => list,
+                    };
+                    let sparse_data = if sparse { sparse_vec3(list, &new_index_ref, index_number) } else { None };
+                    let view = rewrite_accessor(json, accessor, index_number);
+                    match sparse_data {
+                        Some((count, index_type, index_data, value_data)) => {
+                            // Only the non-zero deltas are stored, on top of an implicit zero base
+                            let index_view = new_buffer_view(json);
+                            view_data.insert(view, value_data);
+                            view_data.insert(index_view, index_data);
+                            json["bufferViews"][view as usize].as_object_mut().unwrap().remove("target");
+                            json["accessors"][accessor as usize].as_object_mut().unwrap().remove("bufferView");
+                            json["accessors"][accessor as usize]["sparse"] = json!({
+                                "count": count,
+                                "indices": {"bufferView": index_view, "componentType": index_type},
+                                "values": {"bufferView": view},
+                            });
+                        }
+                        None => {
+                            view_data.insert(view, vec3_bytes(list, &new_index_ref, index_number));
+                        }
+                    }
+                    set_component_type(json, accessor, view, 5126, false, 3);
+                    if name == "POSITION" {
+                        set_min_max(json, accessor, list, &new_index_ref, index_number);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(bits) = draco {
+        debug!(target: "write", "Draco compression");
+        let mut attributes = Vec::new();
+        if position != -1 {
+            let values = (0..index_number).flat_map(|i| {
+                let p = position_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                [p.x, p.y, p.z]
+            }).collect();
+            attributes.push(("POSITION", position, draco::EncodeAttribute {
+                attribute_type: draco::POSITION,
+                unique_id: attributes.len() as u32,
+                num_components: 3,
+                values,
+                quantization_bits: bits.position,
+            }));
+        }
+        if normal != -1 {
+            let values = (0..index_number).flat_map(|i| {
+                let n = normal_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                [n.x, n.y, n.z]
+            }).collect();
+            attributes.push(("NORMAL", normal, draco::EncodeAttribute {
+                attribute_type: draco::NORMAL,
+                unique_id: attributes.len() as u32,
+                num_components: 3,
+                values,
+                quantization_bits: bits.normal,
+            }));
+        }
+        if texcoord_0 != -1 {
+            let values = (0..index_number).flat_map(|i| {
+                let tex0 = texcoord_0_list.get(&(*new_index_ref.get(&i).unwrap() as u32)).unwrap();
+                [tex0.x, tex0.y]
+            }).collect();
+            attributes.push(("TEXCOORD_0", texcoord_0, draco::EncodeAttribute {
+                attribute_type: draco::TEX_COORD,
+                unique_id: attributes.len() as u32,
+                num_components: 2,
+                values,
+                quantization_bits: bits.texcoord,
+            }));
+        }
+        pack_draco(json, &mut view_data, (mesh, primitive), index, &new_index_list, index_number as usize, attributes);
+    }
+    if let Some((offset, scale)) = position_transform {
+        dequantize_nodes(json, mesh as u64, offset, scale);
+    }
+    if quantized {
+        add_extension(json, "KHR_mesh_quantization", true);
+    }
+
+    // Rewritten views move to the end of the binary chunk, write_glb drops the data they leave behind
+    let mut views: Vec<(u32, Vec<u8>)> = view_data.into_iter().collect();
+    views.sort_by_key(|(view, _)| *view);
+    for (view, data) in views {
+        let stride = json["bufferViews"][view as usize]["byteStride"].as_u64().map(|stride| stride as usize);
+        append_view(json, binary_chunk, view, data, stride);
+    }
+    Ok(())
+}
+
+// Lay the bufferViews of the json out in a new binary chunk and write both into filename.
+// Views used by a single accessor are compressed with EXT_meshopt_compression when asked to.
+fn write_glb(mut json:Value, binary_chunk:&[u8], meshopt:bool,
+                filters:&HashMap<usize, &'static str>, filename:&str) -> Result<File, std::io::Error> {
+    let version = 2;
+
+    // Lay the bufferViews out again, copying the ones we did not touch (images...)
+    let mut binary_data = Vec::new();
+    let view_num = json["bufferViews"].as_array().map_or(0, |views| views.len());
+    let meshopt_views = if meshopt { meshopt_views(&json) } else { HashMap::new() };
+    // Compressed views keep their uncompressed layout in a fallback buffer without data
+    let fallback = json["buffers"].as_array().map_or(1, |buffers| buffers.len());
+    let mut fallback_length = 0;
+    for i in 0..view_num {
+        while binary_data.len() % 4 != 0 {
+            binary_data.push(0);
+        }
+        let off = json["bufferViews"][i]["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let len = json["bufferViews"][i]["byteLength"].as_u64().unwrap_or(0) as usize;
+        let data = binary_chunk[off..off+len].to_vec();
+        if let Some((mode, stride, accessor)) = meshopt_views.get(&(i as u32)) {
+            // A filter only applies to the compressed data, the view keeps its plain values otherwise
+            let filter = filters.get(accessor).copied().unwrap_or("NONE");
+            let filtered = match filter {
+                "OCTAHEDRAL" => meshopt::encode_octahedral(&data.chunks_exact(4)
+                    .map(|e| [e[0] as i8 as f32 / 127.0, e[1] as i8 as f32 / 127.0, e[2] as i8 as f32 / 127.0])
+                    .collect::<Vec<_>>()),
+                _ => data.clone(),
+            };
+            match meshopt::encode(&filtered, *stride, mode) {
+                Ok(compressed) => {
+                    json["bufferViews"][i]["buffer"] = json!(fallback);
+                    json["bufferViews"][i]["byteOffset"] = json!(fallback_length);
+                    json["bufferViews"][i]["byteLength"] = json!(data.len());
+                    json["bufferViews"][i]["extensions"]["EXT_meshopt_compression"] = json!({
+                        "buffer": 0,
+                        "byteOffset": binary_data.len(),
+                        "byteLength": compressed.len(),
+                        "byteStride": stride,
+                        "count": data.len() / stride,
+                        "mode": mode,
+                    });
+                    if filter != "NONE" {
+                        json["bufferViews"][i]["extensions"]["EXT_meshopt_compression"]["filter"] = json!(filter);
+                    }
+                    fallback_length += data.len().div_ceil(4) * 4;
+                    binary_data.extend_from_slice(&compressed);
+                    continue;
+                }
+                Err(error) => warn!(target: "write", "bufferView {} is left uncompressed: {}", i, error),
+            }
+        }
+        json["bufferViews"][i]["byteOffset"] = json!(binary_data.len());
+        json["bufferViews"][i]["byteLength"] = json!(data.len());
+        binary_data.extend_from_slice(&data);
+    }
+    if fallback_length > 0 {
+        json["buffers"].as_array_mut().unwrap().push(json!({
+            "byteLength": fallback_length,
+            "extensions": {"EXT_meshopt_compression": {"fallback": true}},
+        }));
+        add_extension(&mut json, "EXT_meshopt_compression", true);
+    }
+    while binary_data.len() % 4 != 0 {
+        binary_data.push(0);
+    }
+    json["buffers"][0]["byteLength"] = json!(binary_data.len());
+
+    // Reorder the json data back to origin
+    // Convert JSON object into an IndexMap
+    let mut index_map = IndexMap::new();
+    if let Value::Object(map) = &json {
+        for (key, value) in map {
+            index_map.insert(key, value);
+        }
+    }
+    
+    // Reorder the IndexMap according to a specific order of keys
+    let mut ordered_index_map = IndexMap::new();
+    for key in ["asset", "scene", "scenes", "nodes", "materials", "meshes", "accessors", "bufferViews", "buffers"] {
+        if let Some((_index, _key, value)) = index_map.swap_remove_full(&String::from(key)) {
+            ordered_index_map.insert((*key).to_string(), value); // Convert borrowed reference to owned String
+        }
+    }
+    // Keep the remaining keys (animations, images, extensions...) after the known ones
+    if let Value::Object(map) = &json {
+        for (key, value) in map {
+            if !ordered_index_map.contains_key(key) {
+                ordered_index_map.insert(key.clone(), value);
+            }
+        }
+    }
+
+    // Convert the ordered IndexMap back into a JSON object
+    let mut new_json = Map::new();
+    for (key, value) in ordered_index_map.clone() {
+        debug!(target: "write", "insert {} to json", key);
+        new_json.insert(key, value.clone());
+    }
+
+    trace!(target: "write", "{}", serde_json::to_string_pretty(&new_json).unwrap());
+    let json_data = &to_vec(&new_json).unwrap();
+    let json_chunk_length = json_data.len() as u32;
+
+    trace!(target: "write", "binary data: {:?}", binary_data);
+    let binary_chunk_length = binary_data.len() as u32;
+    let total_length = 12 + 8 + json_chunk_length + 8 + binary_chunk_length;
+
+    let file = write_file(filename, version, total_length, json_chunk_length, 
+                            json_data, binary_chunk_length, &binary_data);
+    debug!(target: "write", "total file size: {} and {} = {}", binary_data.len(), json_data.len(), total_length);
+    debug!(target: "write", "length of the file: {:?}", &file);
+    file
+}
+
+// Views that can be written with EXT_meshopt_compression, with their mode, element size and accessor:
+// the ones holding exactly one vertex attribute or the indices of a primitive.
+fn meshopt_views(json:&Value) -> HashMap<u32, (&'static str, usize, usize)> {
+    // Primitive mode of every indices accessor
+    let mut indices: HashMap<usize, u64> = HashMap::new();
+    for mesh in json["meshes"].as_array().into_iter().flatten() {
+        for primitive in mesh["primitives"].as_array().into_iter().flatten() {
+            if let Some(index) = primitive["indices"].as_u64() {
+                indices.insert(index as usize, primitive["mode"].as_u64().unwrap_or(4));
+            }
+        }
+    }
+    let mut users: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (a, accessor) in json["accessors"].as_array().into_iter().flatten().enumerate() {
+        if let Some(view) = accessor["bufferView"].as_u64() {
+            users.entry(view).or_default().push(a);
+        }
+        if let Some(view) = accessor["sparse"]["values"]["bufferView"].as_u64() {
+            users.entry(view).or_default().push(usize::MAX);
+        }
+    }
+    let mut views = HashMap::new();
+    for (view, accessors) in users {
+        if accessors.len() != 1 || accessors[0] == usize::MAX {
+            continue;
+        }
+        let accessor = &json["accessors"][accessors[0]];
+        let component_type = accessor["componentType"].as_u64().unwrap() as u32;
+        let element_size = type_size(accessor["type"].as_str().unwrap()) * component_size(component_type);
+        let stride = json["bufferViews"][view as usize]["byteStride"].as_u64().map_or(element_size, |s| s as usize);
+        if let Some(mode) = indices.get(&accessors[0]) {
+            if component_type != 5123 && component_type != 5125 {
+                continue;
+            }
+            let triangles = *mode == 4 && accessor["count"].as_u64().unwrap_or(0).is_multiple_of(3);
+            views.insert(view as u32, (if triangles { "TRIANGLES" } else { "INDICES" }, element_size, accessors[0]));
+        } else if stride.is_multiple_of(4) && stride <= 256 && json["bufferViews"][view as usize]["target"] != 34963
+                    && !json["bufferViews"][view as usize]["extensions"].is_object() {
+            views.insert(view as u32, ("ATTRIBUTES", stride, accessors[0]));
+        }
+    }
+    views
+}
+
+// Replace the indices and attributes of the primitive with a KHR_draco_mesh_compression buffer.
+// The Draco data takes over the view of the indices; the attribute views are dropped.
+fn pack_draco(json:&mut Value, view_data:&mut HashMap<u32, Vec<u8>>, (mesh, primitive):(usize, usize), index:i64, index_list:&[u32], num_points:usize,
+                attributes:Vec<(&str, i64, draco::EncodeAttribute)>) {
+    let faces: Vec<[u32; 3]> = index_list.chunks(3).map(|face| [face[0], face[1], face[2]]).collect();
+    let (names, encode_attributes): (Vec<(&str, i64)>, Vec<draco::EncodeAttribute>) = attributes.into_iter()
+        .map(|(name, accessor, attribute)| ((name, accessor), attribute)).unzip();
+    let data = match draco::encode(&faces, num_points, &encode_attributes) {
+        Ok(data) => data,
+        Err(error) => {
+            warn!(target: "write", "Draco compression failed, the primitive is left uncompressed: {}", error);
+            return;
+        }
+    };
+    info!(target: "write", "Draco buffer: {} bytes", data.len());
+
+    let view = json["accessors"][index as usize]["bufferView"].as_u64().unwrap() as u32;
+    view_data.insert(view, data);
+    if let Some(view) = json["bufferViews"][view as usize].as_object_mut() {
+        view.remove("byteStride");
+        view.remove("target");
+    }
+    json["accessors"][index as usize].as_object_mut().unwrap().remove("bufferView");
+    let mut draco_attributes = Map::new();
+    for ((name, accessor), attribute) in names.iter().zip(&encode_attributes) {
+        if let Some(view) = json["accessors"][*accessor as usize].as_object_mut().unwrap().remove("bufferView") {
+            view_data.remove(&(view.as_u64().unwrap() as u32));
+        }
+        draco_attributes.insert(name.to_string(), json!(attribute.unique_id));
+    }
+    json["meshes"][mesh]["primitives"][primitive]["extensions"]["KHR_draco_mesh_compression"] = json!({
+        "bufferView": view,
+        "attributes": draco_attributes,
+    });
+    add_extension(json, "KHR_draco_mesh_compression", true);
+    remove_unused_views(json, view_data);
+}
+
+// Drop the bufferViews nothing refers to any more, renumbering the references and the rewritten view data
+fn remove_unused_views(json:&mut Value, view_data:&mut HashMap<u32, Vec<u8>>) {
+    fn references(value:&mut Value, visit:&mut dyn FnMut(&mut Value)) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    if key == "bufferView" && value.is_u64() {
+                        visit(value);
+                    } else {
+                        references(value, visit);
+                    }
+                }
+            }
+            Value::Array(list) => list.iter_mut().for_each(|value| references(value, visit)),
+            _ => {}
+        }
+    }
+    let view_num = json["bufferViews"].as_array().map_or(0, |views| views.len());
+    let mut used = vec![false; view_num];
+    references(json, &mut |view| used[view.as_u64().unwrap() as usize] = true);
+    let mut new_view = Vec::with_capacity(view_num);
+    let mut k = 0;
+    for u in &used {
+        new_view.push(k);
+        if *u {
+            k += 1;
+        }
+    }
+    references(json, &mut |view| *view = json!(new_view[view.as_u64().unwrap() as usize]));
+    let mut i = 0;
+    json["bufferViews"].as_array_mut().unwrap().retain(|_| {
+        i += 1;
+        used[i - 1]
+    });
+    *view_data = view_data.drain().filter(|(view, _)| used[*view as usize])
+        .map(|(view, data)| (new_view[view as usize], data)).collect();
+}
+
+fn write_file(filename: &str, version: u32, total_length: u32, json_chunk_length: u32, 
+                json_data: &[u8], binary_chunk_length: u32, binary_data: &[u8]) -> io::Result<File> {
+    let mut file = File::create(filename)?;
+    
+    // Write header
+    file.write_all(&0x46546C67u32.to_le_bytes())?; // Magic number "glTF"
+    file.write_all(&version.to_le_bytes())?;       // Version
+    file.write_all(&total_length.to_le_bytes())?;  // Total length
+
+    // Write JSON chunk length and data
+    file.write_all(&json_chunk_length.to_le_bytes())?; // JSON chunk length
+    file.write_all(b"JSON")?;                          // JSON chunk type
+    file.write_all(json_data)?;                         // JSON data
+
+    // Write binary chunk length and data
+    file.write_all(&binary_chunk_length.to_le_bytes())?; // Binary chunk length
+    file.write_all(b"BIN\x00")?;                         // Binary chunk type
+    file.write_all(binary_data)?;                        // Binary data
+
+    Ok(file)
+}
+
+// Serialize a VEC3 attribute in the new vertex order, missing entries (e.g. sparse deltas) as zero
+fn vec3_bytes(list:&HashMap<u32, Vector3<f32>>, new_index_ref:&HashMap<u32, usize>, index_number:u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity(index_number as usize * 12);
+    for i in 0..index_number {
+        let v = list.get(&(*new_index_ref.get(&i).unwrap() as u32)).copied().unwrap_or(Vector3::zeros());
+        data.extend_from_slice(&v.x.to_le_bytes());
+        data.extend_from_slice(&v.y.to_le_bytes());
+        data.extend_from_slice(&v.z.to_le_bytes());
+    }
+    data
+}
+
+// Point an accessor at the bufferView its new content will be written to, creating one if it had none
+fn rewrite_accessor(json:&mut Value, accessor:i64, count:u32) -> u32 {
+    // A view of its own, the old one may hold other accessors
+    let target = json["accessors"][accessor as usize]["bufferView"].as_u64()
+        .and_then(|view| json["bufferViews"][view as usize].get("target").cloned());
+    let view = new_buffer_view(json);
+    if let Some(target) = target {
+        json["bufferViews"][view as usize]["target"] = target;
+    }
+    json["accessors"][accessor as usize]["count"] = json!(count);
+    json["accessors"][accessor as usize]["bufferView"] = json!(view);
+    if let Some(accessor) = json["accessors"][accessor as usize].as_object_mut() {
+        accessor.remove("byteOffset");
+        accessor.remove("sparse");
+    }
+    view
+}
+
+// Give a primitive accessors of its own before they are rewritten: an accessor that another primitive, or another
+// attribute or morph target of this one, also uses is copied
+fn own_accessors(json:&mut Value, (mesh, primitive):(usize, usize)) {
+    fn accessors(primitive:&mut Value, visit:&mut dyn FnMut(&mut Value)) {
+        for (key, value) in primitive.as_object_mut().into_iter().flatten() {
+            match key.as_str() {
+                "indices" => visit(value),
+                "attributes" => value.as_object_mut().into_iter().flatten().for_each(|(_, accessor)| visit(accessor)),
+                "targets" => value.as_array_mut().into_iter().flatten().filter_map(Value::as_object_mut).flatten()
+                    .for_each(|(_, accessor)| visit(accessor)),
+                _ => {}
+            }
+        }
+    }
+    let mut uses: HashMap<u64, usize> = HashMap::new();
+    for mesh in json["meshes"].as_array_mut().into_iter().flatten() {
+        for primitive in mesh["primitives"].as_array_mut().into_iter().flatten() {
+            accessors(primitive, &mut |accessor| *uses.entry(accessor.as_u64().unwrap()).or_default() += 1);
+        }
+    }
+    let mut prim = json["meshes"][mesh]["primitives"][primitive].take();
+    let list = json["accessors"].as_array_mut().unwrap();
+    accessors(&mut prim, &mut |accessor| {
+        let uses = uses.get_mut(&accessor.as_u64().unwrap()).unwrap();
+        if *uses > 1 {
+            *uses -= 1;
+            list.push(list[accessor.as_u64().unwrap() as usize].clone());
+            debug!(target: "write", "Accessor {} is shared, copied into {}", accessor, list.len() - 1);
+            *accessor = json!(list.len() - 1);
+        }
+    });
+    json["meshes"][mesh]["primitives"][primitive] = prim;
+}
+
+// Set the storage of a rewritten vertex attribute, with the stride its padded elements need
+fn set_component_type(json:&mut Value, accessor:i64, view:u32, component_type:u32, normalized:bool, components:usize) {
+    json["accessors"][accessor as usize]["componentType"] = json!(component_type);
+    if normalized {
+        json["accessors"][accessor as usize]["normalized"] = json!(true);
+    } else if let Some(accessor) = json["accessors"][accessor as usize].as_object_mut() {
+        accessor.remove("normalized");
+    }
+    let size = components * component_size(component_type);
+    if !size.is_multiple_of(4) && json["accessors"][accessor as usize]["bufferView"].as_u64() == Some(view as u64) {
+        json["bufferViews"][view as usize]["byteStride"] = json!(size.div_ceil(4) * 4);
+    }
+}
+
+// Serialize quantized components as integers, each element padded to a multiple of 4 bytes
+fn integer_bytes(elements:&Vec<Vec<f32>>, component_type:u32) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    for element in elements {
+        for c in element {
+            match component_type {
+                5120 => data.extend_from_slice(&(*c as i8).to_le_bytes()),
+                5121 => data.extend_from_slice(&(*c as u8).to_le_bytes()),
+                5122 => data.extend_from_slice(&(*c as i16).to_le_bytes()),
+                5123 => data.extend_from_slice(&(*c as u16).to_le_bytes()),
+                5125 => data.extend_from_slice(&(*c as u32).to_le_bytes()),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                               format!("Unsupported integer component type {}", component_type))),
+            }
+        }
+        while !data.len().is_multiple_of(4) {
+            data.push(0);
+        }
+    }
+    Ok(data)
+}
+
+// Weight of every vertex from a channel of an attribute, the largest one among the vertices at the same position.
+// Empty when the primitive does not have the attribute, unless it is required.
+fn vertex_weights(json:&Value, binary_chunk:&[u8], (mesh, primitive):(usize, usize), weight:&str, required:bool,
+                    position_list:&HashMap<u32, Vector3<f32>>) -> io::Result<HashMap<u32, f32>> {
+    let (name, channel) = weight.split_once('.').unwrap_or((weight, "0"));
+    let accessor = match json["meshes"][mesh]["primitives"][primitive]["attributes"][name].as_u64() {
+        Some(accessor) => accessor as usize,
+        None if required => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                format!("Mesh {} primitive {} has no {} attribute for the weights", mesh, primitive, name))),
+        None => return Ok(HashMap::new()),
+    };
+    let n = type_size(json["accessors"][accessor]["type"].as_str().unwrap());
+    let component = match channel {
+        "0" | "r" | "x" => 0,
+        "1" | "g" | "y" => 1,
+        "2" | "b" | "z" => 2,
+        "3" | "a" | "w" => 3,
+        _ => n,
+    };
+    if component >= n {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("The {} attribute of mesh {} primitive {} has no channel {}", name, mesh, primitive, channel)));
+    }
+    let values = read_values(json, accessor, binary_chunk);
+    let key = |p:&Vector3<f32>| [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+    let mut by_position: HashMap<[u32; 3], f32> = HashMap::new();
+    for (i, p) in position_list {
+        let w = by_position.entry(key(p)).or_insert(0.0);
+        *w = w.max(values[*i as usize * n + component] as f32);
+    }
+    let weights: HashMap<u32, f32> = position_list.iter().map(|(i, p)| (*i, by_position[&key(p)])).collect();
+    info!(target: "decimate", "Weights from {}: {} vertices weighted, {} locked", weight,
+                weights.values().filter(|w| **w > 0.0).count(), weights.values().filter(|w| **w >= 1.0).count());
+    Ok(weights)
+}
+
+// Largest axis scale of the instances of the mesh in the scenes, 1 when no node uses it
+fn world_scale(json:&Value, mesh:u64) -> f32 {
+    let instances = mesh_instances(json, mesh);
+    let scale = instances.iter()
+        .map(|world| (0..3).map(|c| world.fixed_view::<3, 1>(0, c).norm()).fold(0.0, f32::max))
+        .fold(0.0, f32::max);
+    if instances.is_empty() || scale == 0.0 {
+        return 1.0;
+    }
+    info!(target: "decimate", "Mesh {} has {} instances, the largest one is scaled by {}", mesh, instances.len(), scale);
+    scale
+}
+
+// World transform of every node using the mesh in the scenes
+fn mesh_instances(json:&Value, mesh:u64) -> Vec<Matrix4<f32>> {
+    let node_num = json["nodes"].as_array().map_or(0, |nodes| nodes.len());
+    let mut roots: Vec<usize> = json["scenes"].as_array().into_iter().flatten()
+        .flat_map(|scene| scene["nodes"].as_array().into_iter().flatten().filter_map(|n| n.as_u64()))
+        .map(|n| n as usize).collect();
+    if json["scenes"].as_array().is_none_or(|scenes| scenes.is_empty()) {
+        // Without scenes every node that is nobody's child is a root
+        let children: Vec<u64> = json["nodes"].as_array().into_iter().flatten()
+            .flat_map(|node| node["children"].as_array().into_iter().flatten().filter_map(|c| c.as_u64())).collect();
+        roots = (0..node_num).filter(|n| !children.contains(&(*n as u64))).collect();
+    }
+    let mut stack: Vec<(usize, Matrix4<f32>)> = roots.into_iter().map(|n| (n, Matrix4::identity())).collect();
+    let mut instances = Vec::new();
+    while let Some((n, parent)) = stack.pop() {
+        let node = &json["nodes"][n];
+        let world = parent * node_matrix(node);
+        if node["mesh"].as_u64() == Some(mesh) {
+            instances.push(world);
+        }
+        for child in node["children"].as_array().into_iter().flatten().filter_map(|c| c.as_u64()) {
+            stack.push((child as usize, world));
+        }
+    }
+    instances
+}
+
+// Local transform of a node, from its matrix or its translation, rotation and scale
+fn node_matrix(node:&Value) -> Matrix4<f32> {
+    let numbers = |key:&str| -> Option<Vec<f32>> {
+        node[key].as_array().map(|values| values.iter().map(|v| v.as_f64().unwrap_or(0.0) as f32).collect())
+    };
+    if let Some(matrix) = numbers("matrix") {
+        return Matrix4::from_column_slice(&matrix);
+    }
+    let t = numbers("translation").unwrap_or(vec![0.0; 3]);
+    let r = numbers("rotation").unwrap_or(vec![0.0, 0.0, 0.0, 1.0]);
+    let s = numbers("scale").unwrap_or(vec![1.0; 3]);
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::new(r[3], r[0], r[1], r[2]));
+    Matrix4::new_translation(&Vector3::new(t[0], t[1], t[2])) * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&Vector3::new(s[0], s[1], s[2]))
+}
+
+// Skinned meshes ignore the node transform, so it cannot carry a dequantization
+fn mesh_is_skinned(json:&Value, mesh:u64) -> bool {
+    json["nodes"].as_array().is_some_and(|nodes| {
+        nodes.iter().any(|node| node["mesh"].as_u64() == Some(mesh) && !node["skin"].is_null())
+    })
+}
+
+// Move the mesh of every node using it to a child node that scales and offsets the quantized positions back.
+// Children of the original node keep their transform, morph weights follow the mesh.
+fn dequantize_nodes(json:&mut Value, mesh:u64, offset:Vector3<f32>, scale:f32) {
+    let node_num = json["nodes"].as_array().map_or(0, |nodes| nodes.len());
+    for n in 0..node_num {
+        if json["nodes"][n]["mesh"].as_u64() != Some(mesh) {
+            continue;
+        }
+        let child = json["nodes"].as_array().unwrap().len();
+        let mut child_node = json!({
+            "mesh": mesh,
+            "translation": [offset.x, offset.y, offset.z],
+            "scale": [scale, scale, scale],
+        });
+        let parent = json["nodes"][n].as_object_mut().unwrap();
+        parent.remove("mesh");
+        if let Some(name) = parent.get("name") {
+            child_node["name"] = name.clone();
+        }
+        if let Some(weights) = parent.remove("weights") {
+            child_node["weights"] = weights;
+        }
+        if !parent.contains_key("children") {
+            parent.insert(String::from("children"), json!([]));
+        }
+        parent["children"].as_array_mut().unwrap().push(json!(child));
+        json["nodes"].as_array_mut().unwrap().push(child_node);
+        if let Some(animations) = json.get_mut("animations").and_then(|animations| animations.as_array_mut()) {
+            for animation in animations {
+                if let Some(channels) = animation["channels"].as_array_mut() {
+                    for channel in channels {
+                        if channel["target"]["node"].as_u64() == Some(n as u64) &&
+                            channel["target"]["path"] == "weights" {
+                            channel["target"]["node"] = json!(child);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Declare an extension in extensionsUsed, and extensionsRequired when the file cannot be read without it
+fn add_extension(json:&mut Value, name:&str, required:bool) {
+    let mut lists = vec!["extensionsUsed"];
+    if required {
+        lists.push("extensionsRequired");
+    }
+    for list in lists {
+        if !json[list].is_array() {
+            json[list] = json!([]);
+        }
+        let extensions = json[list].as_array_mut().unwrap();
+        if !extensions.iter().any(|extension| extension == name) {
+            extensions.push(json!(name));
+        }
+    }
+}
+
+// Take an extension out of extensionsUsed and extensionsRequired, dropping the lists once empty
+fn remove_extension(json:&mut Value, name:&str) {
+    for list in ["extensionsUsed", "extensionsRequired"] {
+        if let Some(extensions) = json[list].as_array_mut() {
+            extensions.retain(|extension| extension != name);
+            if extensions.is_empty() {
+                json.as_object_mut().unwrap().remove(list);
+            }
+        }
+    }
+}
+
+// Append an empty bufferView, its content and offset are filled in when the binary chunk is laid out
+fn new_buffer_view(json:&mut Value) -> u32 {
+    if !json["bufferViews"].is_array() {
+        json["bufferViews"] = json!([]);
+    }
+    let views = json["bufferViews"].as_array_mut().unwrap();
+    views.push(json!({"buffer": 0, "byteLength": 0}));
+    (views.len() - 1) as u32
+}
+
+// Sparse encoding of a VEC3 delta attribute: (count, index componentType, indices, values).
+// None when storing it dense is not larger.
+fn sparse_vec3(list:&HashMap<u32, Vector3<f32>>, new_index_ref:&HashMap<u32, usize>,
+                index_number:u32) -> Option<(u32, u32, Vec<u8>, Vec<u8>)> {
+    let mut indices = Vec::new();
+    for i in 0..index_number {
+        let v = list.get(&(*new_index_ref.get(&i).unwrap() as u32)).copied().unwrap_or(Vector3::zeros());
+        if v != Vector3::zeros() {
+            indices.push(i);
+        }
+    }
+    // A sparse accessor needs at least one element
+    if indices.is_empty() && index_number > 0 {
+        indices.push(0);
+    }
+    let index_type = if index_number <= u16::MAX as u32 { 5123 } else { 5125 };
+    let sparse_size = indices.len() * (component_size(index_type) + 12);
+    if sparse_size >= index_number as usize * 12 {
+        return None;
+    }
+    let count = indices.len() as u32;
+    let mut index_data = Vec::with_capacity(indices.len() * component_size(index_type));
+    let mut value_data = Vec::with_capacity(indices.len() * 12);
+    for i in indices {
+        if index_type == 5123 {
+            index_data.extend_from_slice(&(i as u16).to_le_bytes());
+        } else {
+            index_data.extend_from_slice(&i.to_le_bytes());
+        }
+        let v = list.get(&(*new_index_ref.get(&i).unwrap() as u32)).copied().unwrap_or(Vector3::zeros());
+        value_data.extend_from_slice(&v.x.to_le_bytes());
+        value_data.extend_from_slice(&v.y.to_le_bytes());
+        value_data.extend_from_slice(&v.z.to_le_bytes());
+    }
+    Some((count, index_type, index_data, value_data))
+}
+
+// POSITION accessors (base and morph target) must carry accurate bounds
+fn set_min_max(json:&mut Value, accessor:i64, list:&HashMap<u32, Vector3<f32>>,
+                new_index_ref:&HashMap<u32, usize>, index_number:u32) {
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for i in 0..index_number {
+        let v = list.get(&(*new_index_ref.get(&i).unwrap() as u32)).copied().unwrap_or(Vector3::zeros());
+        min = min.inf(&v);
+        max = max.sup(&v);
+    }
+    if index_number > 0 {
+        json["accessors"][accessor as usize]["min"] = json!([min.x, min.y, min.z]);
+        json["accessors"][accessor as usize]["max"] = json!([max.x, max.y, max.z]);
+    }
+}
+
+// Replace the attribute of u and v by their average, stored under u
+fn merge_attribute<T>(list:&mut HashMap<u32, T>, u:u32, v:u32)
+where T: Copy + std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T> {
+    let a = list.remove(&u);
+    let b = list.remove(&v);
+    match (a, b) {
+        (Some(a), Some(b)) => { list.insert(u, (a + b) * 0.5); }
+        (Some(a), None) | (None, Some(a)) => { list.insert(u, a); }
+        (None, None) => {}
+    }
+}
+
+// Add a constraint quadric to both ends of every edge whose faces meet at more than angle degrees.
+// Its planes go through the edge, perpendicular to each face, so that sliding along the crease stays cheap.
+// Vertices at the same position share the creases, vertices on more than two creases are returned to be locked.
+fn crease_quadrics(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>,
+                    vertex_list:&mut HashMap<u32, Vertex>, metric:&dyn CostMetric, angle:f32) -> HashSet<u32> {
+    let key = |i:&u32| { let p = position_list[i]; [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()] };
+    let mut same: HashMap<[u32; 3], Vec<u32>> = HashMap::new();
+    for i in position_list.keys() {
+        same.entry(key(i)).or_default().push(*i);
+    }
+    let node = |i:&u32| *same[&key(i)].iter().min().unwrap();
+    // Ordered so that the crease quadrics add up the same way on every run
+    let mut faces: BTreeMap<(u32, u32), Vec<Vector3<f32>>> = BTreeMap::new();
+    for t in index_list.chunks_exact(3) {
+        let (a, b, c) = (node(&t[0]), node(&t[1]), node(&t[2]));
+        let normal = (position_list[&b] - position_list[&a]).cross(&(position_list[&c] - position_list[&a]));
+        for (u, v) in [(a, b), (b, c), (c, a)] {
+            faces.entry((u.min(v), u.max(v))).or_default().push(normal);
+        }
+    }
+    let cos = angle.to_radians().cos();
+    let mut creases: HashMap<u32, usize> = HashMap::new();
+    for ((u, v), normals) in &faces {
+        let [n1, n2] = normals.as_slice() else { continue };
+        if n1.norm() == 0.0 || n2.norm() == 0.0 || n1.normalize().dot(&n2.normalize()) >= cos {
+            continue;
+        }
+        let edge = (position_list[v] - position_list[u]).cast::<f64>();
+        for n in [n1, n2] {
+            // Weighted by the area of the face like the face quadrics, the face normal being twice as long
+            let Some(plane) = edge.cross(&n.cast::<f64>()).try_normalize(0.0) else { continue };
+            let plane = plane * (n.norm() as f64 / 2.0 * CREASE_WEIGHT as f64).sqrt();
+            let k = metric.plane(&plane, -plane.dot(&position_list[u].cast::<f64>()));
+            for i in same[&key(u)].iter().chain(&same[&key(v)]) {
+                if let Some(vertex) = vertex_list.get_mut(i) {
+                    vertex.q_matrix = metric.combine(&vertex.q_matrix, &k);
+                }
+            }
+        }
+        *creases.entry(*u).or_default() += 1;
+        *creases.entry(*v).or_default() += 1;
+    }
+    let corners: HashSet<u32> = creases.iter().filter(|(_, count)| **count > 2)
+        .flat_map(|(n, _)| same[&key(n)].clone()).collect();
+    info!(target: "decimate", "{} crease edges kept sharp, {} corner vertices are locked",
+                creases.values().sum::<usize>() / 2, corners.len());
+    corners
+}
+
+// Select valid edges according to connectivity and distance
+fn get_valid_edge(index_list:&[u32], position_list:&HashMap<u32,Vector3<f32>>,
+                vertex_list:&HashMap<u32, Vertex>, target_list:&[Target], proximity:f32,
+                settings:&Settings) -> BTreeMap<(u32, u32), f32>{
+    let mut valid_edge:BTreeMap<(u32, u32), f32> = BTreeMap::new();
+    // Check by distance
+    let index_num = position_list.len();
+    for i in 0..index_num {
+        for j in 0..index_num {
+            if j > i {
+                let p1 = position_list.get(&(i as u32)).unwrap();
+                let p2 = position_list.get(&(j as u32)).unwrap();
+                // Morph targets could pull them apart: their position deltas must differ by less than the proximity too
+                let delta = |t:&Target, k:usize| t.position.get(&(k as u32)).copied().unwrap_or(Vector3::zeros());
+                if (p1-p2).norm() < proximity && (p1-p2).norm() > 0.0
+                    && target_list.iter().all(|t| (delta(t, i) - delta(t, j)).norm() < proximity) {
+                    let cost = edge_cost(i as u32, j as u32, index_list, position_list, vertex_list, target_list, settings);
+                    valid_edge.insert((i as u32, j as u32), cost);
+                }
+            }
+        }
+    }
+    // Check by connection
+    for i in 0..index_list.len()/3 {
+        let (a, b, c) = (index_list[i*3], index_list[i*3+1], index_list[i*3+2]);
+        valid_edge.insert((b, a), edge_cost(b, a, index_list, position_list, vertex_list, target_list, settings));
+        valid_edge.insert((c, b), edge_cost(c, b, index_list, position_list, vertex_list, target_list, settings));
+        valid_edge.insert((a, c), edge_cost(a, c, index_list, position_list, vertex_list, target_list, settings));
+    }
+    valid_edge
+}
+
+// Cost of collapsing u and v into their placement, summed over the base pose and every morph target
+fn edge_cost(u:u32, v:u32, index_list:&[u32], position_list:&HashMap<u32,Vector3<f32>>,
+            vertex_list:&HashMap<u32, Vertex>, target_list:&[Target], settings:&Settings) -> f32 {
+    let metric = settings.metric;
+    let v1 = vertex_list.get(&u).unwrap();
+    let v2 = vertex_list.get(&v).unwrap();
+    let q_matrix = metric.combine(&v1.q_matrix, &v2.q_matrix);
+    let new_pos = settings.placement.place(u, v, index_list, position_list, &q_matrix, metric);
+    // Summed in double precision, only the total goes back to the precision of the positions
+    let mut cost = metric.evaluate(&q_matrix, &new_pos);
+    for (t, target) in target_list.iter().enumerate() {
+        let d1 = target.position.get(&u).copied().unwrap_or(Vector3::zeros());
+        let d2 = target.position.get(&v).copied().unwrap_or(Vector3::zeros());
+        let morph_pos = new_pos + (d1 + d2) / 2.0;
+        cost += metric.evaluate(&metric.combine(&v1.target_q[t], &v2.target_q[t]), &morph_pos);
+    }
+    cost as f32
+}
+
+// Volume enclosed by the triangles, signed by their winding
+fn signed_volume(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>) -> f64 {
+    index_list.chunks_exact(3).map(|t| {
+        let (a, b, c) = (position_list[&t[0]].cast::<f64>(), position_list[&t[1]].cast::<f64>(), position_list[&t[2]].cast::<f64>());
+        a.dot(&b.cross(&c)) / 6.0
+    }).sum()
+}
+
+fn initialize(index_list:&[u32], position_list:&HashMap<u32,Vector3<f32>>, target_list:&[Target],
+             metric:&dyn CostMetric) -> (HashMap<u32, Vertex>, u32) {
+
+    let mut vertex_list: HashMap<u32, Vertex> = HashMap::new();
+    // Calculate k_matrix for each triangle faces
+    let tri_num = index_list.len() / 3;
+    // Faces and vertices are independent of each other and shared out among the threads
+    let k_list:Vec<Vec<f64>> = (0..tri_num).into_par_iter().map(|i| {
+        let v1 = position_list.get(&index_list[i*3]).unwrap();
+        let v2 = position_list.get(&index_list[i*3+1]).unwrap();
+        let v3 = position_list.get(&index_list[i*3+2]).unwrap();
+        metric.face(v1, v2, v3)
+    }).collect();
+    // Same for every morph target, on the morphed positions
+    let mut target_k_list:Vec<Vec<Vec<f64>>> = Vec::with_capacity(target_list.len());
+    for target in target_list {
+        let k_list:Vec<Vec<f64>> = (0..tri_num).into_par_iter().map(|i| {
+            let mut v = Vec::with_capacity(3);
+            for x in 0..3 {
+                let index = index_list[i*3+x];
+                let delta = target.position.get(&index).copied().unwrap_or(Vector3::zeros());
+                v.push(position_list.get(&index).unwrap() + delta);
+            }
+            metric.face(&v[0], &v[1], &v[2])
+        }).collect();
+        target_k_list.push(k_list);
+    }
+
+    // Initialize neighbor edges and faces for each vertex
+    // Calculate the q_matrix of the vertex according to face_set and their k_matrix
+    let v_num = position_list.len();
+    let vertices: Vec<Vertex> = (0..v_num).into_par_iter().map(|i| {
+        let mut edge_set: Vec<Vector2<u32>> = Vec::new();
+        let mut face_set: Vec<Vector3<u32>> = Vec::new();
+        let mut q_matrix:Vec<f64> = metric.zero();
+        let mut target_q:Vec<Vec<f64>> = vec![metric.zero(); target_list.len()];
+        for j in 0..tri_num {
+            if index_list[j*3] == i as u32 {
+                if index_list[j*3] < index_list[j*3+1] {
+                    edge_set.push(Vector2::new(index_list[j*3], index_list[j*3+1]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3+1], index_list[j*3]));
+                }
+                if index_list[j*3+2] < index_list[j*3] {
+                    edge_set.push(Vector2::new(index_list[j*3+2], index_list[j*3]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3], index_list[j*3+2]));
+                }
+                face_set.push(Vector3::new(index_list[j*3],index_list[j*3+1],index_list[j*3+2]));
+                q_matrix = metric.combine(&q_matrix, &k_list[j]);
+                for t in 0..target_list.len() {
+                    target_q[t] = metric.combine(&target_q[t], &target_k_list[t][j]);
+                }
+            } else if index_list[j*3+1] == i as u32 {
+                if index_list[j*3] < index_list[j*3+1] {
+                    edge_set.push(Vector2::new(index_list[j*3], index_list[j*3+1]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3+1], index_list[j*3]));
+                }
+                if index_list[j*3+1] < index_list[j*3+2] {
+                    edge_set.push(Vector2::new(index_list[j*3+1], index_list[j*3+2]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3+2], index_list[j*3+1]));
+                }
+                face_set.push(Vector3::new(index_list[j*3],index_list[j*3+1],index_list[j*3+2]));
+                q_matrix = metric.combine(&q_matrix, &k_list[j]);
+                for t in 0..target_list.len() {
+                    target_q[t] = metric.combine(&target_q[t], &target_k_list[t][j]);
+                }
+            } else if index_list[j*3+2] == i as u32 {
+                if index_list[j*3+1] < index_list[j*3+2] {
+                    edge_set.push(Vector2::new(index_list[j*3+1], index_list[j*3+2]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3+2], index_list[j*3+1]));
+                }
+                if index_list[j*3+2] < index_list[j*3] {
+                    edge_set.push(Vector2::new(index_list[j*3+2], index_list[j*3]));
+                } else {
+                    edge_set.push(Vector2::new(index_list[j*3], index_list[j*3+2]));
+                }
+                face_set.push(Vector3::new(index_list[j*3],index_list[j*3+1],index_list[j*3+2]));
+                q_matrix = metric.combine(&q_matrix, &k_list[j]);
+                for t in 0..target_list.len() {
+                    target_q[t] = metric.combine(&target_q[t], &target_k_list[t][j]);
+                }
+            }
+        }
+        Vertex::new(edge_set, face_set, q_matrix, target_q)
+    }).collect();
+    vertex_list.extend(vertices.into_iter().enumerate().map(|(i, vertex)| (i as u32, vertex)));
+    (vertex_list, tri_num as u32)
+}
+
+// Read a glb file into its json and binary chunks, with every compressed primitive and bufferView decoded
+fn read_glb(path:&Path) -> io::Result<(Value, Vec<u8>)> {
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::new();
+
+    // Read the file contents into a buffer
+    file.read_to_end(&mut buffer)?;
+    // Extract JSON chunk length (the length of the JSON chunk is stored as a little-endian u32 at byte offset 12)
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a valid glb file", path.display()));
+    let json_length = u32::from_le_bytes(buffer.get(12..16).ok_or_else(invalid)?.try_into().unwrap()) as usize;
+    // Extract the JSON chunk and binary chunk from the buffer
+    let json_chunk = buffer.get(20..20 + json_length).ok_or_else(invalid)?;
+    let mut binary_chunk = buffer.get(20 + json_length + 8..).unwrap_or_default().to_vec();
+    // Parse the json_chunk as JSON
+    let mut json:Value = serde_json::from_slice(json_chunk).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    unpack_meshopt(&mut json, &mut binary_chunk)?;
+    for mesh in 0..json["meshes"].as_array().map_or(0, |meshes| meshes.len()) {
+        for primitive in 0..json["meshes"][mesh]["primitives"].as_array().map_or(0, |primitives| primitives.len()) {
+            unpack_draco(&mut json, &mut binary_chunk, mesh, primitive)?;
+        }
+    }
+    Ok((json, binary_chunk))
+}
+
+fn unpack_gltf(json:&Value, binary_chunk:&[u8], mesh:usize, primitive:usize) -> (Attributes, HashMap<String,Prim>) {
+
+    // Processing json information into HashMaps of struct Prim
+    let mut primitives:HashMap<String,Prim> = HashMap::new();
+    trace!(target: "parse", "{}", serde_json::to_string_pretty(&json).unwrap());
+
+    let indices = if let Some(indices) = json["meshes"][mesh]["primitives"][primitive]["indices"].as_i64(){
+        indices
+    } else {
+        debug!(target: "parse", "No Indices");
+        -1
+    };
+    let normal = if let Some(normal) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["NORMAL"].as_i64(){
+        normal
+    } else {
+        debug!(target: "parse", "No NORMAL");
+        -1
+    };
+    let position = if let Some(position) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["POSITION"].as_i64(){
+        position
+    } else {
+        debug!(target: "parse", "No POSITION");
+        -1
+    };
+    let tangent = if let Some(tangent) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["TANGENT"].as_i64(){
+        tangent
+    } else {
+        debug!(target: "parse", "No TANGENT");
+        -1
+    };
+    let texcoord_0 = if let Some(texcoord_0) = json["meshes"][mesh]["primitives"][primitive]["attributes"]["TEXCOORD_0"].as_i64(){
+        texcoord_0
+    } else {
+        debug!(target: "parse", "No TEXCOORD_0");
+        -1
+    };
+
+    // Morph target accessors, named like "targets[0].POSITION"
+    let mut target_keys:HashMap<i64,String> = HashMap::new();
+    let target_num = json["meshes"][mesh]["primitives"][primitive]["targets"].as_array().map_or(0, |targets| targets.len());
+    for t in 0..target_num {
+        for name in ["POSITION", "NORMAL", "TANGENT"] {
+            if let Some(accessor) = json["meshes"][mesh]["primitives"][primitive]["targets"][t][name].as_i64() {
+                target_keys.insert(accessor, format!("targets[{}].{}", t, name));
+            }
+        }
+    }
+
+    // Write primitives' information
+    if let Some(accessors) = json["accessors"].as_array() {
+        for (i, accessor) in (0i64..).zip(accessors) {
+            let component_type = accessor["componentType"].as_u64().unwrap() as u32;
+            let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+            let mut key = String::from("ERROR");
+            if i == indices{
+                key = String::from("indices");
+            } else if i == normal{
+                key = String::from("NORMAL");
+            } else if i == position{
+                key = String::from("POSITION");
+            } else if i == tangent{
+                key = String::from("TANGENT");
+            } else if i == texcoord_0{
+                key = String::from("TEXCOORD_0");
+            } else if let Some(target_key) = target_keys.get(&i) {
+                key = target_key.clone();
+            }
+            primitives.insert(key, Prim::new(component_type, normalized));
+        }
+    }
+
+    let mut index_list:Vec<u32> = Vec::new();
+
+    // Write indices information
+    if indices != -1 {
+        let values = read_accessor(json, indices as usize, binary_chunk);
+        for value in values {
+            index_list.push(value as u32);
+        }
+    } else {
+        debug!(target: "parse", "No view found for indices: {}", indices);
+    }
+
+    let mut normal_list: HashMap<u32,Vector3<f32>> = HashMap::new();
+    let mut position_list: HashMap<u32,Vector3<f32>> = HashMap::new();
+    let mut tangent_list: HashMap<u32,Vector4<f32>> = HashMap::new();
+    let mut texcoord_0_list: HashMap<u32,Vector2<f32>> = HashMap::new();
+
+    // Write normal information
+    if normal != -1 {
+        let values = dequantize(read_accessor(json, normal as usize, binary_chunk), primitives.get("NORMAL").unwrap());
+        for i in &index_list {
+            let x = (i*3) as usize;
+            normal_list.insert(*i,Vector3::new(values[x] as f32, values[x+1] as f32, values[x+2] as f32));
+        }
+    }
+
+    // Write position information
+    if position != -1 {
+        let values = dequantize(read_accessor(json, position as usize, binary_chunk), primitives.get("POSITION").unwrap());
+        for i in &index_list {
+            let x = (i*3) as usize;
+            position_list.insert(*i,Vector3::new(values[x] as f32, values[x+1] as f32, values[x+2] as f32));
+        }
+    }
+
+    // Write tangent information
+    if tangent != -1 {
+        let values = dequantize(read_accessor(json, tangent as usize, binary_chunk), primitives.get("TANGENT").unwrap());
+        for i in &index_list {
+            let x = (i*4) as usize;
+            tangent_list.insert(*i,Vector4::new(values[x] as f32, values[x+1] as f32,
+                                values[x+2] as f32, values[x+3] as f32));
+        }
+    }
+
+    // Write texcoord_0 information
+    if texcoord_0 != -1 {
+        let values = dequantize(read_accessor(json, texcoord_0 as usize, binary_chunk), primitives.get("TEXCOORD_0").unwrap());
+        for i in &index_list {
+            let x = (i*2) as usize;
+            texcoord_0_list.insert(*i,Vector2::new(values[x] as f32, values[x+1] as f32));
+        }
+    }
+
+    // Write morph target deltas
+    let mut target_list: Vec<Target> = Vec::with_capacity(target_num);
+    for t in 0..target_num {
+        let mut lists = Vec::with_capacity(3);
+        for name in ["POSITION", "NORMAL", "TANGENT"] {
+            let key = format!("targets[{}].{}", t, name);
+            let mut list: HashMap<u32,Vector3<f32>> = HashMap::new();
+            if let Some(prim) = primitives.get(&key) {
+                let accessor = json["meshes"][mesh]["primitives"][primitive]["targets"][t][name].as_u64().unwrap();
+                let values = dequantize(read_accessor(json, accessor as usize, binary_chunk), prim);
+                for i in &index_list {
+                    let x = (i*3) as usize;
+                    list.insert(*i,Vector3::new(values[x] as f32, values[x+1] as f32, values[x+2] as f32));
+                }
+            }
+            lists.push(list);
+        }
+        let tangent = lists.pop().unwrap();
+        let normal = lists.pop().unwrap();
+        let position = lists.pop().unwrap();
+        target_list.push(Target::new(position, normal, tangent));
+    }
+
+    (Attributes { index_list, normal_list, position_list, tangent_list, texcoord_0_list, target_list }, primitives)
+}
+
+// Give the primitive an index list of triangles, or of line segments for lines: non-indexed vertices are welded,
+// strips, fans and loops are unrolled. Points get an index per point.
+fn triangulate(json:&mut Value, binary_chunk:&mut Vec<u8>, mesh:usize, primitive:usize) -> io::Result<()> {
+    let mode = json["meshes"][mesh]["primitives"][primitive]["mode"].as_u64().unwrap_or(4);
+    let name = match mode {
+        0 => "POINTS",
+        1 => "LINES",
+        2 => "LINE_LOOP",
+        3 => "LINE_STRIP",
+        4 => "TRIANGLES",
+        5 => "TRIANGLE_STRIP",
+        6 => "TRIANGLE_FAN",
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                       format!("Primitive {} of mesh {} has an unknown mode {}", primitive, mesh, mode))),
+    };
+    if json["meshes"][mesh]["primitives"][primitive]["indices"].is_u64() && (mode <= 1 || mode == 4) {
+        return Ok(());
+    }
+    // The indices and the welded attributes are rewritten in place, other primitives keep the accessors they share
+    own_accessors(json, (mesh, primitive));
+    let prim = json["meshes"][mesh]["primitives"][primitive].clone();
+    let vertices = match prim["indices"].as_u64() {
+        Some(indices) => read_accessor(json, indices as usize, binary_chunk).iter().map(|i| *i as u32).collect(),
+        None if mode == 0 => {
+            let position = prim["attributes"]["POSITION"].as_u64().unwrap() as usize;
+            (0..json["accessors"][position]["count"].as_u64().unwrap() as u32).collect()
+        }
+        None => weld(json, &prim, binary_chunk),
+    };
+    info!(target: "parse", "{} primitive of {} vertices{} turned into an indexed {} list", name, vertices.len(),
+                if prim["indices"].is_null() { " without indices" } else { "" },
+                ["point", "segment", "segment", "segment", "triangle", "triangle", "triangle"][mode as usize]);
+
+    let mut index_list = Vec::with_capacity(vertices.len() * 3);
+    if mode == 0 {
+        index_list = vertices.clone();
+    } else if mode < 4 {
+        let segments = match mode {
+            1 => vertices.len() / 2,
+            // The loop closes back on its first vertex, two vertices make a single segment
+            2 if vertices.len() > 2 => vertices.len(),
+            _ => vertices.len().saturating_sub(1),
+        };
+        for i in 0..segments {
+            let segment = match mode {
+                1 => [vertices[2*i], vertices[2*i+1]],
+                _ => [vertices[i], vertices[(i+1) % vertices.len()]],
+            };
+            if segment[0] != segment[1] {
+                index_list.extend_from_slice(&segment);
+            }
+        }
+    }
+    let triangles = match mode {
+        0..=3 => 0,
+        4 => vertices.len() / 3,
+        _ => vertices.len().saturating_sub(2),
+    };
+    for i in 0..triangles {
+        let triangle = match mode {
+            4 => [vertices[3*i], vertices[3*i+1], vertices[3*i+2]],
+            // Every other triangle of a strip is flipped back to the winding of the first one
+            5 if i % 2 == 0 => [vertices[i], vertices[i+1], vertices[i+2]],
+            5 => [vertices[i+1], vertices[i], vertices[i+2]],
+            _ => [vertices[i+1], vertices[i+2], vertices[0]],
+        };
+        // Strips use degenerate triangles to restart
+        if mode != 4 && (triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[0] == triangle[2]) {
+            continue;
+        }
+        index_list.extend_from_slice(&triangle);
+    }
+
+    let data = index_list.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let view = new_buffer_view(json);
+    append_view(json, binary_chunk, view, data, None);
+    json["bufferViews"][view as usize]["target"] = json!(34963);
+    let accessor = json!({"bufferView": view, "componentType": 5125, "count": index_list.len(), "type": "SCALAR"});
+    let accessor = match prim["indices"].as_u64() {
+        Some(indices) => {
+            json["accessors"][indices as usize] = accessor;
+            indices as usize
+        }
+        None => {
+            let accessors = json["accessors"].as_array_mut().unwrap();
+            accessors.push(accessor);
+            accessors.len() - 1
+        }
+    };
+    json["meshes"][mesh]["primitives"][primitive]["indices"] = json!(accessor);
+    if mode < 4 {
+        json["meshes"][mesh]["primitives"][primitive]["mode"] = json!(mode.min(1));
+    } else {
+        json["meshes"][mesh]["primitives"][primitive].as_object_mut().unwrap().remove("mode");
+    }
+    Ok(())
+}
+
+// Merge the vertices with the same attributes (morph targets included) and return the index of each vertex.
+// The attribute accessors are rewritten with the unique vertices only.
+fn weld(json:&mut Value, primitive:&Value, binary_chunk:&mut Vec<u8>) -> Vec<u32> {
+    let mut accessors: Vec<u64> = primitive["attributes"].as_object().unwrap().values().filter_map(|a| a.as_u64()).collect();
+    for target in primitive["targets"].as_array().into_iter().flatten() {
+        accessors.extend(target.as_object().unwrap().values().filter_map(|a| a.as_u64()));
+    }
+    let count = json["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize]["count"].as_u64().unwrap() as usize;
+    let attributes: Vec<(usize, Vec<f64>)> = accessors.iter().map(|a| {
+        (type_size(json["accessors"][*a as usize]["type"].as_str().unwrap()), read_accessor(json, *a as usize, binary_chunk))
+    }).collect();
+    let mut first: HashMap<Vec<u64>, u32> = HashMap::new();
+    let mut unique = Vec::new();
+    let mut vertices = Vec::with_capacity(count);
+    for v in 0..count {
+        let key: Vec<u64> = attributes.iter().flat_map(|(n, values)| values[v*n..(v+1)*n].iter().map(|x| x.to_bits())).collect();
+        vertices.push(*first.entry(key).or_insert_with(|| {
+            unique.push(v);
+            unique.len() as u32 - 1
+        }));
+    }
+    info!(target: "parse", "Welded {} vertices into {}", count, unique.len());
+    for (accessor, (n, values)) in accessors.iter().zip(&attributes) {
+        let values: Vec<f64> = unique.iter().flat_map(|v| values[v*n..(v+1)*n].iter().copied()).collect();
+        replace_accessor_data(json, binary_chunk, *accessor as usize, &values);
+    }
+    vertices
+}
+
+// Decompress every EXT_meshopt_compression bufferView into the binary chunk and drop the fallback buffers
+fn unpack_meshopt(json:&mut Value, binary_chunk:&mut Vec<u8>) -> io::Result<()> {
+    let view_num = json["bufferViews"].as_array().map_or(0, |views| views.len());
+    for i in 0..view_num {
+        let extension = json["bufferViews"][i]["extensions"]["EXT_meshopt_compression"].clone();
+        if !extension.is_object() {
+            continue;
+        }
+        let error = |kind, what:&str| io::Error::new(kind, format!("Meshopt bufferView {}: {}", i, what));
+        if extension["buffer"].as_u64() != Some(0) {
+            return Err(error(io::ErrorKind::Unsupported, "compressed into an external buffer"));
+        }
+        let field = |name:&str| extension[name].as_u64().map(|x| x as usize)
+            .ok_or_else(|| error(io::ErrorKind::InvalidData, &format!("no {}", name)));
+        let off = extension["byteOffset"].as_u64().unwrap_or(0) as usize;
+        let (len, count, stride) = (field("byteLength")?, field("count")?, field("byteStride")?);
+        if count.checked_mul(stride) != json["bufferViews"][i]["byteLength"].as_u64().map(|x| x as usize) {
+            return Err(error(io::ErrorKind::InvalidData, "count and byteStride do not match the byteLength of the view"));
+        }
+        let compressed = binary_chunk.get(off..off + len)
+            .ok_or_else(|| error(io::ErrorKind::InvalidData, "the data is out of the binary chunk"))?;
+        let data = meshopt::decode(compressed, count, stride, extension["mode"].as_str().unwrap_or(""),
+                                    extension["filter"].as_str().unwrap_or("NONE"))
+            .map_err(|what| error(io::ErrorKind::InvalidData, &what))?;
+        append_view(json, binary_chunk, i as u32, data, json["bufferViews"][i]["byteStride"].as_u64().map(|s| s as usize));
+        let view = json["bufferViews"][i].as_object_mut().unwrap();
+        let extensions = view["extensions"].as_object_mut().unwrap();
+        extensions.remove("EXT_meshopt_compression");
+        if extensions.is_empty() {
+            view.remove("extensions");
+        }
+    }
+
+    // Every view now lives in the binary chunk, the fallback buffers have no users left
+    let fallback: Vec<bool> = json["buffers"].as_array().map_or(Vec::new(), |buffers| {
+        buffers.iter().map(|buffer| buffer["extensions"]["EXT_meshopt_compression"]["fallback"] == true).collect()
+    });
+    if fallback.iter().any(|f| *f) {
+        let mut i = 0;
+        json["buffers"].as_array_mut().unwrap().retain(|_| {
+            i += 1;
+            !fallback[i - 1]
+        });
+        if let Some(views) = json["bufferViews"].as_array_mut() {
+            for view in views {
+                let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+                view["buffer"] = json!(fallback[..buffer].iter().filter(|f| !**f).count());
+            }
+        }
+    }
+    remove_extension(json, "EXT_meshopt_compression");
+    Ok(())
+}
+
+// Decompress a KHR_draco_mesh_compression primitive in place: the decoded indices and attributes are appended
+// to the binary chunk as plain bufferViews, so the rest of the pipeline reads them like any other accessor.
+fn unpack_draco(json:&mut Value, binary_chunk:&mut Vec<u8>, mesh:usize, primitive:usize) -> io::Result<()> {
+    let extension = json["meshes"][mesh]["primitives"][primitive]["extensions"]["KHR_draco_mesh_compression"].clone();
+    let compressed_view = match extension["bufferView"].as_u64() {
+        Some(view) => view as usize,
+        None => return Ok(()),
+    };
+    let invalid = |what:String| io::Error::new(io::ErrorKind::InvalidData,
+                                               format!("Draco primitive {} of mesh {}: {}", primitive, mesh, what));
+    let off = json["bufferViews"][compressed_view]["byteOffset"].as_u64().unwrap_or(0) as usize;
+    let len = json["bufferViews"][compressed_view]["byteLength"].as_u64().unwrap_or(0) as usize;
+    let data = binary_chunk.get(off..off + len).ok_or_else(|| invalid(String::from("the buffer is out of the binary chunk")))?;
+    let decoded = draco::decode(data).map_err(invalid)?;
+    info!(target: "parse", "Draco primitive: {} faces, {} points", decoded.faces.len(), decoded.num_points);
+
+    // The compressed view is reused for the indices so that no view is left without a user
+    if let Some(indices) = json["meshes"][mesh]["primitives"][primitive]["indices"].as_u64() {
+        let mut data = Vec::with_capacity(decoded.faces.len() * 12);
+        for index in decoded.faces.iter().flatten() {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        append_view(json, binary_chunk, compressed_view as u32, data, None);
+        json["accessors"][indices as usize]["bufferView"] = json!(compressed_view);
+        json["accessors"][indices as usize]["componentType"] = json!(5125);
+        json["accessors"][indices as usize]["count"] = json!(decoded.faces.len() * 3);
+        if let Some(accessor) = json["accessors"][indices as usize].as_object_mut() {
+            accessor.remove("byteOffset");
+        }
+    }
+    if let Some(attributes) = extension["attributes"].as_object() {
+        for (name, unique_id) in attributes {
+            let accessor = match json["meshes"][mesh]["primitives"][primitive]["attributes"][name].as_u64() {
+                Some(accessor) => accessor as usize,
+                None => continue,
+            };
+            let attribute = unique_id.as_u64().and_then(|id| decoded.attribute(id as u32))
+                .ok_or_else(|| invalid(format!("attribute {} is missing", name)))?;
+            let (n, _) = accessor_layout(json, accessor)?;
+            if attribute.num_components != n {
+                return Err(invalid(format!("attribute {} has {} components for {} in its accessor", name,
+                                           attribute.num_components, n)));
+            }
+            replace_accessor_data(json, binary_chunk, accessor, &attribute.point_values());
+        }
+    }
+
+    if let Some(extensions) = json["meshes"][mesh]["primitives"][primitive]["extensions"].as_object_mut() {
+        extensions.remove("KHR_draco_mesh_compression");
+        if extensions.is_empty() {
+            json["meshes"][mesh]["primitives"][primitive].as_object_mut().unwrap().remove("extensions");
+        }
+    }
+    // Other primitives may still be compressed
+    let compressed = json["meshes"].as_array().into_iter().flatten()
+        .flat_map(|mesh| mesh["primitives"].as_array().into_iter().flatten())
+        .any(|primitive| primitive["extensions"]["KHR_draco_mesh_compression"].is_object());
+    if !compressed {
+        remove_extension(json, "KHR_draco_mesh_compression");
+    }
+    Ok(())
+}
+
+// Give an accessor new values in a bufferView of its own, keeping the storage it declares
+fn replace_accessor_data(json:&mut Value, binary_chunk:&mut Vec<u8>, accessor:usize, values:&[f64]) {
+    let component_type = json["accessors"][accessor]["componentType"].as_u64().unwrap() as u32;
+    let n = type_size(json["accessors"][accessor]["type"].as_str().unwrap());
+    let (data, stride) = if component_type == 5126 {
+        (values.iter().flat_map(|x| (*x as f32).to_le_bytes()).collect(), None)
+    } else {
+        let elements: Vec<Vec<f32>> = values.chunks(n).map(|e| e.iter().map(|x| *x as f32).collect()).collect();
+        let size = n * component_size(component_type);
+        let data = integer_bytes(&elements, component_type).expect("The values were read with this component type");
+        (data, Some(size.div_ceil(4) * 4))
+    };
+    let view = new_buffer_view(json);
+    append_view(json, binary_chunk, view, data, stride);
+    json["accessors"][accessor]["bufferView"] = json!(view);
+    json["accessors"][accessor]["count"] = json!(values.len() / n);
+    if let Some(accessor) = json["accessors"][accessor].as_object_mut() {
+        accessor.remove("byteOffset");
+        accessor.remove("sparse");
+    }
+}
+
+// Point a bufferView at data appended to the binary chunk
+fn append_view(json:&mut Value, binary_chunk:&mut Vec<u8>, view:u32, data:Vec<u8>, stride:Option<usize>) {
+    while !binary_chunk.len().is_multiple_of(4) {
+        binary_chunk.push(0);
+    }
+    let view = json["bufferViews"][view as usize].as_object_mut().unwrap();
+    view.insert(String::from("buffer"), json!(0));
+    view.insert(String::from("byteOffset"), json!(binary_chunk.len()));
+    view.insert(String::from("byteLength"), json!(data.len()));
+    match stride {
+        Some(stride) => view.insert(String::from("byteStride"), json!(stride)),
+        None => view.remove("byteStride"),
+    };
+    binary_chunk.extend_from_slice(&data);
+}
+
+// Components of an accessor and their size in bytes, an error for an unknown type or component type
+fn accessor_layout(json:&Value, a:usize) -> io::Result<(usize, usize)> {
+    let invalid = |what:String| io::Error::new(io::ErrorKind::InvalidData, format!("Accessor {}: {}", a, what));
+    let n = match json["accessors"][a]["type"].as_str() {
+        Some(prim_type @ ("SCALAR" | "VEC2" | "VEC3" | "VEC4" | "MAT2" | "MAT3" | "MAT4")) => type_size(prim_type),
+        prim_type => return Err(invalid(format!("unknown type {:?}", prim_type))),
+    };
+    let size = match json["accessors"][a]["componentType"].as_u64() {
+        Some(component_type @ (5120..=5123 | 5125 | 5126)) => component_size(component_type as u32),
+        component_type => return Err(invalid(format!("unknown component type {:?}", component_type))),
+    };
+    Ok((n, size))
+}
+
+// Check that every accessor has a known type and component type and lies within the binary chunk, dense and
+// sparse parts alike, before read_accessor reads them
+fn check_accessors(json:&Value, binary_chunk:&[u8]) -> io::Result<()> {
+    let invalid = |a:usize, what:String| io::Error::new(io::ErrorKind::InvalidData, format!("Accessor {}: {}", a, what));
+    // Bytes from the offset within the view to the last one read, inside the view and the binary chunk
+    let within = |a:usize, view:&Value, offset:&Value, length:usize| -> io::Result<usize> {
+        let (view_offset, view_length) = (view["byteOffset"].as_u64().unwrap_or(0) as usize,
+                                          view["byteLength"].as_u64().unwrap_or(0) as usize);
+        let offset = offset.as_u64().unwrap_or(0) as usize;
+        if view.is_null() || offset + length > view_length || view_offset + view_length > binary_chunk.len() {
+            return Err(invalid(a, String::from("the data is out of its bufferView or of the binary chunk")));
+        }
+        Ok(view_offset + offset)
+    };
+    for (a, accessor) in json["accessors"].as_array().into_iter().flatten().enumerate() {
+        let (n, size) = accessor_layout(json, a)?;
+        let count = accessor["count"].as_u64().ok_or_else(|| invalid(a, String::from("no count")))? as usize;
+        if let Some(view) = accessor["bufferView"].as_u64() {
+            let view = &json["bufferViews"][view as usize];
+            let stride = view["byteStride"].as_u64().map_or(n * size, |stride| stride as usize);
+            let length = if count > 0 { (count - 1) * stride + n * size } else { 0 };
+            within(a, view, &accessor["byteOffset"], length)?;
+        }
+        let sparse = &accessor["sparse"];
+        if sparse.is_object() {
+            let sparse_count = sparse["count"].as_u64().unwrap_or(0) as usize;
+            let index_size = match sparse["indices"]["componentType"].as_u64() {
+                Some(index_type @ (5121 | 5123 | 5125)) => component_size(index_type as u32),
+                index_type => return Err(invalid(a, format!("sparse indices of component type {:?}", index_type))),
+            };
+            let index_view = &json["bufferViews"][sparse["indices"]["bufferView"].as_u64().unwrap_or(u64::MAX) as usize];
+            let start = within(a, index_view, &sparse["indices"]["byteOffset"], sparse_count * index_size)?;
+            let index_type = sparse["indices"]["componentType"].as_u64().unwrap() as u32;
+            if (0..sparse_count).any(|k| byte_component(binary_chunk, start + k * index_size, index_type) as usize >= count) {
+                return Err(invalid(a, String::from("a sparse index is out of the accessor")));
+            }
+            let value_view = &json["bufferViews"][sparse["values"]["bufferView"].as_u64().unwrap_or(u64::MAX) as usize];
+            within(a, value_view, &sparse["values"]["byteOffset"], sparse_count * n * size)?;
+        }
+    }
+    Ok(())
+}
+
+// Decode an accessor into a flat list of components.
+// Accessors without bufferView start from zeros, then sparse elements replace the dense ones.
+fn read_accessor(json:&Value, accessor:usize, binary_chunk:&[u8]) -> Vec<f64> {
+    let accessor = &json["accessors"][accessor];
+    let component_type = accessor["componentType"].as_u64().unwrap() as u32;
+    let count = accessor["count"].as_u64().unwrap() as usize;
+    let n = type_size(accessor["type"].as_str().unwrap());
+    let size = component_size(component_type);
+    let mut values = vec![0.0; count * n];
+    if let Some(view) = accessor["bufferView"].as_u64() {
+        let view = &json["bufferViews"][view as usize];
+        let off = (view["byteOffset"].as_u64().unwrap_or(0) + accessor["byteOffset"].as_u64().unwrap_or(0)) as usize;
+        let stride = view["byteStride"].as_u64().map_or(n * size, |stride| stride as usize);
+        for i in 0..count {
+            for c in 0..n {
+                values[i*n+c] = byte_component(binary_chunk, off + i*stride + c*size, component_type);
+            }
+        }
+    }
+    if accessor["sparse"].is_object() {
+        let sparse = &accessor["sparse"];
+        let sparse_count = sparse["count"].as_u64().unwrap() as usize;
+        let index_type = sparse["indices"]["componentType"].as_u64().unwrap() as u32;
+        let index_view = &json["bufferViews"][sparse["indices"]["bufferView"].as_u64().unwrap() as usize];
+        let index_off = (index_view["byteOffset"].as_u64().unwrap_or(0) +
+                        sparse["indices"]["byteOffset"].as_u64().unwrap_or(0)) as usize;
+        let value_view = &json["bufferViews"][sparse["values"]["bufferView"].as_u64().unwrap() as usize];
+        let value_off = (value_view["byteOffset"].as_u64().unwrap_or(0) +
+                        sparse["values"]["byteOffset"].as_u64().unwrap_or(0)) as usize;
+        for k in 0..sparse_count {
+            let i = byte_component(binary_chunk, index_off + k*component_size(index_type), index_type) as usize;
+            for c in 0..n {
+                values[i*n+c] = byte_component(binary_chunk, value_off + (k*n+c)*size, component_type);
+            }
+        }
+    }
+    values
+}
+
+// Components of an accessor, normalized integers mapped back to floats
+fn read_values(json:&Value, accessor:usize, binary_chunk:&[u8]) -> Vec<f64> {
+    let a = &json["accessors"][accessor];
+    let prim = Prim::new(a["componentType"].as_u64().unwrap() as u32, a["normalized"] == true);
+    dequantize(read_accessor(json, accessor, binary_chunk), &prim)
+}
+
+// Map normalized integer components back to floats (KHR_mesh_quantization), others are used as is
+fn dequantize(mut values:Vec<f64>, prim:&Prim) -> Vec<f64> {
+    if prim.normalized {
+        let scale = match prim.component_type {
+            5120 => 127.0,
+            5121 => 255.0,
+            5122 => 32767.0,
+            5123 => 65535.0,
+            _ => 1.0,
+        };
+        for value in values.iter_mut() {
+            *value = (*value / scale).max(-1.0);
+        }
+    }
+    values
+}
+
+// Number of components for an accessor type
+fn type_size(prim_type:&str) -> usize {
+    match prim_type {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" | "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        _ => unreachable!("Accessor types are checked by check_accessors"),
+    }
+}
+
+// Size in bytes of a single component
+fn component_size(component_type:u32) -> usize {
+    match component_type {
+        5120 | 5121 => 1,
+        5122 | 5123 => 2,
+        5125 | 5126 => 4,
+        _ => unreachable!("Component types are checked by check_accessors"),
+    }
+}
+
+fn byte_component(buff:&[u8], x:usize, component_type:u32) -> f64 {
+    match component_type {
+        5120 => i8::from_le_bytes([buff[x]]) as f64,
+        5121 => byte_u8(buff, x) as f64,
+        5122 => i16::from_le_bytes([buff[x], buff[x+1]]) as f64,
+        5123 => byte_u16(buff, x) as f64,
+        5125 => byte_u32(buff, x) as f64,
+        5126 => byte_f32(buff, x) as f64,
+        _ => unreachable!("Component types are checked by check_accessors"),
+    }
+}
+
+fn byte_u8(buff:&[u8], x:usize) -> u32{
+    let byte = &buff[x..x+1];
+    u8::from_le_bytes(byte.try_into().unwrap()) as u32
+}
+fn byte_u16(buff:&[u8], x:usize) -> u32{
+    let byte = &buff[x..x+2];
+    u16::from_le_bytes(byte.try_into().unwrap()) as u32
+}
+fn byte_u32(buff:&[u8], x:usize) -> u32{
+    let byte = &buff[x..x+4];
+    u32::from_le_bytes(byte.try_into().unwrap())
+}
+fn byte_f32(buff:&[u8], x:usize) -> f32{
+    let byte = &buff[x..x+4];
+    f32::from_le_bytes(byte.try_into().unwrap())
+}
\ No newline at end of file
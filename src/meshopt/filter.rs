@@ -0,0 +1,97 @@
+// Filters applied to decoded vertex data: octahedral normals, quaternions and shared exponent floats
+
+fn round_to_int(x:f32) -> i32 {
+    (x + if x >= 0.0 { 0.5 } else { -0.5 }) as i32
+}
+
+// Unit vectors stored as two octahedral coordinates of 8 or 16 bits, with 1.0 at the position of z
+pub fn decode_octahedral(data:&mut [u8], stride:usize) -> Result<(), String> {
+    match stride {
+        4 => {
+            for element in data.chunks_exact_mut(4) {
+                let v = [element[0] as i8 as f32, element[1] as i8 as f32, element[2] as i8 as f32];
+                for (c, x) in octahedral_to_unit(v, 127.0).iter().enumerate() {
+                    element[c] = *x as i8 as u8;
+                }
+            }
+        }
+        8 => {
+            for element in data.chunks_exact_mut(8) {
+                let v = [read_i16(element, 0) as f32, read_i16(element, 1) as f32, read_i16(element, 2) as f32];
+                for (c, x) in octahedral_to_unit(v, 32767.0).iter().enumerate() {
+                    write_i16(element, c, *x as i16);
+                }
+            }
+        }
+        _ => return Err(format!("Invalid meshopt octahedral filter stride {}", stride)),
+    }
+    Ok(())
+}
+
+fn octahedral_to_unit(v:[f32; 3], max:f32) -> [i32; 3] {
+    let (mut x, mut y) = (v[0], v[1]);
+    let z = v[2] - x.abs() - y.abs();
+    let t = z.min(0.0);
+    x += if x >= 0.0 { t } else { -t };
+    y += if y >= 0.0 { t } else { -t };
+    let s = max / (x * x + y * y + z * z).sqrt();
+    [round_to_int(x * s), round_to_int(y * s), round_to_int(z * s)]
+}
+
+// Counterpart of decode_octahedral for 8-bit elements; the fourth byte is kept
+pub fn encode_octahedral(normals:&[[f32; 3]]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(normals.len() * 4);
+    for n in normals {
+        let l = n[0].abs() + n[1].abs() + n[2].abs();
+        let s = if l == 0.0 { 0.0 } else { 1.0 / l };
+        let (nx, ny) = (n[0] * s, n[1] * s);
+        let u = if n[2] >= 0.0 { nx } else { (1.0 - ny.abs()) * if nx >= 0.0 { 1.0 } else { -1.0 } };
+        let v = if n[2] >= 0.0 { ny } else { (1.0 - nx.abs()) * if ny >= 0.0 { 1.0 } else { -1.0 } };
+        let snorm = |x:f32| round_to_int(x.clamp(-1.0, 1.0) * 127.0) as i8 as u8;
+        data.extend_from_slice(&[snorm(u), snorm(v), 127, 0]);
+    }
+    data
+}
+
+// Rotations as three 16-bit components, the largest one being rebuilt
+pub fn decode_quaternion(data:&mut [u8], stride:usize) -> Result<(), String> {
+    if stride != 8 {
+        return Err(format!("Invalid meshopt quaternion filter stride {}", stride));
+    }
+    let scale = std::f32::consts::FRAC_1_SQRT_2;
+    for element in data.chunks_exact_mut(8) {
+        let q = [read_i16(element, 0), read_i16(element, 1), read_i16(element, 2), read_i16(element, 3)];
+        let ss = scale / (q[3] | 3) as f32;
+        let (x, y, z) = (q[0] as f32 * ss, q[1] as f32 * ss, q[2] as f32 * ss);
+        let w = (1.0 - x * x - y * y - z * z).max(0.0).sqrt();
+        let qc = (q[3] & 3) as usize;
+        write_i16(element, (qc + 1) & 3, round_to_int(x * 32767.0) as i16);
+        write_i16(element, (qc + 2) & 3, round_to_int(y * 32767.0) as i16);
+        write_i16(element, (qc + 3) & 3, round_to_int(z * 32767.0) as i16);
+        write_i16(element, qc, round_to_int(w * 32767.0) as i16);
+    }
+    Ok(())
+}
+
+// 24-bit signed mantissa with an 8-bit signed exponent per 32-bit component
+pub fn decode_exponential(data:&mut [u8], stride:usize) -> Result<(), String> {
+    if !stride.is_multiple_of(4) {
+        return Err(format!("Invalid meshopt exponential filter stride {}", stride));
+    }
+    for component in data.chunks_exact_mut(4) {
+        let v = i32::from_le_bytes([component[0], component[1], component[2], component[3]]);
+        let e = v >> 24;
+        let m = (v << 8) >> 8;
+        let value = m as f32 * f32::from_bits(((e + 127) as u32) << 23);
+        component.copy_from_slice(&value.to_le_bytes());
+    }
+    Ok(())
+}
+
+pub(super) fn read_i16(element:&[u8], c:usize) -> i16 {
+    i16::from_le_bytes([element[2 * c], element[2 * c + 1]])
+}
+
+fn write_i16(element:&mut [u8], c:usize, value:i16) {
+    element[2 * c..2 * c + 2].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,335 @@
+// Index codecs: triangle lists through edge and vertex FIFOs, and generic index sequences
+const TRIANGLE_HEADER: u8 = 0xe0;
+const SEQUENCE_HEADER: u8 = 0xd0;
+
+// Pairs of vertex FIFO lookups for new triangles, the table is also the padding at the end of the stream
+const CODE_AUX_TABLE: [u8; 16] = [
+    0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0, 0,
+];
+
+struct Fifos {
+    edges: [(u32, u32); 16],
+    edge_offset: usize,
+    vertices: [u32; 16],
+    vertex_offset: usize,
+}
+
+impl Fifos {
+    fn new() -> Self {
+        Fifos {
+            edges: [(u32::MAX, u32::MAX); 16],
+            edge_offset: 0,
+            vertices: [u32::MAX; 16],
+            vertex_offset: 0,
+        }
+    }
+
+    fn push_edge(&mut self, a:u32, b:u32) {
+        self.edges[self.edge_offset] = (a, b);
+        self.edge_offset = (self.edge_offset + 1) & 15;
+    }
+
+    fn push_vertex(&mut self, v:u32, cond:bool) {
+        self.vertices[self.vertex_offset] = v;
+        self.vertex_offset = (self.vertex_offset + cond as usize) & 15;
+    }
+
+    fn edge(&self, fe:usize) -> (u32, u32) {
+        self.edges[(self.edge_offset + 15 - fe) & 15]
+    }
+
+    // Vertex pushed `back` pushes ago, 1 being the latest
+    fn vertex(&self, back:usize) -> u32 {
+        self.vertices[(self.vertex_offset + 16 - back) & 15]
+    }
+
+    fn find_vertex(&self, v:u32) -> Option<usize> {
+        (1..=16).find(|back| self.vertex(*back) == v)
+    }
+
+    // Edge of the triangle already in the FIFO, with the rotation that puts it first.
+    // The last slot is not addressable, its codes are used by triangles without a shared edge.
+    fn find_edge(&self, a:u32, b:u32, c:u32) -> Option<(usize, [u32; 3])> {
+        (0..15).find_map(|fe| {
+            let edge = self.edge(fe);
+            [[a, b, c], [b, c, a], [c, a, b]].into_iter().find(|t| edge == (t[0], t[1])).map(|t| (fe, t))
+        })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        if self.pos >= self.end {
+            return Err(String::from("Meshopt index buffer is truncated"));
+        }
+        self.pos += 1;
+        Ok(self.data[self.pos - 1])
+    }
+
+    fn vbyte(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn index(&mut self, last:u32) -> Result<u32, String> {
+        let v = self.vbyte()?;
+        Ok(last.wrapping_add((v >> 1) ^ 0u32.wrapping_sub(v & 1)))
+    }
+}
+
+fn write_vbyte(out:&mut Vec<u8>, mut value:u32) {
+    while value >= 0x80 {
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_index(out:&mut Vec<u8>, index:u32, last:u32) {
+    let d = index.wrapping_sub(last) as i32;
+    write_vbyte(out, ((d << 1) ^ (d >> 31)) as u32);
+}
+
+pub fn decode_triangles(data:&[u8], count:usize) -> Result<Vec<u32>, String> {
+    if !count.is_multiple_of(3) || data.len() < 1 + count / 3 + 16 {
+        return Err(String::from("Meshopt index buffer is too short"));
+    }
+    let version = data[0] & 0x0f;
+    if data[0] & 0xf0 != TRIANGLE_HEADER || version > 1 {
+        return Err(format!("Unsupported meshopt index buffer header {:#x}", data[0]));
+    }
+    let fec_max = if version >= 1 { 13 } else { 15 };
+    let code_aux_table = &data[data.len() - 16..];
+    let mut reader = Reader {
+        data,
+        pos: 1 + count / 3,
+        end: data.len() - 16,
+    };
+    let mut fifos = Fifos::new();
+    let (mut next, mut last) = (0u32, 0u32);
+    let mut out = Vec::with_capacity(count);
+    for code in data[1..1 + count / 3].iter().copied() {
+        if code < 0xf0 {
+            // Edge from the FIFO and a third vertex
+            let (a, b) = fifos.edge((code >> 4) as usize);
+            let fec = (code & 15) as u32;
+            let c = if fec < fec_max {
+                let c = if fec == 0 { next } else { fifos.vertex(fec as usize + 1) };
+                if fec == 0 {
+                    next += 1;
+                }
+                fifos.push_vertex(c, fec == 0);
+                c
+            } else {
+                last = if fec != 15 { last.wrapping_add(fec).wrapping_sub(fec ^ 3) } else { reader.index(last)? };
+                fifos.push_vertex(last, true);
+                last
+            };
+            out.extend_from_slice(&[a, b, c]);
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+        } else {
+            let (a, b, c, feb, fec) = if code < 0xfe {
+                let aux = code_aux_table[(code & 15) as usize];
+                let (feb, fec) = ((aux >> 4) as usize, (aux & 15) as usize);
+                let a = next;
+                next += 1;
+                let b = if feb == 0 { next } else { fifos.vertex(feb) };
+                next += (feb == 0) as u32;
+                let c = if fec == 0 { next } else { fifos.vertex(fec) };
+                next += (fec == 0) as u32;
+                (a, b, c, feb, fec)
+            } else {
+                let aux = reader.byte()?;
+                let (feb, fec) = ((aux >> 4) as usize, (aux & 15) as usize);
+                // A zero aux byte, which the table could have held, restarts the new vertices from 0
+                if aux == 0 {
+                    next = 0;
+                }
+                let mut a = if code == 0xfe { next } else { 0 };
+                next += (code == 0xfe) as u32;
+                let mut b = if feb == 0 { next } else { fifos.vertex(feb) };
+                next += (feb == 0) as u32;
+                let mut c = if fec == 0 { next } else { fifos.vertex(fec) };
+                next += (fec == 0) as u32;
+                if code == 0xff {
+                    a = reader.index(last)?;
+                    last = a;
+                }
+                if feb == 15 {
+                    b = reader.index(last)?;
+                    last = b;
+                }
+                if fec == 15 {
+                    c = reader.index(last)?;
+                    last = c;
+                }
+                (a, b, c, feb, fec)
+            };
+            out.extend_from_slice(&[a, b, c]);
+            fifos.push_vertex(a, true);
+            fifos.push_vertex(b, feb == 0 || feb == 15);
+            fifos.push_vertex(c, fec == 0 || fec == 15);
+            fifos.push_edge(b, a);
+            fifos.push_edge(c, b);
+            fifos.push_edge(a, c);
+        }
+    }
+    if reader.pos != reader.end {
+        return Err(String::from("Meshopt index buffer has trailing data"));
+    }
+    Ok(out)
+}
+
+// Version 1 encoding; the decoded triangles may be rotated but keep their winding
+pub fn encode_triangles(indices:&[u32]) -> Vec<u8> {
+    let mut codes = vec![TRIANGLE_HEADER | 1];
+    let mut data = Vec::new();
+    let mut fifos = Fifos::new();
+    let (mut next, mut last) = (0u32, 0u32);
+    for triangle in indices.chunks_exact(3) {
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        match fifos.find_edge(a, b, c) {
+            Some((fe, [a, b, c])) => {
+                let fec = if c == next {
+                    next += 1;
+                    0
+                } else {
+                    match fifos.find_vertex(c) {
+                        Some(back) if back - 1 < 13 && back > 1 => back - 1,
+                        _ if c == last.wrapping_sub(1) => 13,
+                        _ if c == last.wrapping_add(1) => 14,
+                        _ => 15,
+                    }
+                };
+                codes.push((fe << 4) as u8 | fec as u8);
+                if fec == 15 {
+                    write_index(&mut data, c, last);
+                }
+                if fec >= 13 {
+                    last = c;
+                }
+                fifos.push_vertex(c, fec == 0 || fec >= 13);
+                fifos.push_edge(c, b);
+                fifos.push_edge(a, c);
+            }
+            None => {
+                // Start from the next new vertex when there is one
+                let [a, b, c] = if b == next { [b, c, a] } else if c == next { [c, a, b] } else { [a, b, c] };
+                // 0 1 2 past the first vertices restarts them, like a new mesh in the same buffer
+                let restart = [a, b, c] == [0, 1, 2] && next > 0;
+                if restart {
+                    next = 0;
+                }
+                let fea = if a == next {
+                    next += 1;
+                    0
+                } else {
+                    15
+                };
+                let lookup = |v:u32, next:&mut u32| {
+                    if v == *next {
+                        *next += 1;
+                        0
+                    } else {
+                        match fifos.find_vertex(v) {
+                            Some(back) if back < 15 => back,
+                            _ => 15,
+                        }
+                    }
+                };
+                let feb = lookup(b, &mut next);
+                let fec = lookup(c, &mut next);
+                let aux = ((feb << 4) | fec) as u8;
+                match CODE_AUX_TABLE[..14].iter().position(|x| *x == aux) {
+                    Some(i) if fea == 0 && !restart => codes.push(0xf0 | i as u8),
+                    _ => {
+                        codes.push(if fea == 0 { 0xfe } else { 0xff });
+                        data.push(aux);
+                    }
+                }
+                if fea == 15 {
+                    write_index(&mut data, a, last);
+                    last = a;
+                }
+                if feb == 15 {
+                    write_index(&mut data, b, last);
+                    last = b;
+                }
+                if fec == 15 {
+                    write_index(&mut data, c, last);
+                    last = c;
+                }
+                fifos.push_vertex(a, true);
+                fifos.push_vertex(b, feb == 0 || feb == 15);
+                fifos.push_vertex(c, fec == 0 || fec == 15);
+                fifos.push_edge(b, a);
+                fifos.push_edge(c, b);
+                fifos.push_edge(a, c);
+            }
+        }
+    }
+    codes.extend_from_slice(&data);
+    codes.extend_from_slice(&CODE_AUX_TABLE);
+    codes
+}
+
+pub fn decode_sequence(data:&[u8], count:usize) -> Result<Vec<u32>, String> {
+    if data.len() < 1 + count + 4 {
+        return Err(String::from("Meshopt index sequence is too short"));
+    }
+    if data[0] & 0xf0 != SEQUENCE_HEADER || data[0] & 0x0f > 1 {
+        return Err(format!("Unsupported meshopt index sequence header {:#x}", data[0]));
+    }
+    let mut reader = Reader {
+        data,
+        pos: 1,
+        end: data.len() - 4,
+    };
+    let mut last = [0u32; 2];
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        let v = reader.vbyte()?;
+        let current = (v & 1) as usize;
+        let v = v >> 1;
+        let index = last[current].wrapping_add((v >> 1) ^ 0u32.wrapping_sub(v & 1));
+        last[current] = index;
+        out.push(index);
+    }
+    if reader.pos != reader.end {
+        return Err(String::from("Meshopt index sequence has trailing data"));
+    }
+    Ok(out)
+}
+
+pub fn encode_sequence(indices:&[u32]) -> Vec<u8> {
+    let mut out = vec![SEQUENCE_HEADER | 1];
+    let mut last = [0u32; 2];
+    let mut current = 0;
+    for index in indices {
+        let delta = |base:u32| (index.wrapping_sub(base) as i32).unsigned_abs();
+        // Deltas are taken from whichever of the two last indices is closer
+        if delta(last[current ^ 1]) < delta(last[current]) {
+            current ^= 1;
+        }
+        let d = index.wrapping_sub(last[current]) as i32;
+        let v = ((d << 1) ^ (d >> 31)) as u32;
+        write_vbyte(&mut out, (v << 1) | current as u32);
+        last[current] = *index;
+    }
+    out.extend_from_slice(&[0; 4]);
+    out
+}
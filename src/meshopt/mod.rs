@@ -0,0 +1,224 @@
+// EXT_meshopt_compression bufferView codecs
+mod filter;
+mod index;
+mod vertex;
+
+pub use filter::encode_octahedral;
+
+// Decode the compressed data of a bufferView into count elements of stride bytes
+pub fn decode(data:&[u8], count:usize, stride:usize, mode:&str, filter:&str) -> Result<Vec<u8>, String> {
+    let mut out = match mode {
+        "ATTRIBUTES" => vertex::decode(data, count, stride)?,
+        "TRIANGLES" => index_bytes(&index::decode_triangles(data, count)?, stride)?,
+        "INDICES" => index_bytes(&index::decode_sequence(data, count)?, stride)?,
+        _ => return Err(format!("Unknown meshopt mode {}", mode)),
+    };
+    if mode != "ATTRIBUTES" && filter != "NONE" {
+        return Err(format!("Meshopt filter {} only applies to attributes", filter));
+    }
+    match filter {
+        "NONE" => {}
+        "OCTAHEDRAL" => filter::decode_octahedral(&mut out, stride)?,
+        "QUATERNION" => filter::decode_quaternion(&mut out, stride)?,
+        "EXPONENTIAL" => filter::decode_exponential(&mut out, stride)?,
+        _ => return Err(format!("Unknown meshopt filter {}", filter)),
+    }
+    Ok(out)
+}
+
+// Compress a bufferView of count elements of stride bytes; indices are 2 or 4 bytes wide
+pub fn encode(data:&[u8], stride:usize, mode:&str) -> Result<Vec<u8>, String> {
+    match mode {
+        "ATTRIBUTES" => vertex::encode(data, stride),
+        "TRIANGLES" => Ok(index::encode_triangles(&read_indices(data, stride)?)),
+        "INDICES" => Ok(index::encode_sequence(&read_indices(data, stride)?)),
+        _ => Err(format!("Unknown meshopt mode {}", mode)),
+    }
+}
+
+fn index_bytes(indices:&[u32], stride:usize) -> Result<Vec<u8>, String> {
+    match stride {
+        2 => Ok(indices.iter().flat_map(|i| (*i as u16).to_le_bytes()).collect()),
+        4 => Ok(indices.iter().flat_map(|i| i.to_le_bytes()).collect()),
+        _ => Err(format!("Invalid meshopt index size {}", stride)),
+    }
+}
+
+fn read_indices(data:&[u8], stride:usize) -> Result<Vec<u32>, String> {
+    match stride {
+        2 => Ok(data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32).collect()),
+        4 => Ok(data.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect()),
+        _ => Err(format!("Invalid meshopt index size {}", stride)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors of the meshoptimizer test suite
+    const INDEX_V0: [u8; 27] = [0xe0, 0xf0, 0x10, 0xfe, 0xff, 0xf0, 0x0c, 0xff, 0x02, 0x02, 0x02, 0x00, 0x76, 0x87, 0x56, 0x67,
+                                0x78, 0xa9, 0x86, 0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00];
+    const INDICES: [u32; 12] = [0, 1, 2, 2, 1, 3, 4, 6, 5, 7, 8, 9];
+    // Version 1 restarts the new vertices and codes the last index plus or minus one
+    const INDEX_V1: [u8; 24] = [0xe1, 0xf0, 0x10, 0xfe, 0x1f, 0x3d, 0x00, 0x0a, 0x00, 0x76, 0x87, 0x56, 0x67, 0x78, 0xa9, 0x86,
+                                0x65, 0x89, 0x68, 0x98, 0x01, 0x69, 0x00, 0x00];
+    const INDICES_V1: [u32; 15] = [0, 1, 2, 2, 1, 3, 0, 1, 2, 2, 1, 5, 2, 1, 4];
+
+    fn words(values:&[u32]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    // Triangles with their first vertex the smallest, the index codec keeps the winding but not the rotation
+    fn rotated(indices:&[u8]) -> Vec<[u32; 3]> {
+        read_indices(indices, 4).unwrap().chunks(3).map(|t| {
+            let k = (0..3).min_by_key(|k| t[*k]).unwrap();
+            [t[k], t[(k + 1) % 3], t[(k + 2) % 3]]
+        }).collect()
+    }
+
+    // Four vertices of 16 bytes: three u16 positions, two u8 normal coordinates, two u16 texture coordinates
+    fn vertices() -> Vec<u8> {
+        [[0u16, 0, 0, 0, 0, 0, 0, 0], [300, 0, 0, 0, 500, 0, 0, 0], [0, 300, 0, 0, 0, 500, 0, 0], [300, 300, 0, 0, 500, 500, 0, 0]]
+            .iter().flatten().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    fn vertex_v0() -> Vec<u8> {
+        let mut data = vec![0xa0];
+        for channel in [&[0x01, 0x3f, 0x00, 0x00, 0x00, 0x58, 0x57, 0x58][..], &[0x01, 0x26, 0x00, 0x00, 0x00],
+                        &[0x01, 0x0c, 0x00, 0x00, 0x00, 0x58], &[0x01, 0x08, 0x00, 0x00, 0x00], &[0x00], &[0x00], &[0x00], &[0x00],
+                        &[0x01, 0x3f, 0x00, 0x00, 0x00, 0x17, 0x18, 0x17], &[0x01, 0x26, 0x00, 0x00, 0x00],
+                        &[0x01, 0x0c, 0x00, 0x00, 0x00, 0x17], &[0x01, 0x08, 0x00, 0x00, 0x00], &[0x00], &[0x00], &[0x00], &[0x00]] {
+            data.extend_from_slice(channel);
+        }
+        // The tail holds the first vertex, all zeros
+        data.extend_from_slice(&[0; 32]);
+        data
+    }
+
+    fn shorts(values:&[u16]) -> Vec<u8> {
+        values.iter().flat_map(|x| x.to_le_bytes()).collect()
+    }
+
+    // Test counterparts of the quaternion and exponential filters, which the writer does not use
+    fn encode_quaternion(quaternions:&[[f32; 4]], bits:u32) -> Vec<u8> {
+        let scaler = 2f32.sqrt() * ((1 << (bits - 1)) - 1) as f32;
+        let mut data = Vec::new();
+        for q in quaternions {
+            let qc = (0..4).fold(0, |qc, k| if q[k].abs() > q[qc].abs() { k } else { qc });
+            let sign = if q[qc] < 0.0 { -1.0 } else { 1.0 };
+            for k in 1..4 {
+                data.extend_from_slice(&((q[(qc + k) & 3] * scaler * sign).round() as i16).to_le_bytes());
+            }
+            data.extend_from_slice(&(((((1 << (bits - 1)) - 1) & !3) | qc as i32) as i16).to_le_bytes());
+        }
+        data
+    }
+
+    fn encode_exponential(values:&[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| {
+            // 23 bits of mantissa and the sign, like frexp
+            let e = if *v == 0.0 { 0 } else { v.abs().log2().floor() as i32 + 1 - 23 };
+            let m = (*v as f64 / 2f64.powi(e)).round() as i32;
+            ((e << 24) | (m & 0xff_ffff)).to_le_bytes()
+        }).collect()
+    }
+
+    #[test]
+    fn decodes_the_reference_buffers() {
+        assert_eq!(decode(&INDEX_V0, 12, 4, "TRIANGLES", "NONE").unwrap(), words(&INDICES));
+        assert_eq!(decode(&INDEX_V1, 15, 4, "TRIANGLES", "NONE").unwrap(), words(&INDICES_V1));
+        assert_eq!(decode(&vertex_v0(), 4, 16, "ATTRIBUTES", "NONE").unwrap(), vertices());
+    }
+
+    #[test]
+    fn encodes_the_reference_buffers() {
+        assert_eq!(encode(&words(&INDICES_V1), 4, "TRIANGLES").unwrap(), INDEX_V1);
+        assert_eq!(encode(&vertices(), 16, "ATTRIBUTES").unwrap(), vertex_v0());
+    }
+
+    #[test]
+    fn codecs_round_trip() {
+        // Triangles of a 20 by 20 grid, with their vertices
+        let n = 20u32;
+        let mut triangles = Vec::new();
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let i = y * n + x;
+                triangles.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+            }
+        }
+        for stride in [2, 4] {
+            let data = index_bytes(&triangles, stride).unwrap();
+            let count = triangles.len();
+            let decoded = decode(&encode(&data, stride, "TRIANGLES").unwrap(), count, stride, "TRIANGLES", "NONE").unwrap();
+            assert_eq!(rotated(&index_bytes(&read_indices(&decoded, stride).unwrap(), 4).unwrap()),
+                       rotated(&index_bytes(&triangles, 4).unwrap()));
+            assert_eq!(decode(&encode(&data, stride, "INDICES").unwrap(), count, stride, "INDICES", "NONE").unwrap(), data);
+        }
+        let vertices: Vec<u8> = (0..n * n).flat_map(|i| {
+            let (x, y) = ((i % n) as f32, (i / n) as f32);
+            [x, y, (x * 0.3).sin() * (y * 0.2).cos()].into_iter().flat_map(|c| c.to_le_bytes())
+        }).collect();
+        assert_eq!(decode(&encode(&vertices, 12, "ATTRIBUTES").unwrap(), (n * n) as usize, 12, "ATTRIBUTES", "NONE").unwrap(),
+                   vertices);
+    }
+
+    #[test]
+    fn filters_decode_the_reference_vectors() {
+        let mut oct8: Vec<u8> = vec![0, 1, 127, 0, 0, 187, 127, 1, 255, 1, 127, 0, 14, 130, 127, 1];
+        filter::decode_octahedral(&mut oct8, 4).unwrap();
+        assert_eq!(oct8, vec![0, 1, 127, 0, 0, 159, 82, 1, 255, 1, 127, 0, 1, 130, 241, 1]);
+
+        let mut oct12 = shorts(&[0, 1, 2047, 0, 0, 1870, 2047, 1, 2017, 1, 2047, 0, 14, 1300, 2047, 1]);
+        filter::decode_octahedral(&mut oct12, 8).unwrap();
+        assert_eq!(oct12, shorts(&[0, 16, 32767, 0, 0, 32621, 3088, 1, 32764, 16, 471, 0, 307, 28541, 16093, 1]));
+
+        let mut quat12 = shorts(&[0, 1, 0, 0x7fc, 0, 1870, 0, 0x7fd, 2017, 1, 0, 0x7fe, 14, 1300, 0, 0x7ff]);
+        filter::decode_quaternion(&mut quat12, 8).unwrap();
+        assert_eq!(quat12, shorts(&[32767, 0, 11, 0, 0, 25013, 0, 21166, 11, 0, 23504, 22830, 158, 14715, 0, 29277]));
+
+        let mut exp: Vec<u8> = [0u32, 0xff000003, 0x02fffff7, 0xfe7fffff].iter().flat_map(|x| x.to_le_bytes()).collect();
+        filter::decode_exponential(&mut exp, 4).unwrap();
+        let expected: Vec<u8> = [0u32, 0x3fc00000, 0xc2100000, 0x49fffffe].iter().flat_map(|x| x.to_le_bytes()).collect();
+        assert_eq!(exp, expected);
+    }
+
+    #[test]
+    fn filters_round_trip() {
+        let normals: Vec<[f32; 3]> = (0..64).map(|i| {
+            let (a, b) = (i as f32 * 0.7, i as f32 * 0.3 - 1.5);
+            [a.cos() * b.cos(), a.sin() * b.cos(), b.sin()]
+        }).collect();
+        let mut data = encode_octahedral(&normals);
+        filter::decode_octahedral(&mut data, 4).unwrap();
+        for (n, element) in normals.iter().zip(data.chunks(4)) {
+            // Rounded to 8 bits, the decoded vector is not quite of unit length
+            let decoded: Vec<f32> = element[..3].iter().map(|x| *x as i8 as f32).collect();
+            let length = decoded.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let dot: f32 = (0..3).map(|c| n[c] * decoded[c] / length).sum();
+            assert!(dot > 0.999, "{:?} decoded as {:?}", n, decoded);
+        }
+
+        let quaternions: Vec<[f32; 4]> = (0..64).map(|i| {
+            let q = [(i as f32 * 0.9).sin(), (i as f32 * 0.4).cos(), i as f32 * 0.05 - 1.6, 0.5];
+            let length = q.iter().map(|x| x * x).sum::<f32>().sqrt();
+            q.map(|x| x / length)
+        }).collect();
+        let mut data = encode_quaternion(&quaternions, 12);
+        filter::decode_quaternion(&mut data, 8).unwrap();
+        for (q, element) in quaternions.iter().zip(data.chunks(8)) {
+            // Either sign is the same rotation
+            let dot: f32 = (0..4).map(|c| q[c] * filter::read_i16(element, c) as f32 / 32767.0).sum();
+            assert!(dot.abs() > 0.9999, "{:?} decoded with a dot product of {}", q, dot);
+        }
+
+        let values = [0.0f32, 1.0, -2.5, 1e-6, 123456.78, -0.0625, 3.0e20];
+        let mut data = encode_exponential(&values);
+        filter::decode_exponential(&mut data, 4).unwrap();
+        for (v, bytes) in values.iter().zip(data.chunks(4)) {
+            let decoded = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            assert!((decoded - v).abs() <= v.abs() * 2f32.powi(-22), "{} decoded as {}", v, decoded);
+        }
+    }
+}
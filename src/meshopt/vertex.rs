@@ -0,0 +1,191 @@
+// Vertex buffer codec (version 0): bytes are transposed per component, delta coded and bit packed in groups of 16
+const HEADER: u8 = 0xa0;
+const BYTE_GROUP_SIZE: usize = 16;
+const BLOCK_SIZE_BYTES: usize = 8192;
+const BLOCK_MAX_SIZE: usize = 256;
+const TAIL_MAX_SIZE: usize = 32;
+
+fn block_size(vertex_size:usize) -> usize {
+    let result = (BLOCK_SIZE_BYTES / vertex_size) & !(BYTE_GROUP_SIZE - 1);
+    result.min(BLOCK_MAX_SIZE)
+}
+
+fn check_vertex_size(vertex_size:usize) -> Result<(), String> {
+    if vertex_size == 0 || vertex_size > 256 || !vertex_size.is_multiple_of(4) {
+        return Err(format!("Invalid meshopt vertex size {}", vertex_size));
+    }
+    Ok(())
+}
+
+pub fn decode(data:&[u8], count:usize, vertex_size:usize) -> Result<Vec<u8>, String> {
+    check_vertex_size(vertex_size)?;
+    let tail_size = vertex_size.max(TAIL_MAX_SIZE);
+    if data.len() < 1 + tail_size {
+        return Err(String::from("Meshopt vertex buffer is too short"));
+    }
+    if data[0] & 0xf0 != HEADER || data[0] & 0x0f != 0 {
+        return Err(format!("Unsupported meshopt vertex buffer header {:#x}", data[0]));
+    }
+    let end = data.len() - tail_size;
+    let mut last_vertex = data[data.len() - vertex_size..].to_vec();
+    let mut out = Vec::with_capacity(count * vertex_size);
+    let mut pos = 1;
+    let mut buffer = [0u8; BLOCK_MAX_SIZE];
+    let mut vertex_offset = 0;
+    while vertex_offset < count {
+        let block = block_size(vertex_size).min(count - vertex_offset);
+        let aligned = (block + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        let start = out.len();
+        out.resize(start + block * vertex_size, 0);
+        for k in 0..vertex_size {
+            pos = decode_bytes(data, pos, end, &mut buffer[..aligned])?;
+            let mut p = last_vertex[k];
+            for (i, byte) in buffer[..block].iter().enumerate() {
+                let v = unzigzag8(*byte).wrapping_add(p);
+                out[start + i * vertex_size + k] = v;
+                p = v;
+            }
+        }
+        last_vertex.copy_from_slice(&out[out.len() - vertex_size..]);
+        vertex_offset += block;
+    }
+    if pos != end {
+        return Err(String::from("Meshopt vertex buffer has trailing data"));
+    }
+    Ok(out)
+}
+
+fn decode_bytes(data:&[u8], mut pos:usize, end:usize, buffer:&mut [u8]) -> Result<usize, String> {
+    let header_size = (buffer.len() / BYTE_GROUP_SIZE).div_ceil(4);
+    if end - pos < header_size {
+        return Err(String::from("Meshopt vertex buffer is truncated"));
+    }
+    let header = &data[pos..pos + header_size];
+    pos += header_size;
+    for (g, group) in buffer.chunks_mut(BYTE_GROUP_SIZE).enumerate() {
+        let bitslog2 = (header[g / 4] >> ((g % 4) * 2)) & 3;
+        pos = decode_group(data, pos, end, group, bitslog2)?;
+    }
+    Ok(pos)
+}
+
+fn decode_group(data:&[u8], pos:usize, end:usize, group:&mut [u8], bitslog2:u8) -> Result<usize, String> {
+    let truncated = || String::from("Meshopt vertex buffer is truncated");
+    match bitslog2 {
+        0 => {
+            group.fill(0);
+            Ok(pos)
+        }
+        3 => {
+            if end - pos < BYTE_GROUP_SIZE {
+                return Err(truncated());
+            }
+            group.copy_from_slice(&data[pos..pos + BYTE_GROUP_SIZE]);
+            Ok(pos + BYTE_GROUP_SIZE)
+        }
+        _ => {
+            // Values equal to the sentinel are stored in full after the packed bits
+            let bits = 1 << bitslog2;
+            let packed = BYTE_GROUP_SIZE * bits / 8;
+            if end - pos < packed {
+                return Err(truncated());
+            }
+            let sentinel = (1u8 << bits) - 1;
+            let mut extra = pos + packed;
+            for (i, value) in group.iter_mut().enumerate() {
+                let byte = data[pos + i * bits / 8];
+                let shift = 8 - bits - (i * bits) % 8;
+                let enc = (byte >> shift) & sentinel;
+                *value = if enc == sentinel {
+                    if extra >= end {
+                        return Err(truncated());
+                    }
+                    extra += 1;
+                    data[extra - 1]
+                } else {
+                    enc
+                };
+            }
+            Ok(extra)
+        }
+    }
+}
+
+pub fn encode(vertices:&[u8], vertex_size:usize) -> Result<Vec<u8>, String> {
+    check_vertex_size(vertex_size)?;
+    if !vertices.len().is_multiple_of(vertex_size) {
+        return Err(String::from("Meshopt vertex data is not a whole number of vertices"));
+    }
+    let count = vertices.len() / vertex_size;
+    let mut out = vec![HEADER];
+    let first_vertex = if count > 0 { vertices[..vertex_size].to_vec() } else { vec![0; vertex_size] };
+    let mut last_vertex = first_vertex.clone();
+    let mut buffer = [0u8; BLOCK_MAX_SIZE];
+    let mut vertex_offset = 0;
+    while vertex_offset < count {
+        let block = block_size(vertex_size).min(count - vertex_offset);
+        let aligned = (block + BYTE_GROUP_SIZE - 1) & !(BYTE_GROUP_SIZE - 1);
+        let block_data = &vertices[vertex_offset * vertex_size..(vertex_offset + block) * vertex_size];
+        for k in 0..vertex_size {
+            buffer.fill(0);
+            let mut p = last_vertex[k];
+            for i in 0..block {
+                let v = block_data[i * vertex_size + k];
+                buffer[i] = zigzag8(v.wrapping_sub(p));
+                p = v;
+            }
+            encode_bytes(&mut out, &buffer[..aligned]);
+        }
+        last_vertex.copy_from_slice(&block_data[(block - 1) * vertex_size..]);
+        vertex_offset += block;
+    }
+    let tail_size = vertex_size.max(TAIL_MAX_SIZE);
+    out.resize(out.len() + tail_size - vertex_size, 0);
+    out.extend_from_slice(&first_vertex);
+    Ok(out)
+}
+
+fn encode_bytes(out:&mut Vec<u8>, buffer:&[u8]) {
+    let header_start = out.len();
+    out.resize(header_start + (buffer.len() / BYTE_GROUP_SIZE).div_ceil(4), 0);
+    for (g, group) in buffer.chunks(BYTE_GROUP_SIZE).enumerate() {
+        // Pick the packing that takes the fewest bytes
+        let bitslog2 = (0..4u8).min_by_key(|b| group_size(group, *b)).unwrap();
+        out[header_start + g / 4] |= bitslog2 << ((g % 4) * 2);
+        match bitslog2 {
+            0 => {}
+            3 => out.extend_from_slice(group),
+            _ => {
+                let bits = 1 << bitslog2;
+                let sentinel = (1u8 << bits) - 1;
+                let packed_start = out.len();
+                out.resize(packed_start + BYTE_GROUP_SIZE * bits / 8, 0);
+                for (i, value) in group.iter().enumerate() {
+                    let enc = if *value >= sentinel { sentinel } else { *value };
+                    out[packed_start + i * bits / 8] |= enc << (8 - bits - (i * bits) % 8);
+                }
+                out.extend(group.iter().filter(|value| **value >= sentinel));
+            }
+        }
+    }
+}
+
+fn group_size(group:&[u8], bitslog2:u8) -> usize {
+    match bitslog2 {
+        0 => if group.iter().all(|value| *value == 0) { 0 } else { usize::MAX },
+        3 => BYTE_GROUP_SIZE,
+        _ => {
+            let bits = 1 << bitslog2;
+            let sentinel = (1u8 << bits) - 1;
+            BYTE_GROUP_SIZE * bits / 8 + group.iter().filter(|value| **value >= sentinel).count()
+        }
+    }
+}
+
+fn zigzag8(v:u8) -> u8 {
+    ((v as i8) >> 7) as u8 ^ (v << 1)
+}
+
+fn unzigzag8(v:u8) -> u8 {
+    (0u8.wrapping_sub(v & 1)) ^ (v >> 1)
+}
@@ -0,0 +1,252 @@
+// What the edge collapses are ranked by and where they leave the vertex. A cost metric accumulates a state for
+// every vertex from the faces around it and tells the error of moving a vertex with that state, a placement policy
+// picks the position a collapse is evaluated at. The quadric error metric (Garland–Heckbert) and the placements of
+// the command line are built on them, other metrics plug into the collapse loop through decimate the same way.
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+// Condition number above which the placement of a collapse is not solved for, the pull towards the midpoint
+// alone giving about 1e3
+const MAX_CONDITION: f64 = 1e8;
+
+/// Error of moving a vertex, from a state accumulated over the faces around it. Painted weights multiply every
+/// value of a state, so it should grow with them.
+pub trait CostMetric: Debug + Sync {
+    /// State of a vertex before the faces around it are added, which costs nothing to move
+    fn zero(&self) -> Vec<f64>;
+    /// State a face adds to each of its corners
+    fn face(&self, v1:&Vector3<f32>, v2:&Vector3<f32>, v3:&Vector3<f32>) -> Vec<f64>;
+    /// State of the plane normal.p + d = 0, weighted by the squared length of the normal, added along creases
+    fn plane(&self, normal:&Vector3<f64>, d:f64) -> Vec<f64>;
+    /// State of the vertex two vertices collapse into, or of a vertex and one more face
+    fn combine(&self, a:&[f64], b:&[f64]) -> Vec<f64> {
+        a.iter().zip(b).map(|(a, b)| a + b).collect()
+    }
+    /// Error of moving a vertex of this state to the position
+    fn evaluate(&self, state:&[f64], position:&Vector3<f32>) -> f64;
+    /// The state as the quadratic form p.A.p + 2b.p + c, as (A, b), for the placements that solve for its minimum
+    fn quadric(&self, _state:&[f64]) -> Option<(Matrix3<f64>, Vector3<f64>)> {
+        None
+    }
+}
+
+/// Where the vertex left by the collapse of u and v goes, chosen before the faces around them change. The state
+/// is the one of u and v combined.
+pub trait PlacementPolicy: Debug + Sync {
+    fn place(&self, u:u32, v:u32, index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, state:&[f64],
+             metric:&dyn CostMetric) -> Vector3<f32>;
+}
+
+/// Sum of the squared distances to the planes of the faces, weighted by their area, as the 10 values of a
+/// symmetric 4x4 matrix
+#[derive(Debug)]
+pub struct Quadric;
+
+impl CostMetric for Quadric {
+    fn zero(&self) -> Vec<f64> {
+        vec![0.0; 10]
+    }
+
+    fn face(&self, v1:&Vector3<f32>, v2:&Vector3<f32>, v3:&Vector3<f32>) -> Vec<f64> {
+        get_k_matrix(v1, v2, v3)
+    }
+
+    fn plane(&self, normal:&Vector3<f64>, d:f64) -> Vec<f64> {
+        plane_quadric(normal, d)
+    }
+
+    fn evaluate(&self, q:&[f64], position:&Vector3<f32>) -> f64 {
+        // Cost: q11x^2 + 2q12xy + 2q13xz + 2q14x + q22y^2
+        //       + 2q23yz + 2q24y + q33z^2 + 2q34z + q44
+        // Q: 0 1 2 3
+        //    1 4 5 6
+        //    2 5 7 8
+        //    3 6 8 9
+        let p = position.cast::<f64>();
+        q[0]*p[0]*p[0] +
+        2.0*q[1]*p[0]*p[1] +
+        2.0*q[2]*p[0]*p[2] +
+        2.0*q[3]*p[0] +
+        q[4]*p[1]*p[1] +
+        2.0*q[5]*p[1]*p[2] +
+        2.0*q[6]*p[1] +
+        q[7]*p[2]*p[2] +
+        2.0*q[8]*p[2] + q[9]
+    }
+
+    fn quadric(&self, q:&[f64]) -> Option<(Matrix3<f64>, Vector3<f64>)> {
+        Some((Matrix3::new(q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]), Vector3::new(q[3], q[6], q[8])))
+    }
+}
+
+// Implement trait to form a k matrix(Fundamental Error Quadric), weighted by the area of the face
+pub fn get_k_matrix(v1:&Vector3<f32>, v2:&Vector3<f32>, v3:&Vector3<f32>) -> Vec<f64> {
+    let (v1, v2, v3) = (v1.cast::<f64>(), v2.cast::<f64>(), v3.cast::<f64>());
+    // Calculate the normal vector to the plane, twice the area of the face long
+    let normal = (v2 - v1).cross(&(v3 - v1));
+    // Unit normal scaled so that the squared distance is weighted by the area
+    let area = normal.norm() / 2.0;
+    let normal = normal.try_normalize(0.0).map_or(Vector3::zeros(), |n| n * area.sqrt());
+    // Calculate the distance from the origin to the plane
+    let d = -normal.dot(&v1);
+    plane_quadric(&normal, d)
+}
+
+// Quadric of the squared distance to the plane normal.p + d = 0, scaled by the squared length of the normal
+fn plane_quadric(normal:&Vector3<f64>, d:f64) -> Vec<f64> {
+    // Extract components of the normal vector
+    let (a, b, c) = (normal[0], normal[1], normal[2]);
+    //     a^2   ab    ac    ad
+    //     ab    b^2   bc    bd
+    //     ac    bc    c^2   cd
+    //     ad    bd    cd    d^2
+    vec![a*a, a*b, a*c, a*d, b*b, b*c, b*d, c*c, c*d, d*d]
+}
+
+/// Halfway between u and v
+#[derive(Debug)]
+pub struct Midpoint;
+
+impl PlacementPolicy for Midpoint {
+    fn place(&self, u:u32, v:u32, _index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, _state:&[f64],
+             _metric:&dyn CostMetric) -> Vector3<f32> {
+        (position_list[&u] + position_list[&v]) / 2.0
+    }
+}
+
+/// The point of least error of the metric, or the midpoint when the metric has no quadric to solve
+#[derive(Debug)]
+pub struct Optimal;
+
+impl PlacementPolicy for Optimal {
+    fn place(&self, u:u32, v:u32, _index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, state:&[f64],
+             metric:&dyn CostMetric) -> Vector3<f32> {
+        solve(metric.quadric(state), (position_list[&u] + position_list[&v]) / 2.0, Vec::new())
+    }
+}
+
+/// The point of least error that keeps the volume under the faces around u and v and the area vector of their
+/// open borders (Lindstrom–Turk). Every collapse leaves the signed volume between the origin and the faces around it
+/// as it was, so a closed mesh keeps its enclosed volume up to rounding, and the open borders keep the area they span
+/// as a vector, not their length or shape. Nothing bounds the distance to the original surface beyond the quadric
+/// being minimized under these constraints, and a collapse whose constraints cannot be solved for takes the midpoint
+/// and keeps neither.
+#[derive(Debug)]
+pub struct PreserveVolume;
+
+impl PlacementPolicy for PreserveVolume {
+    fn place(&self, u:u32, v:u32, index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, state:&[f64],
+             metric:&dyn CostMetric) -> Vector3<f32> {
+        let position = |i:u32| position_list[&i].cast::<f64>();
+        // The face sets of the neighbours are not kept up to date through the collapses, the index list is
+        let faces: Vec<&[u32]> = index_list.chunks_exact(3).filter(|t| t.contains(&u) || t.contains(&v)).collect();
+        // Volume: the tetrahedra from the origin to the faces that stay, with the new vertex in place of u or v
+        let mut constraints: Vec<(Vector3<f64>, f64)> = Vec::new();
+        let (mut normal, mut volume) = (Vector3::zeros(), 0.0);
+        for face in &faces {
+            let (a, b, c) = (position(face[0]), position(face[1]), position(face[2]));
+            volume += a.dot(&b.cross(&c));
+            let moved: Vec<usize> = (0..3).filter(|k| face[*k] == u || face[*k] == v).collect();
+            if let [k] = moved[..] {
+                normal += position(face[(k + 1) % 3]).cross(&position(face[(k + 2) % 3]));
+            }
+        }
+        constraints.push((normal, volume));
+        // Border area: the open edges from or to u and v, which become edges from or to the new vertex
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for face in &faces {
+            for (a, b) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        let (mut direction, mut area) = (Vector3::zeros(), Vector3::zeros());
+        for face in &faces {
+            for (a, b) in [(face[0], face[1]), (face[1], face[2]), (face[2], face[0])] {
+                let moved = (a == u || a == v, b == u || b == v);
+                if edges[&(a.min(b), a.max(b))] != 1 || moved == (false, false) {
+                    continue;
+                }
+                area += position(a).cross(&position(b));
+                match moved {
+                    (true, false) => direction += position(b),
+                    (false, true) => direction -= position(a),
+                    _ => {}
+                }
+            }
+        }
+        // p x direction = area, one row for each axis. Only two of them are independent, the row of the axis along
+        // the direction vanishes and is left out
+        let rows = Matrix3::new(0.0, direction.z, -direction.y, -direction.z, 0.0, direction.x, direction.y, -direction.x, 0.0);
+        for r in 0..3 {
+            if rows.row(r).norm() > direction.norm() * 1f64.to_radians().sin() {
+                constraints.push((rows.row(r).transpose(), area[r]));
+            }
+        }
+        solve(metric.quadric(state), (position_list[&u] + position_list[&v]) / 2.0, constraints)
+    }
+}
+
+// Least error point of the quadric under the constraints n.p = d, pulled slightly towards the midpoint so that flat
+// regions have a single solution. Constraints nearly parallel to the ones before are dropped, the midpoint is kept
+// when the solve fails.
+fn solve(quadric:Option<(Matrix3<f64>, Vector3<f64>)>, midpoint:Vector3<f32>,
+         constraints:Vec<(Vector3<f64>, f64)>) -> Vector3<f32> {
+    let mut accepted: Vec<(Vector3<f64>, f64)> = Vec::new();
+    let mut basis: Vec<Vector3<f64>> = Vec::new();
+    for (n, d) in constraints {
+        let residual = basis.iter().fold(n, |r, b| r - b * b.dot(&r));
+        if n.norm() > 0.0 && residual.norm() > n.norm() * 1f64.to_radians().sin() {
+            basis.push(residual.normalize());
+            accepted.push((n, d));
+        }
+    }
+    let (quadric, linear) = quadric.unwrap_or((Matrix3::zeros(), Vector3::zeros()));
+    let m = midpoint.cast::<f64>();
+    // The quadric is brought to a unit trace and the constraints to unit normals, which leaves the solution as it is
+    // and the condition number to the geometry rather than the units
+    let unit = if quadric.trace() > 0.0 { 1.0 / quadric.trace() } else { 0.0 };
+    let pull = 1e-3;
+    let size = 3 + accepted.len();
+    let mut system = DMatrix::zeros(size, size);
+    let mut rhs = DVector::zeros(size);
+    system.view_mut((0, 0), (3, 3)).copy_from(&(quadric * unit + Matrix3::identity() * pull));
+    rhs.rows_mut(0, 3).copy_from(&(m * pull - linear * unit));
+    for (k, (n, d)) in accepted.iter().enumerate() {
+        system.view_mut((0, 3 + k), (3, 1)).copy_from(&(n / n.norm()));
+        system.view_mut((3 + k, 0), (1, 3)).copy_from(&(n / n.norm()).transpose());
+        rhs[3 + k] = *d / n.norm();
+    }
+    // Through the singular values, a system too ill-conditioned to trust keeps the midpoint
+    let svd = system.svd(true, true);
+    let (largest, smallest) = (svd.singular_values.max(), svd.singular_values.min());
+    if smallest * MAX_CONDITION <= largest || smallest.is_nan() {
+        return midpoint;
+    }
+    match svd.solve(&rhs, 0.0).ok() {
+        Some(solution) if solution.iter().all(|x| x.is_finite()) =>
+            Vector3::new(solution[0] as f32, solution[1] as f32, solution[2] as f32),
+        _ => midpoint,
+    }
+}
+
+/// Placements of the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Placement {
+    /// Halfway between the two vertices
+    Midpoint,
+    /// The point of least error
+    Optimal,
+    /// The point of least error that keeps the enclosed volume and the open border area, see PreserveVolume
+    Volume,
+}
+
+impl Placement {
+    pub fn policy(self) -> &'static dyn PlacementPolicy {
+        match self {
+            Placement::Midpoint => &Midpoint,
+            Placement::Optimal => &Optimal,
+            Placement::Volume => &PreserveVolume,
+        }
+    }
+}
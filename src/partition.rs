@@ -0,0 +1,136 @@
+// Parallel decimation of a triangle primitive in spatial chunks. The chunks are decimated side by side with the
+// vertices they share locked, then the triangles around the shared vertices go through a last pass of their own.
+// The chunks only depend on the mesh and are put back in their order, so the thread count does not change the result.
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{collapse_edges, Attributes, Options, Settings, Stats, Target};
+
+// Remove up to remove triangles like collapse_edges, chunk after chunk in parallel, and return the largest cost
+// with the collapses of all the chunks
+pub fn decimate(attributes:&mut Attributes, weights:&HashMap<u32, f32>, locked:&HashSet<u32>, settings:&Settings,
+                options:&Options, scale:f32, remove:usize) -> (f32, Stats) {
+    let tri_num = attributes.index_list.len() / 3;
+    let position = |i:&u32| attributes.position_list[i];
+    // Chunks on a grid over the bounds like the streaming mode, by the centroid of the triangles. A surface crosses
+    // about the square of the cells along an axis, so there are as many of them as chunks of chunk_triangles, the
+    // cells it leaves empty costing nothing
+    let (mut min, mut max) = (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN));
+    for i in &attributes.index_list {
+        min = min.inf(&position(i));
+        max = max.sup(&position(i));
+    }
+    let grid = ((tri_num as f64 / options.chunk_triangles.max(1) as f64).sqrt().ceil() as usize).max(1);
+    let size = (max - min).map(|x| x.max(f32::MIN_POSITIVE)) / grid as f32;
+    let mut chunks: BTreeMap<[usize; 3], Vec<usize>> = BTreeMap::new();
+    let mut vertex_chunk: HashMap<u32, Option<[usize; 3]>> = HashMap::new();
+    for (t, corners) in attributes.index_list.chunks_exact(3).enumerate() {
+        let centroid = corners.iter().map(position).sum::<Vector3<f32>>() / 3.0;
+        let c = (centroid - min).component_div(&size).map(|x| (x.max(0.0) as usize).min(grid - 1));
+        let c = [c.x, c.y, c.z];
+        chunks.entry(c).or_default().push(t);
+        for i in corners {
+            let chunk = vertex_chunk.entry(*i).or_insert(Some(c));
+            if *chunk != Some(c) {
+                *chunk = None;
+            }
+        }
+    }
+    let shared: HashSet<u32> = vertex_chunk.into_iter().filter(|(_, c)| c.is_none()).map(|(i, _)| i).collect();
+    info!(target: "decimate", "{} triangles in {} chunks, {} vertices shared between them", tri_num, chunks.len(), shared.len());
+
+    let chunks: Vec<Vec<usize>> = chunks.into_values().collect();
+    let decimated: Vec<(Attributes, Vec<u32>, f32, Stats)> = chunks.par_iter().map(|triangles| {
+        let (mut chunk, global) = extract(attributes, triangles);
+        let chunk_weights = global.iter().enumerate()
+            .filter_map(|(k, i)| weights.get(i).map(|w| (k as u32, *w))).collect();
+        let chunk_locked = global.iter().enumerate()
+            .filter(|(_, i)| locked.contains(i) || shared.contains(i)).map(|(k, _)| k as u32).collect();
+        let chunk_remove = remove * triangles.len() / tri_num;
+        let (error, stats) = collapse_edges(&mut chunk, &chunk_weights, chunk_locked, settings, options, scale, chunk_remove);
+        (chunk, global, error, stats)
+    }).collect();
+    let mut error = 0.0f32;
+    let mut stats = Stats::default();
+    let mut index_list = Vec::with_capacity(attributes.index_list.len());
+    for (chunk, global, e, chunk_stats) in decimated {
+        error = error.max(e);
+        stats.add(&chunk_stats);
+        put_back(attributes, &chunk, &global);
+        index_list.extend(chunk.index_list.iter().map(|k| global[*k as usize]));
+    }
+    attributes.index_list = index_list;
+
+    // The triangles around the shared vertices make the last chunk, everything else in it stays where it is
+    let left = remove.saturating_sub(tri_num - attributes.index_list.len() / 3);
+    if left == 0 || shared.is_empty() {
+        return (error, stats);
+    }
+    let (border, inner): (Vec<usize>, Vec<usize>) = (0..attributes.index_list.len() / 3)
+        .partition(|t| attributes.index_list[t * 3..t * 3 + 3].iter().any(|i| shared.contains(i)));
+    let (mut chunk, global) = extract(attributes, &border);
+    let chunk_weights = global.iter().enumerate()
+        .filter_map(|(k, i)| weights.get(i).map(|w| (k as u32, *w))).collect();
+    let chunk_locked = global.iter().enumerate()
+        .filter(|(_, i)| locked.contains(i) || !shared.contains(i)).map(|(k, _)| k as u32).collect();
+    info!(target: "decimate", "Border pass over {} triangles, {} left to remove", border.len(), left);
+    let (border_error, border_stats) = collapse_edges(&mut chunk, &chunk_weights, chunk_locked, settings, options, scale, left);
+    error = error.max(border_error);
+    stats.add(&border_stats);
+    put_back(attributes, &chunk, &global);
+    let mut index_list: Vec<u32> = inner.iter().flat_map(|t| attributes.index_list[t * 3..t * 3 + 3].to_vec()).collect();
+    index_list.extend(chunk.index_list.iter().map(|k| global[*k as usize]));
+    attributes.index_list = index_list;
+    (error, stats)
+}
+
+// Vertex data of the given triangles, renumbered from 0 as collapse_edges expects, with the index of every vertex
+// in the whole primitive
+fn extract(attributes:&Attributes, triangles:&[usize]) -> (Attributes, Vec<u32>) {
+    let mut local: HashMap<u32, u32> = HashMap::new();
+    let mut global = Vec::new();
+    let mut index_list = Vec::with_capacity(triangles.len() * 3);
+    for t in triangles {
+        for i in &attributes.index_list[t * 3..t * 3 + 3] {
+            index_list.push(*local.entry(*i).or_insert_with(|| {
+                global.push(*i);
+                global.len() as u32 - 1
+            }));
+        }
+    }
+    let chunk = Attributes {
+        index_list,
+        normal_list: pick(&attributes.normal_list, &global),
+        position_list: pick(&attributes.position_list, &global),
+        tangent_list: pick(&attributes.tangent_list, &global),
+        texcoord_0_list: pick(&attributes.texcoord_0_list, &global),
+        target_list: attributes.target_list.iter().map(|target| Target::new(pick(&target.position, &global),
+                        pick(&target.normal, &global), pick(&target.tangent, &global))).collect(),
+    };
+    (chunk, global)
+}
+
+fn pick<T:Clone>(list:&HashMap<u32, T>, global:&[u32]) -> HashMap<u32, T> {
+    global.iter().enumerate().filter_map(|(k, i)| list.get(i).map(|x| (k as u32, x.clone()))).collect()
+}
+
+// Write the vertices left in a decimated chunk back under their index in the whole primitive
+fn put_back(attributes:&mut Attributes, chunk:&Attributes, global:&[u32]) {
+    put(&mut attributes.normal_list, &chunk.normal_list, global);
+    put(&mut attributes.position_list, &chunk.position_list, global);
+    put(&mut attributes.tangent_list, &chunk.tangent_list, global);
+    put(&mut attributes.texcoord_0_list, &chunk.texcoord_0_list, global);
+    for (target, chunk) in attributes.target_list.iter_mut().zip(&chunk.target_list) {
+        put(&mut target.position, &chunk.position, global);
+        put(&mut target.normal, &chunk.normal, global);
+        put(&mut target.tangent, &chunk.tangent, global);
+    }
+}
+
+fn put<T:Clone>(list:&mut HashMap<u32, T>, chunk:&HashMap<u32, T>, global:&[u32]) {
+    for (k, x) in chunk {
+        list.insert(global[*k as usize], x.clone());
+    }
+}
@@ -0,0 +1,148 @@
+// Point cloud subsampling: voxel grid, Poisson disk and random sampling down to a target count
+use log::info;
+use nalgebra::Vector3;
+use std::collections::{HashMap, HashSet};
+
+/// Subsampling of a point cloud
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sampling {
+    /// The point nearest to the centroid of each cell of a voxel grid
+    Voxel,
+    /// Points no closer to each other than a radius
+    Poisson,
+    /// Points picked at random, the same ones on every run
+    Random,
+}
+
+// Keep about count of the points of the index list, each kept point keeps its own attributes
+pub fn sample(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, count:usize, method:Sampling) -> Vec<u32> {
+    let mut points: Vec<u32> = index_list.iter().copied().collect::<HashSet<u32>>().into_iter().collect();
+    points.sort();
+    let mut kept = if count >= points.len() {
+        points.clone()
+    } else {
+        match method {
+            Sampling::Voxel => voxel_grid(&points, position_list, count),
+            Sampling::Poisson => poisson_disk(&points, position_list, count),
+            Sampling::Random => {
+                let mut shuffled = points.clone();
+                shuffle(&mut shuffled);
+                shuffled.truncate(count);
+                shuffled
+            }
+        }
+    };
+    kept.sort();
+    info!(target: "decimate", "{} points sampled into {} ({:?})", points.len(), kept.len(), method);
+    kept
+}
+
+// Smallest cubic cell that puts the points in at most count cells, found by bisection on the cell size.
+// Each occupied cell keeps the point nearest to the centroid of its points.
+fn voxel_grid(points:&[u32], position_list:&HashMap<u32, Vector3<f32>>, count:usize) -> Vec<u32> {
+    let (min, max) = bounds(points, position_list);
+    let cells = |size:f32| {
+        let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        for i in points {
+            cells.entry(cell(position_list.get(i).unwrap(), &min, size)).or_default().push(*i);
+        }
+        cells
+    };
+    let (mut low, mut high) = (0.0f32, (max - min).max().max(f32::MIN_POSITIVE) * 2.0);
+    for _ in 0..32 {
+        let size = (low + high) / 2.0;
+        if size <= low || size >= high {
+            break;
+        }
+        if cells(size).len() > count {
+            low = size;
+        } else {
+            high = size;
+        }
+    }
+    let mut kept = Vec::with_capacity(count);
+    for members in cells(high).values() {
+        let centroid = members.iter().map(|i| position_list.get(i).unwrap()).sum::<Vector3<f32>>() / members.len() as f32;
+        let nearest = members.iter().min_by(|a, b| {
+            (position_list.get(a).unwrap() - centroid).norm_squared()
+                .total_cmp(&(position_list.get(b).unwrap() - centroid).norm_squared())
+        });
+        kept.push(*nearest.unwrap());
+    }
+    kept
+}
+
+// Dart throwing over the shuffled points: a point is kept when no kept point lies within the radius.
+// The radius is bisected until about count points are kept.
+fn poisson_disk(points:&[u32], position_list:&HashMap<u32, Vector3<f32>>, count:usize) -> Vec<u32> {
+    let (min, max) = bounds(points, position_list);
+    let mut order = points.to_vec();
+    shuffle(&mut order);
+    let throw = |radius:f32| {
+        let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+        let mut kept = Vec::new();
+        for i in &order {
+            let p = position_list.get(i).unwrap();
+            let c = cell(p, &min, radius);
+            let mut free = true;
+            'neighbours: for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        let neighbour = [c[0] + dx, c[1] + dy, c[2] + dz];
+                        if grid.get(&neighbour).is_some_and(|others| {
+                            others.iter().any(|j| (position_list.get(j).unwrap() - p).norm() < radius)
+                        }) {
+                            free = false;
+                            break 'neighbours;
+                        }
+                    }
+                }
+            }
+            if free {
+                grid.entry(c).or_default().push(*i);
+                kept.push(*i);
+            }
+        }
+        kept
+    };
+    let (mut low, mut high) = (0.0f32, (max - min).norm().max(f32::MIN_POSITIVE));
+    for _ in 0..32 {
+        let radius = (low + high) / 2.0;
+        if radius <= low || radius >= high {
+            break;
+        }
+        if throw(radius).len() > count {
+            low = radius;
+        } else {
+            high = radius;
+        }
+    }
+    throw(high)
+}
+
+fn bounds(points:&[u32], position_list:&HashMap<u32, Vector3<f32>>) -> (Vector3<f32>, Vector3<f32>) {
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for i in points {
+        let p = position_list.get(i).unwrap();
+        min = min.inf(p);
+        max = max.sup(p);
+    }
+    (min, max)
+}
+
+fn cell(p:&Vector3<f32>, min:&Vector3<f32>, size:f32) -> [i64; 3] {
+    let c = (p - min) / size;
+    [c.x.floor() as i64, c.y.floor() as i64, c.z.floor() as i64]
+}
+
+// Fisher–Yates with a fixed xorshift seed, so that the same file always gives the same points
+fn shuffle(points:&mut [u32]) {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for i in (1..points.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        points.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
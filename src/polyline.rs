@@ -0,0 +1,163 @@
+// Douglas–Peucker simplification of the polylines formed by a list of line segments
+use log::info;
+use nalgebra::Vector3;
+use std::collections::{HashMap, HashSet};
+
+// Segments of a chain between two fixed vertices, in walking order.
+// Segments keep their vertex indices so that attribute seams survive the simplification.
+struct Chain {
+    segments: Vec<(u32, u32)>,
+    closed: bool,
+}
+
+// Remove up to goal segments from the index pairs, moving no vertex further than max_error from the simplified line.
+// Endpoints and junctions, found by position, are kept. The largest distance of a removed vertex is returned, with the
+// vertices removed and the sum of their distances.
+pub fn simplify(index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, goal:usize,
+                max_error:f32) -> (Vec<u32>, f32, usize, f64) {
+    let position = |i:u32| *position_list.get(&i).unwrap();
+    // Vertices at the same position are the same polyline vertex
+    let mut first: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut node = |i:u32| {
+        let p = position(i);
+        *first.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_insert(i)
+    };
+    let mut segments = Vec::with_capacity(index_list.len() / 2);
+    let mut seen = HashSet::new();
+    for pair in index_list.chunks_exact(2) {
+        let (a, b) = (node(pair[0]), node(pair[1]));
+        if a != b && seen.insert((a.min(b), a.max(b))) {
+            segments.push(((pair[0], pair[1]), (a, b)));
+        }
+    }
+    let mut incident: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (s, (_, (a, b))) in segments.iter().enumerate() {
+        incident.entry(*a).or_default().push(s);
+        incident.entry(*b).or_default().push(s);
+    }
+
+    // Walk from every endpoint and junction, what is left over are closed loops
+    let mut used = vec![false; segments.len()];
+    let mut chains = Vec::new();
+    let fixed = |n:u32| incident.get(&n).unwrap().len() != 2;
+    let mut starts: Vec<(usize, u32)> = Vec::new();
+    for (s, (_, (a, b))) in segments.iter().enumerate() {
+        for n in [*a, *b] {
+            if fixed(n) {
+                starts.push((s, n));
+            }
+        }
+    }
+    starts.extend(segments.iter().enumerate().map(|(s, (_, (a, _)))| (s, *a)));
+    for (start, from) in starts {
+        if used[start] {
+            continue;
+        }
+        let mut chain = Vec::new();
+        let (mut s, mut n) = (start, from);
+        loop {
+            used[s] = true;
+            let ((i, j), (a, _)) = segments[s];
+            let (next, segment) = if a == n { (segments[s].1.1, (i, j)) } else { (a, (j, i)) };
+            chain.push(segment);
+            n = next;
+            if fixed(n) {
+                break;
+            }
+            match incident.get(&n).unwrap().iter().find(|t| !used[**t]) {
+                Some(t) => s = *t,
+                None => break,
+            }
+        }
+        chains.push(Chain { segments: chain, closed: n == from });
+    }
+
+    // Importance of the inner vertices of every chain, the lowest ones are dropped first
+    let mut inner = Vec::new();
+    for (c, chain) in chains.iter().enumerate() {
+        for (k, importance) in importance(chain, &position).into_iter().enumerate() {
+            if let Some(importance) = importance {
+                inner.push((importance, c, k + 1));
+            }
+        }
+    }
+    inner.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let mut removed: Vec<HashSet<usize>> = vec![HashSet::new(); chains.len()];
+    let (mut error, mut error_sum) = (0.0f32, 0.0f64);
+    for (importance, c, k) in inner.iter().take(goal).take_while(|(importance, _, _)| *importance <= max_error) {
+        removed[*c].insert(*k);
+        error = error.max(*importance);
+        error_sum += *importance as f64;
+    }
+
+    let mut new_index_list = Vec::with_capacity(index_list.len());
+    for (chain, removed) in chains.iter().zip(&removed) {
+        let mut start = chain.segments[0].0;
+        for (k, segment) in chain.segments.iter().enumerate() {
+            if removed.contains(&(k + 1)) {
+                continue;
+            }
+            new_index_list.extend_from_slice(&[start, segment.1]);
+            if let Some(next) = chain.segments.get(k + 1) {
+                start = next.0;
+            }
+        }
+    }
+    info!(target: "decimate", "{} line segments simplified into {}", index_list.len() / 2, new_index_list.len() / 2);
+    let collapses = removed.iter().map(|removed| removed.len()).sum();
+    (new_index_list, error, collapses, error_sum)
+}
+
+// Douglas–Peucker distance at which each vertex after the first one gets split off, None for the vertices
+// that are kept anyway. A vertex never outranks the vertex that split its span, so any threshold gives a
+// Douglas–Peucker simplification.
+fn importance(chain:&Chain, position:&dyn Fn(u32) -> Vector3<f32>) -> Vec<Option<f32>> {
+    let mut points = vec![position(chain.segments[0].0)];
+    points.extend(chain.segments.iter().map(|s| position(s.1)));
+    let last = points.len() - 1;
+    let mut importance = vec![Some(0.0); last];
+    // The last vertex of the chain is an endpoint, or the start again for loops
+    importance[last - 1] = None;
+    let mut spans = vec![(0, last, f32::MAX)];
+    if chain.closed {
+        // Loops are anchored on their start, the vertex furthest from it and the vertex furthest from both
+        let far = (1..last).max_by(|a, b| (points[*a] - points[0]).norm().total_cmp(&(points[*b] - points[0]).norm()));
+        spans = match far {
+            Some(far) => {
+                importance[far - 1] = None;
+                let third = (1..last).filter(|k| *k != far)
+                    .max_by(|a, b| distance(&points, *a, 0, far).total_cmp(&distance(&points, *b, 0, far)));
+                match third {
+                    Some(third) => {
+                        importance[third - 1] = None;
+                        let mut anchors = [0, far, third, last];
+                        anchors.sort();
+                        anchors.windows(2).map(|w| (w[0], w[1], f32::MAX)).collect()
+                    }
+                    None => vec![(0, far, f32::MAX), (far, last, f32::MAX)],
+                }
+            }
+            None => Vec::new(),
+        };
+    }
+    while let Some((a, b, parent)) = spans.pop() {
+        if b - a < 2 {
+            continue;
+        }
+        let (k, d) = (a + 1..b).map(|k| (k, distance(&points, k, a, b))).max_by(|x, y| x.1.total_cmp(&y.1)).unwrap();
+        let d = d.min(parent);
+        importance[k - 1] = Some(d);
+        spans.push((a, k, d));
+        spans.push((k, b, d));
+    }
+    importance
+}
+
+// Distance from points[k] to the segment between points[a] and points[b]
+fn distance(points:&[Vector3<f32>], k:usize, a:usize, b:usize) -> f32 {
+    let ab = points[b] - points[a];
+    let ak = points[k] - points[a];
+    let length = ab.norm_squared();
+    let t = if length > 0.0 { (ak.dot(&ab) / length).clamp(0.0, 1.0) } else { 0.0 };
+    (ak - ab * t).norm()
+}
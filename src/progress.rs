@@ -0,0 +1,89 @@
+// Progress of the edge collapses and their cooperative cancellation. Every collapse loop adds the triangles it is
+// to remove to a shared total and reports the ones it removes, so that primitives and chunks decimated side by side
+// make up a single progress. A cancelled loop stops at its next collapse and keeps the collapses done so far.
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Where the decimation stands, in triangles, with the largest error reached in world units
+#[derive(Debug, Clone, Copy)]
+pub struct Update {
+    pub done: usize,
+    pub remaining: usize,
+    pub error: f32,
+}
+
+/// Shared flag stopping the collapse loops, which can be handed to another thread or a signal handler
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Callback of the collapse loops, with the cancellation token they check
+pub struct Progress {
+    report: Box<dyn Fn(Update) + Send + Sync>,
+    cancel: Cancel,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    // Bits of a positive f32, which order like the floats
+    error: AtomicU32,
+}
+
+impl Progress {
+    pub fn new(report:impl Fn(Update) + Send + Sync + 'static, cancel:Cancel) -> Self {
+        Progress {
+            report: Box::new(report),
+            cancel,
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            error: AtomicU32::new(0),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// A collapse loop is to remove this many triangles
+    pub fn start(&self, triangles:usize) {
+        self.total.fetch_add(triangles, Ordering::Relaxed);
+        self.send();
+    }
+
+    /// A collapse removed this many triangles and reached this error
+    pub fn step(&self, triangles:usize, error:f32) {
+        self.done.fetch_add(triangles, Ordering::Relaxed);
+        self.error.fetch_max(error.max(0.0).to_bits(), Ordering::Relaxed);
+        self.send();
+    }
+
+    /// A collapse loop stopped with this many triangles it did not remove
+    pub fn finish(&self, left:usize) {
+        self.total.fetch_sub(left, Ordering::Relaxed);
+        self.send();
+    }
+
+    fn send(&self) {
+        let (done, total) = (self.done.load(Ordering::Relaxed), self.total.load(Ordering::Relaxed));
+        (self.report)(Update {
+            done,
+            remaining: total.saturating_sub(done),
+            error: f32::from_bits(self.error.load(Ordering::Relaxed)),
+        });
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Progress").field("cancel", &self.cancel).field("done", &self.done)
+            .field("total", &self.total).finish()
+    }
+}
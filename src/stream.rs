@@ -0,0 +1,558 @@
+// Streaming decimation of GLB files too large to be expanded in memory. The file is memory mapped, the triangles
+// of every primitive are decimated in spatial chunks whose shared vertices are locked, and the new accessors are
+// written chunk after chunk into temporary files that make up the binary chunk of the output.
+use log::{info, warn};
+use memmap2::Mmap;
+use nalgebra::Vector3;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{byte_component, byte_f32, check_accessors, component_size, decimate_primitive, mesh_instances, mesh_settings,
+            remove_unused_views, report_entry, type_size, write_report, Method, Options, Settings, Stats};
+
+// Attribute locking the vertices shared with other chunks, read by decimate_primitive and never written out
+pub const BORDER: &str = "_DECIMATE_BORDER";
+
+// Chunks along each axis of the bounds at most, so that a chunk number fits in 16 bits
+const MAX_GRID: usize = 40;
+const NONE: u16 = u16::MAX;
+const SHARED: u16 = u16::MAX - 1;
+
+// Temporary files made so far, so that files decimated at the same time in one process do not share them
+static TEMPORARY: AtomicUsize = AtomicUsize::new(0);
+
+fn unsupported(message:String) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message)
+}
+
+// Where the elements of an accessor are in the binary chunk
+struct Layout {
+    offset: usize,
+    stride: usize,
+    size: usize,
+    component_type: u32,
+}
+
+impl Layout {
+    fn new(json:&Value, accessor:usize) -> io::Result<Self> {
+        let a = &json["accessors"][accessor];
+        let view = match a["bufferView"].as_u64() {
+            Some(view) if !a["sparse"].is_object() => &json["bufferViews"][view as usize],
+            _ => return Err(unsupported(format!("Accessor {} is sparse or has no bufferView, which streaming does not support",
+                                                accessor))),
+        };
+        let component_type = a["componentType"].as_u64().unwrap() as u32;
+        let size = component_size(component_type) * type_size(a["type"].as_str().unwrap());
+        Ok(Layout {
+            offset: (view["byteOffset"].as_u64().unwrap_or(0) + a["byteOffset"].as_u64().unwrap_or(0)) as usize,
+            stride: view["byteStride"].as_u64().map_or(size, |stride| stride as usize),
+            size,
+            component_type,
+        })
+    }
+
+    fn element<'a>(&self, binary:&'a [u8], i:usize) -> &'a [u8] {
+        let at = self.offset + i * self.stride;
+        &binary[at..at + self.size]
+    }
+
+    fn index(&self, binary:&[u8], i:usize) -> u32 {
+        byte_component(binary, self.offset + i * self.stride, self.component_type) as u32
+    }
+}
+
+// An output accessor gathered chunk after chunk in a temporary file, with its bounds. The file is removed with the
+// stream, once it is copied into the output or when the decimation fails
+struct Stream {
+    accessor: Value,
+    target: u32,
+    path: PathBuf,
+    file: BufWriter<File>,
+    count: usize,
+    min: Vec<f64>,
+    max: Vec<f64>,
+}
+
+impl Stream {
+    fn new(template:&Value, target:u32) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("decimation_gltf_{}_{}.bin", std::process::id(),
+                                                     TEMPORARY.fetch_add(1, Ordering::Relaxed)));
+        let mut accessor = json!({});
+        for key in ["componentType", "normalized", "type"] {
+            if !template[key].is_null() {
+                accessor[key] = template[key].clone();
+            }
+        }
+        Ok(Stream { accessor, target, file: BufWriter::new(File::create(&path)?), path, count: 0, min: Vec::new(), max: Vec::new() })
+    }
+
+    fn push(&mut self, element:&[u8]) -> io::Result<()> {
+        let component_type = self.accessor["componentType"].as_u64().unwrap() as u32;
+        let size = component_size(component_type);
+        for (c, x) in (0..element.len() / size).map(|c| (c, byte_component(element, c * size, component_type))) {
+            if self.min.len() <= c {
+                self.min.push(x);
+                self.max.push(x);
+            }
+            self.min[c] = self.min[c].min(x);
+            self.max[c] = self.max[c].max(x);
+        }
+        self.count += 1;
+        self.file.write_all(element)
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Decimate every triangle primitive of the file chunk by chunk into options.output
+pub fn decimate(path:&Path, options:&Options, defaults:Settings) -> io::Result<()> {
+    let file = File::open(path)?;
+    // Safety: the input is only read, and is not expected to change while it is decimated
+    let mmap = unsafe { Mmap::map(&file)? };
+    let json_length = u32::from_le_bytes(mmap[12..16].try_into().unwrap()) as usize;
+    let mut json: Value = serde_json::from_slice(&mmap[20..20 + json_length]).expect("Failed to parse JSON");
+    let bin_start = 20 + json_length + 8;
+    let bin_length = if mmap.len() > bin_start {
+        u32::from_le_bytes(mmap[bin_start - 8..bin_start - 4].try_into().unwrap()) as usize
+    } else {
+        0
+    };
+    let binary = &mmap[bin_start.min(mmap.len())..(bin_start + bin_length).min(mmap.len())];
+    for extension in json["extensionsUsed"].as_array().into_iter().flatten() {
+        if *extension == "EXT_meshopt_compression" || *extension == "KHR_draco_mesh_compression" {
+            return Err(unsupported(format!("{} is not supported when streaming, decompress the file first", extension)));
+        }
+    }
+    check_accessors(&json, binary)?;
+    // Compression and quantization need the whole primitive, the chunks are written as they come. The command line
+    // does not take them together with --stream
+    if options.quantize || options.meshopt || options.draco.is_some() || options.sparse {
+        warn!(target: "write", "Quantization, compression and sparse targets are not written when streaming");
+    }
+    let options = &Options { quantize: false, meshopt: false, draco: None, sparse: false, ..options.clone() };
+
+    let mut streams: Vec<Stream> = Vec::new();
+    // Output accessors of every decimated primitive, in the layout of its "attributes", "indices" and "targets"
+    let mut outputs: Vec<((usize, usize), Value)> = Vec::new();
+    let mut report = Vec::new();
+    let mesh_num = json["meshes"].as_array().map_or(0, |meshes| meshes.len());
+    for mesh in 0..mesh_num {
+        let settings = mesh_settings(&json, mesh, defaults);
+        if settings.method == Method::Budget {
+            return Err(unsupported(String::from("The budget method needs the whole scene and is not supported when streaming")));
+        }
+        let primitive_num = json["meshes"][mesh]["primitives"].as_array().map_or(0, |primitives| primitives.len());
+        for primitive in 0..primitive_num {
+            let prim = json["meshes"][mesh]["primitives"][primitive].clone();
+            if settings.skip || prim["mode"].as_u64().unwrap_or(4) != 4 {
+                info!(target: "decimate", "Mesh {} primitive {} is left as it is", mesh, primitive);
+                continue;
+            }
+            let indices = prim["indices"].as_u64().map(|accessor| Layout::new(&json, accessor as usize)).transpose()?;
+            let position = prim["attributes"]["POSITION"].as_u64().unwrap() as usize;
+            let positions = Layout::new(&json, position)?;
+            if positions.component_type != 5126 {
+                return Err(unsupported(String::from("Quantized positions are not supported when streaming")));
+            }
+            let vertex_num = json["accessors"][position]["count"].as_u64().unwrap() as usize;
+            let index = |i:usize| indices.as_ref().map_or(i as u32, |layout| layout.index(binary, i));
+            let point = |v:u32| {
+                let element = positions.element(binary, v as usize);
+                Vector3::new(byte_f32(element, 0), byte_f32(element, 4), byte_f32(element, 8))
+            };
+            let tri_num = match &prim["indices"] {
+                Value::Number(accessor) => json["accessors"][accessor.as_u64().unwrap() as usize]["count"].as_u64().unwrap() as usize,
+                _ => vertex_num,
+            } / 3;
+
+            // Chunks on a grid over the bounds, scans being surfaces most chunks of a cube of them stay empty
+            let (mut min, mut max) = (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN));
+            for v in 0..vertex_num {
+                min = min.inf(&point(v as u32));
+                max = max.sup(&point(v as u32));
+            }
+            let grid = ((tri_num as f64 / options.chunk_triangles.max(1) as f64).sqrt().ceil() as usize).clamp(1, MAX_GRID);
+            let size = (max - min).map(|x| x.max(f32::MIN_POSITIVE)) / grid as f32;
+            let cell = |p:Vector3<f32>| {
+                let c = (p - min).component_div(&size);
+                let c = c.map(|x| (x.max(0.0) as usize).min(grid - 1));
+                ((c.x * grid + c.y) * grid + c.z) as u16
+            };
+            // Chunk of every triangle by its centroid, and vertices used by several chunks
+            let mut vertex_chunk = vec![NONE; vertex_num];
+            let mut count = vec![0usize; grid * grid * grid + 1];
+            let mut tri_chunk = Vec::with_capacity(tri_num);
+            for t in 0..tri_num {
+                let corners = [index(t * 3), index(t * 3 + 1), index(t * 3 + 2)];
+                let c = cell(corners.iter().map(|v| point(*v)).sum::<Vector3<f32>>() / 3.0);
+                for v in corners {
+                    let chunk = &mut vertex_chunk[v as usize];
+                    *chunk = if *chunk == NONE || *chunk == c { c } else { SHARED };
+                }
+                tri_chunk.push(c);
+                count[c as usize + 1] += 1;
+            }
+            for c in 1..count.len() {
+                count[c] += count[c - 1];
+            }
+            let mut order = vec![0u32; tri_num];
+            let mut next = count.clone();
+            for (t, c) in tri_chunk.into_iter().enumerate() {
+                order[next[c as usize]] = t as u32;
+                next[c as usize] += 1;
+            }
+            drop(next);
+
+            let mut keys: Vec<(String, usize)> = Vec::new();
+            let mut output = json!({"attributes": {}});
+            let (mut before, mut after, mut error, mut offset) = (0, 0, 0.0f32, 0u32);
+            let mut stats = Stats::default();
+            // Each chunk gets its share of what is left of the target, the smaller chunks first: their locked borders
+            // are the likeliest to keep them above their share, which the larger ones then make up for
+            let (mut limit_left, mut triangles_left) = (settings.limit, tri_num);
+            let mut chunks: Vec<&[usize]> = count.windows(2).filter(|w| w[1] > w[0]).collect();
+            chunks.sort_by_key(|chunk| chunk[1] - chunk[0]);
+            for chunk in chunks {
+                let triangles = &order[chunk[0]..chunk[1]];
+                let (mut mini, mut mini_binary) = chunk_gltf(&json, binary, (mesh, primitive), triangles, &index,
+                                                             &vertex_chunk)?;
+                let mut chunk_settings = settings;
+                if matches!(chunk_settings.method, Method::Max | Method::ClusterCount) {
+                    chunk_settings.limit = (limit_left.max(0.0) * triangles.len() as f64 / triangles_left as f64).round();
+                }
+                let (b, a, e, _, s) = decimate_primitive(&mut mini, &mut mini_binary, (mesh, 0), &chunk_settings, options,
+                                                          &mut HashMap::new())?;
+                (before, after, error) = (before + b, after + a, error.max(e));
+                (limit_left, triangles_left) = (limit_left - a as f64, triangles_left - triangles.len());
+                stats.add(&s);
+                offset += append_chunk(&mini, &mini_binary, mesh, offset, &mut keys, &mut streams, &mut output)?;
+            }
+            info!(target: "decimate", "Mesh {} primitive {}: {} triangles decimated into {} in {} chunks", mesh, primitive, before, after,
+                        count.windows(2).filter(|w| w[1] > w[0]).count());
+            // The vertices shared between chunks are written once for each of them
+            stats.vertices = (json["accessors"][position]["count"].as_u64().unwrap_or(0) as usize, offset as usize);
+            outputs.push(((mesh, primitive), output));
+            report.push(((mesh, primitive), before, after, error, stats));
+        }
+    }
+
+    write_streams(json_without(&mut json, &outputs), binary, streams, &options.output)?;
+    info!(target: "decimate", "Mesh  Primitive  Before   After  Error");
+    let mut entries = Vec::new();
+    let mut scene = (0, 0);
+    for ((mesh, primitive), before, after, error, stats) in report {
+        info!(target: "decimate", "{:>4}  {:>9}  {:>6}  {:>6}  {}", mesh, primitive, before, after, error);
+        entries.push(report_entry((mesh, primitive), 4, before, after, error, None, &stats));
+        let instances = mesh_instances(&json, mesh as u64).len();
+        scene = (scene.0 + before * instances, scene.1 + after * instances);
+    }
+    if let Some(report) = &options.report {
+        write_report(report, path, options, scene, entries);
+    }
+    Ok(())
+}
+
+// Small glTF holding the triangles of a chunk as the only primitive of the mesh, with the vertices shared with
+// other chunks marked in the BORDER attribute
+fn chunk_gltf(json:&Value, binary:&[u8], (mesh, primitive):(usize, usize), triangles:&[u32],
+                index:&dyn Fn(usize) -> u32, vertex_chunk:&[u16]) -> io::Result<(Value, Vec<u8>)> {
+    let mut local: HashMap<u32, u32> = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+    for t in triangles {
+        for k in 0..3 {
+            let v = index(*t as usize * 3 + k);
+            let i = *local.entry(v).or_insert_with(|| {
+                vertices.push(v);
+                vertices.len() as u32 - 1
+            });
+            indices.push(i);
+        }
+    }
+    let mut mini = json.clone();
+    for key in ["images", "animations"] {
+        mini.as_object_mut().unwrap().remove(key);
+    }
+    mini["accessors"] = json!([]);
+    mini["bufferViews"] = json!([]);
+    let mut mini_binary = Vec::new();
+    let mut add = |mini:&mut Value, accessor:Value, data:Vec<u8>| {
+        let view = mini["bufferViews"].as_array().unwrap().len();
+        mini["bufferViews"].as_array_mut().unwrap().push(json!({"buffer": 0, "byteOffset": mini_binary.len(),
+                                                                "byteLength": data.len()}));
+        mini_binary.extend_from_slice(&data);
+        while mini_binary.len() % 4 != 0 {
+            mini_binary.push(0);
+        }
+        let mut accessor = accessor;
+        accessor["bufferView"] = json!(view);
+        mini["accessors"].as_array_mut().unwrap().push(accessor);
+        mini["accessors"].as_array().unwrap().len() - 1
+    };
+    let copy = |mini:&mut Value, add:&mut dyn FnMut(&mut Value, Value, Vec<u8>) -> usize, accessor:usize| -> io::Result<Value> {
+        let layout = Layout::new(json, accessor)?;
+        let mut data = Vec::with_capacity(vertices.len() * layout.size);
+        for v in &vertices {
+            data.extend_from_slice(layout.element(binary, *v as usize));
+        }
+        let mut a = json["accessors"][accessor].clone();
+        for key in ["byteOffset", "min", "max", "sparse"] {
+            a.as_object_mut().unwrap().remove(key);
+        }
+        a["count"] = json!(vertices.len());
+        Ok(json!(add(mini, a, data)))
+    };
+    let mut prim = json["meshes"][mesh]["primitives"][primitive].clone();
+    for (_, accessor) in prim["attributes"].as_object_mut().unwrap() {
+        *accessor = copy(&mut mini, &mut add, accessor.as_u64().unwrap() as usize)?;
+    }
+    for target in prim["targets"].as_array_mut().into_iter().flatten() {
+        for (_, accessor) in target.as_object_mut().unwrap() {
+            *accessor = copy(&mut mini, &mut add, accessor.as_u64().unwrap() as usize)?;
+        }
+    }
+    let border: Vec<u8> = vertices.iter()
+        .flat_map(|v| if vertex_chunk[*v as usize] == SHARED { 1.0f32 } else { 0.0 }.to_le_bytes()).collect();
+    prim["attributes"][BORDER] = json!(add(&mut mini, json!({"componentType": 5126, "count": vertices.len(),
+                                                              "type": "SCALAR"}), border));
+    prim["indices"] = json!(add(&mut mini, json!({"componentType": 5125, "count": indices.len(), "type": "SCALAR"}),
+                                indices.iter().flat_map(|i| i.to_le_bytes()).collect()));
+    prim["mode"] = json!(4);
+    mini["meshes"][mesh]["primitives"] = json!([prim]);
+    mini["buffers"] = json!([{"byteLength": mini_binary.len()}]);
+    Ok((mini, mini_binary))
+}
+
+// Append the decimated chunk to the streams of its primitive, opened on the first chunk, and return its vertex count
+fn append_chunk(mini:&Value, mini_binary:&[u8], mesh:usize, offset:u32, keys:&mut Vec<(String, usize)>,
+                streams:&mut Vec<Stream>, output:&mut Value) -> io::Result<u32> {
+    let prim = &mini["meshes"][mesh]["primitives"][0];
+    let mut accessors: Vec<(String, usize)> = prim["attributes"].as_object().unwrap().iter()
+        .filter(|(name, _)| *name != BORDER)
+        .map(|(name, accessor)| (name.clone(), accessor.as_u64().unwrap() as usize)).collect();
+    for (t, target) in prim["targets"].as_array().into_iter().flatten().enumerate() {
+        accessors.extend(target.as_object().unwrap().iter()
+            .map(|(name, accessor)| (format!("{}/{}", t, name), accessor.as_u64().unwrap() as usize)));
+    }
+    let vertex_num = mini["accessors"][prim["attributes"]["POSITION"].as_u64().unwrap() as usize]["count"].as_u64().unwrap();
+    for (name, accessor) in &accessors {
+        let stream = match keys.iter().find(|(key, _)| key == name) {
+            Some((_, stream)) => *stream,
+            None => {
+                streams.push(Stream::new(&mini["accessors"][*accessor], 34962)?);
+                keys.push((name.clone(), streams.len() - 1));
+                match name.split_once('/') {
+                    Some((t, name)) => {
+                        let t = t.parse::<usize>().unwrap();
+                        if output["targets"].is_null() {
+                            output["targets"] = json!([]);
+                        }
+                        let targets = output["targets"].as_array_mut().unwrap();
+                        targets.resize(targets.len().max(t + 1), json!({}));
+                        targets[t][name] = json!(streams.len() - 1);
+                    }
+                    None => output["attributes"][name] = json!(streams.len() - 1),
+                }
+                streams.len() - 1
+            }
+        };
+        let layout = Layout::new(mini, *accessor)?;
+        if layout.component_type as u64 != streams[stream].accessor["componentType"].as_u64().unwrap() {
+            return Err(unsupported(format!("{} changed component type between two chunks, which streaming does not support",
+                                           name)));
+        }
+        for i in 0..vertex_num as usize {
+            streams[stream].push(layout.element(mini_binary, i))?;
+        }
+    }
+    let indices = Layout::new(mini, prim["indices"].as_u64().unwrap() as usize)?;
+    let stream = match keys.iter().find(|(key, _)| key == "indices") {
+        Some((_, stream)) => *stream,
+        None => {
+            streams.push(Stream::new(&json!({"componentType": 5125, "type": "SCALAR"}), 34963)?);
+            keys.push((String::from("indices"), streams.len() - 1));
+            output["indices"] = json!(streams.len() - 1);
+            streams.len() - 1
+        }
+    };
+    let index_num = mini["accessors"][prim["indices"].as_u64().unwrap() as usize]["count"].as_u64().unwrap() as usize;
+    for i in 0..index_num {
+        streams[stream].push(&(indices.index(mini_binary, i) + offset).to_le_bytes())?;
+    }
+    Ok(vertex_num as u32)
+}
+
+// Point the decimated primitives at their streams, numbered after the accessors left, and drop the accessors and the
+// data nothing else uses
+fn json_without(json:&mut Value, outputs:&[((usize, usize), Value)]) -> Value {
+    let decimated: HashSet<(usize, usize)> = outputs.iter().map(|(at, _)| *at).collect();
+    let mut kept: HashSet<u64> = HashSet::new();
+    references(json, &decimated, &mut |accessor| { kept.insert(accessor.as_u64().unwrap()); });
+    let mut dropped: HashSet<u64> = HashSet::new();
+    for (at, _) in outputs {
+        dropped.extend(accessors_of(&json["meshes"][at.0]["primitives"][at.1]).into_iter()
+            .filter(|accessor| !kept.contains(accessor)));
+    }
+    let accessor_num = json["accessors"].as_array().map_or(0, |accessors| accessors.len());
+    let mut new_accessor = Vec::with_capacity(accessor_num);
+    let mut k = 0;
+    for a in 0..accessor_num as u64 {
+        new_accessor.push(k);
+        if !dropped.contains(&a) {
+            k += 1;
+        }
+    }
+    references(json, &decimated, &mut |accessor| *accessor = json!(new_accessor[accessor.as_u64().unwrap() as usize]));
+    if let Some(accessors) = json.get_mut("accessors").and_then(|accessors| accessors.as_array_mut()) {
+        let mut a = 0;
+        accessors.retain(|_| {
+            a += 1;
+            !dropped.contains(&(a - 1))
+        });
+    }
+
+    let first = k;
+    for (at, output) in outputs {
+        let prim = &mut json["meshes"][at.0]["primitives"][at.1];
+        let shift = |value:&Value| json!(first + value.as_u64().unwrap() as usize);
+        prim["attributes"] = Value::Object(output["attributes"].as_object().unwrap().iter()
+            .map(|(name, stream)| (name.clone(), shift(stream))).collect());
+        prim["indices"] = shift(&output["indices"]);
+        if let Some(targets) = output["targets"].as_array() {
+            prim["targets"] = Value::Array(targets.iter().map(|target| Value::Object(target.as_object().unwrap().iter()
+                .map(|(name, stream)| (name.clone(), shift(stream))).collect())).collect());
+        }
+        prim["mode"] = json!(4);
+    }
+    remove_unused_views(json, &mut HashMap::new());
+    json.clone()
+}
+
+fn accessors_of(prim:&Value) -> Vec<u64> {
+    let mut accessors: Vec<u64> = prim["attributes"].as_object().into_iter().flatten().filter_map(|(_, a)| a.as_u64()).collect();
+    accessors.extend(prim["indices"].as_u64());
+    for target in prim["targets"].as_array().into_iter().flatten() {
+        accessors.extend(target.as_object().into_iter().flatten().filter_map(|(_, a)| a.as_u64()));
+    }
+    accessors
+}
+
+// Visit the accessors of the primitives that are not decimated, of the skins, of the animations and of the
+// instanced nodes
+fn references(json:&mut Value, decimated:&HashSet<(usize, usize)>, visit:&mut dyn FnMut(&mut Value)) {
+    fn values(map:Option<&mut Value>, visit:&mut dyn FnMut(&mut Value)) {
+        for (_, accessor) in map.and_then(|map| map.as_object_mut()).into_iter().flatten() {
+            if accessor.is_u64() {
+                visit(accessor);
+            }
+        }
+    }
+    fn keys(value:&mut Value, visit:&mut dyn FnMut(&mut Value)) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map.iter_mut() {
+                    match key.as_str() {
+                        "input" | "output" | "inverseBindMatrices" if value.is_u64() => visit(value),
+                        _ => keys(value, visit),
+                    }
+                }
+            }
+            Value::Array(list) => list.iter_mut().for_each(|value| keys(value, visit)),
+            _ => {}
+        }
+    }
+    for (m, mesh) in json.get_mut("meshes").and_then(|meshes| meshes.as_array_mut()).into_iter().flatten().enumerate() {
+        for (p, prim) in mesh["primitives"].as_array_mut().into_iter().flatten().enumerate() {
+            if decimated.contains(&(m, p)) {
+                continue;
+            }
+            values(prim.get_mut("attributes"), visit);
+            if let Some(indices) = prim.get_mut("indices").filter(|indices| indices.is_u64()) {
+                visit(indices);
+            }
+            for target in prim.get_mut("targets").and_then(|targets| targets.as_array_mut()).into_iter().flatten() {
+                values(Some(target), visit);
+            }
+        }
+    }
+    // Skins and animations only point at accessors through these keys
+    for key in ["skins", "animations"] {
+        for value in json.get_mut(key).and_then(|list| list.as_array_mut()).into_iter().flatten() {
+            keys(value, visit);
+        }
+    }
+    for node in json.get_mut("nodes").and_then(|nodes| nodes.as_array_mut()).into_iter().flatten() {
+        values(node.pointer_mut("/extensions/EXT_mesh_gpu_instancing/attributes"), visit);
+    }
+}
+
+// Write the output: the views still used are copied from the mapped input, then every stream follows as a view
+fn write_streams(mut json:Value, binary:&[u8], mut streams:Vec<Stream>, output:&str) -> io::Result<()> {
+    let mut length = 0;
+    let mut copies = Vec::new();
+    for view in json["bufferViews"].as_array_mut().into_iter().flatten() {
+        let (off, len) = (view["byteOffset"].as_u64().unwrap_or(0) as usize, view["byteLength"].as_u64().unwrap() as usize);
+        copies.push((off, len));
+        view["byteOffset"] = json!(length);
+        length += len.div_ceil(4) * 4;
+    }
+    let mut files = Vec::new();
+    for stream in &mut streams {
+        stream.file.flush()?;
+        let len = fs::metadata(&stream.path)?.len() as usize;
+        let view = json["bufferViews"].as_array().map_or(0, |views| views.len());
+        if json["bufferViews"].is_null() {
+            json["bufferViews"] = json!([]);
+        }
+        json["bufferViews"].as_array_mut().unwrap().push(json!({"buffer": 0, "byteOffset": length, "byteLength": len,
+                                                                 "target": stream.target}));
+        let mut accessor = stream.accessor.clone();
+        accessor["bufferView"] = json!(view);
+        accessor["count"] = json!(stream.count);
+        accessor["min"] = json!(stream.min);
+        accessor["max"] = json!(stream.max);
+        json["accessors"].as_array_mut().unwrap().push(accessor);
+        length += len.div_ceil(4) * 4;
+        files.push((&stream.path, len));
+    }
+    json["buffers"] = json!([{"byteLength": length}]);
+
+    let mut json_data = serde_json::to_vec(&json).unwrap();
+    while !json_data.len().is_multiple_of(4) {
+        json_data.push(b' ');
+    }
+    // The chunk and file lengths of a glTF binary file are 32-bit
+    let u32_length = |length:usize| u32::try_from(length).map_err(|_| io::Error::new(io::ErrorKind::InvalidData,
+        format!("The output of {} bytes is too large for a glb file", 12 + 8 + json_data.len() + 8 + length)));
+    let (file_length, json_length, bin_length) =
+        (u32_length(12 + 8 + json_data.len() + 8 + length)?, u32_length(json_data.len())?, u32_length(length)?);
+    let mut file = BufWriter::new(File::create(output)?);
+    file.write_all(&0x46546C67u32.to_le_bytes())?;
+    file.write_all(&2u32.to_le_bytes())?;
+    file.write_all(&file_length.to_le_bytes())?;
+    file.write_all(&json_length.to_le_bytes())?;
+    file.write_all(b"JSON")?;
+    file.write_all(&json_data)?;
+    file.write_all(&bin_length.to_le_bytes())?;
+    file.write_all(b"BIN\x00")?;
+    let pad = |file:&mut BufWriter<File>, len:usize| file.write_all(&vec![0; len.div_ceil(4) * 4 - len]);
+    for (off, len) in copies {
+        file.write_all(&binary[off..off + len])?;
+        pad(&mut file, len)?;
+    }
+    for (path, len) in files {
+        io::copy(&mut File::open(path)?, &mut file)?;
+        pad(&mut file, len)?;
+    }
+    file.flush()?;
+    info!(target: "write", "{} written, {} bytes of binary data", output, length);
+    Ok(())
+}
@@ -0,0 +1,85 @@
+// Mirror symmetry of a mesh across an axis-aligned plane through the center of its bounds
+use log::{info, warn};
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+// Share of the vertices that must have a mirror for Auto to accept a plane
+const AUTO_MIN_SHARE: f32 = 0.9;
+
+// Tolerance of the mirror lookup, relative to the diagonal of the bounds
+const TOLERANCE: f32 = 1e-4;
+
+/// Plane of a mirror symmetry, through the center of the bounds of the mesh
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Symmetry {
+    /// Across the x axis
+    X,
+    /// Across the y axis
+    Y,
+    /// Across the z axis
+    Z,
+    /// Across the axis with the most mirrored vertices, if enough of them are
+    Auto,
+}
+
+// Mirror of every vertex that has one across the plane of symmetry. Vertices on the plane are their own mirror.
+pub fn pairs(position_list:&HashMap<u32, Vector3<f32>>, symmetry:Symmetry) -> HashMap<u32, u32> {
+    let (mirror, axis) = match symmetry {
+        Symmetry::X => (mirror(position_list, 0), 0),
+        Symmetry::Y => (mirror(position_list, 1), 1),
+        Symmetry::Z => (mirror(position_list, 2), 2),
+        Symmetry::Auto => {
+            let (mirror, axis) = (0..3).map(|axis| (mirror(position_list, axis), axis))
+                .max_by_key(|(mirror, axis)| (mirror.len(), std::cmp::Reverse(*axis))).unwrap();
+            if (mirror.len() as f32) < position_list.len() as f32 * AUTO_MIN_SHARE {
+                warn!(target: "decimate", "No symmetry plane found, {} of {} vertices mirrored at best", mirror.len(), position_list.len());
+                return HashMap::new();
+            }
+            (mirror, axis)
+        }
+    };
+    info!(target: "decimate", "Symmetry across {}: {} of {} vertices mirrored", ["x", "y", "z"][axis], mirror.len(), position_list.len());
+    mirror
+}
+
+fn mirror(position_list:&HashMap<u32, Vector3<f32>>, axis:usize) -> HashMap<u32, u32> {
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(f32::MIN);
+    for p in position_list.values() {
+        min = min.inf(p);
+        max = max.sup(p);
+    }
+    let tolerance = ((max - min).norm() * TOLERANCE).max(f32::MIN_POSITIVE);
+    let center = (min[axis] + max[axis]) / 2.0;
+    let cell = |p:&Vector3<f32>| [(p.x / tolerance).round() as i64, (p.y / tolerance).round() as i64,
+                                    (p.z / tolerance).round() as i64];
+    let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    for (i, p) in position_list {
+        grid.entry(cell(p)).or_default().push(*i);
+    }
+    let mut mirror = HashMap::new();
+    for (i, p) in position_list {
+        let mut q = *p;
+        q[axis] = 2.0 * center - q[axis];
+        let c = cell(&q);
+        // Nearest vertex within the tolerance, the lowest index among equals so that pairs agree
+        let mut nearest: Option<(f32, u32)> = None;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    for j in grid.get(&[c[0] + dx, c[1] + dy, c[2] + dz]).into_iter().flatten() {
+                        let d = (position_list[j] - q).norm();
+                        if d <= tolerance && nearest.is_none_or(|n| (d, *j) < n) {
+                            nearest = Some((d, *j));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some((_, j)) = nearest {
+            mirror.insert(*i, j);
+        }
+    }
+    // Keep the pairs that agree both ways
+    mirror.iter().filter(|(i, j)| mirror.get(j) == Some(i)).map(|(i, j)| (*i, *j)).collect()
+}
@@ -0,0 +1,954 @@
+// Hand-built files going through the library entry point, checking what the decimated primitives reference
+use decimation_gltf::{compare, decimate, Args, Cancel, CostMetric, Method, Options, PlacementPolicy, Progress, Quadric,
+                      Symmetry};
+use clap::Parser;
+use nalgebra::{Matrix3, Vector3};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// A glTF binary file from its JSON and binary chunks
+fn glb(json:&Value, mut binary:Vec<u8>) -> Vec<u8> {
+    let mut text = serde_json::to_vec(json).unwrap();
+    while !text.len().is_multiple_of(4) {
+        text.push(b' ');
+    }
+    while !binary.len().is_multiple_of(4) {
+        binary.push(0);
+    }
+    let mut file = Vec::new();
+    file.extend_from_slice(b"glTF");
+    file.extend_from_slice(&2u32.to_le_bytes());
+    file.extend_from_slice(&(12 + 8 + text.len() as u32 + 8 + binary.len() as u32).to_le_bytes());
+    file.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    file.extend_from_slice(b"JSON");
+    file.extend_from_slice(&text);
+    file.extend_from_slice(&(binary.len() as u32).to_le_bytes());
+    file.extend_from_slice(b"BIN\0");
+    file.extend_from_slice(&binary);
+    file
+}
+
+// The JSON and binary chunks of a glTF binary file
+fn read_glb(file:&[u8]) -> (Value, Vec<u8>) {
+    let json_length = u32::from_le_bytes(file[12..16].try_into().unwrap()) as usize;
+    let json = serde_json::from_slice(&file[20..20 + json_length]).unwrap();
+    let binary = file.get(28 + json_length..).unwrap_or_default().to_vec();
+    (json, binary)
+}
+
+// Float or unsigned integer components of a tightly packed or strided accessor, zeros without a bufferView, with
+// its sparse elements in place
+fn read_accessor(json:&Value, binary:&[u8], accessor:u64) -> Vec<Vec<f64>> {
+    let accessor = &json["accessors"][accessor as usize];
+    let n = match accessor["type"].as_str().unwrap() { "SCALAR" => 1, "VEC2" => 2, "VEC3" => 3, _ => 4 };
+    let component = |component_type:u64, at:usize| match component_type {
+        5120 => binary[at] as i8 as f64,
+        5121 => binary[at] as f64,
+        5122 => i16::from_le_bytes([binary[at], binary[at + 1]]) as f64,
+        5123 => u16::from_le_bytes([binary[at], binary[at + 1]]) as f64,
+        5125 => u32::from_le_bytes(binary[at..at + 4].try_into().unwrap()) as f64,
+        _ => f32::from_le_bytes(binary[at..at + 4].try_into().unwrap()) as f64,
+    };
+    let size = |component_type:u64| match component_type { 5120 | 5121 => 1, 5122 | 5123 => 2, _ => 4 };
+    let start = |view:&Value, offset:&Value| {
+        json["bufferViews"][view.as_u64().unwrap() as usize]["byteOffset"].as_u64().unwrap_or(0) as usize +
+            offset.as_u64().unwrap_or(0) as usize
+    };
+    let component_type = accessor["componentType"].as_u64().unwrap();
+    let count = accessor["count"].as_u64().unwrap() as usize;
+    let mut values = vec![vec![0.0; n]; count];
+    if !accessor["bufferView"].is_null() {
+        let view = &json["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+        let stride = view["byteStride"].as_u64().map_or(n * size(component_type), |stride| stride as usize);
+        let first = start(&accessor["bufferView"], &accessor["byteOffset"]);
+        for (i, value) in values.iter_mut().enumerate() {
+            for (c, x) in value.iter_mut().enumerate() {
+                *x = component(component_type, first + i * stride + c * size(component_type));
+            }
+        }
+    }
+    let sparse = &accessor["sparse"];
+    if sparse.is_object() {
+        let index_type = sparse["indices"]["componentType"].as_u64().unwrap();
+        let indices = start(&sparse["indices"]["bufferView"], &sparse["indices"]["byteOffset"]);
+        let elements = start(&sparse["values"]["bufferView"], &sparse["values"]["byteOffset"]);
+        for k in 0..sparse["count"].as_u64().unwrap() as usize {
+            let i = component(index_type, indices + k * size(index_type)) as usize;
+            for (c, x) in values[i].iter_mut().enumerate() {
+                *x = component(component_type, elements + (k * n + c) * size(component_type));
+            }
+        }
+    }
+    values
+}
+
+// Decimate a file written in a directory of its own with the options and read the output back
+fn run_options(name:&str, file:Vec<u8>, mut options:Options) -> (Value, Vec<u8>) {
+    let dir = std::env::temp_dir().join(format!("decimation_gltf_primitives_{}_{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    let (input, output): (PathBuf, PathBuf) = (dir.join("input.glb"), dir.join("output.glb"));
+    fs::write(&input, file).unwrap();
+    options.output = output.to_str().unwrap().to_string();
+    let progress = Progress::new(|_| {}, Cancel::default());
+    decimate(&input, &options, &progress).unwrap();
+    let output = read_glb(&fs::read(output).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+// Decimate a file with the options of the command line
+fn run(name:&str, file:Vec<u8>, options:&[&str]) -> (Value, Vec<u8>) {
+    let mut command = vec!["decimation_gltf", "input.glb"];
+    command.extend_from_slice(options);
+    run_options(name, file, Args::parse_from(command).options())
+}
+
+// The error decimating a file with the options of the command line
+fn run_error(name:&str, file:Vec<u8>, options:&[&str]) -> std::io::Error {
+    let dir = std::env::temp_dir().join(format!("decimation_gltf_primitives_{}_{}", std::process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("input.glb"), file).unwrap();
+    let mut command = vec!["decimation_gltf", "input.glb"];
+    command.extend_from_slice(options);
+    let mut options = Args::parse_from(command).options();
+    options.output = dir.join("output.glb").to_str().unwrap().to_string();
+    let progress = Progress::new(|_| {}, Cancel::default());
+    let error = decimate(&dir.join("input.glb"), &options, &progress).unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+    error
+}
+
+// A file of a single mesh in the scene, with the lists of vectors as the first accessors and the lists of indices
+// as the next ones
+fn mesh_file(vectors:&[Vec<[f32; 3]>], indices:&[Vec<u16>], mesh:Value) -> Vec<u8> {
+    let mut binary = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    for list in vectors {
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in list {
+            for c in 0..3 {
+                (min[c], max[c]) = (min[c].min(p[c]), max[c].max(p[c]));
+            }
+        }
+        views.push(json!({"buffer": 0, "byteOffset": binary.len(), "byteLength": list.len() * 12}));
+        accessors.push(json!({"bufferView": views.len() - 1, "componentType": 5126, "count": list.len(), "type": "VEC3",
+                              "min": min, "max": max}));
+        binary.extend(list.iter().flatten().flat_map(|c| c.to_le_bytes()));
+    }
+    for list in indices {
+        views.push(json!({"buffer": 0, "byteOffset": binary.len(), "byteLength": list.len() * 2}));
+        accessors.push(json!({"bufferView": views.len() - 1, "componentType": 5123, "count": list.len(), "type": "SCALAR"}));
+        binary.extend(list.iter().flat_map(|i| i.to_le_bytes()));
+        while !binary.len().is_multiple_of(4) {
+            binary.push(0);
+        }
+    }
+    let json = json!({
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{"mesh": 0}],
+        "meshes": [mesh],
+        "accessors": accessors,
+        "bufferViews": views,
+        "buffers": [{"byteLength": binary.len()}],
+    });
+    glb(&json, binary)
+}
+
+// A bumpy n by n grid over the unit square
+fn grid(n:u16) -> Vec<[f32; 3]> {
+    (0..n * n).map(|i| {
+        let (x, y) = ((i % n) as f32 / (n - 1) as f32, (i / n) as f32 / (n - 1) as f32);
+        [x, y, 0.1 * (x * 7.0).sin() * (y * 5.0).cos()]
+    }).collect()
+}
+
+#[test]
+fn primitives_sharing_an_accessor_keep_their_own_vertices() {
+    // A left and a right primitive over the positions of the whole grid
+    let n = 9;
+    let mut halves = vec![Vec::new(), Vec::new()];
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            halves[(2 * x >= n - 1) as usize].extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let file = mesh_file(&[grid(n)], &halves, json!({"primitives": [
+        {"attributes": {"POSITION": 0}, "indices": 1},
+        {"attributes": {"POSITION": 0}, "indices": 2},
+    ]}));
+    let (json, binary) = run("shared", file, &["percent", "0.5"]);
+    let primitives = json["meshes"][0]["primitives"].as_array().unwrap();
+    assert_ne!(primitives[0]["attributes"]["POSITION"], primitives[1]["attributes"]["POSITION"]);
+    // Midpoints of the edges of a half stay in that half
+    for (side, primitive) in primitives.iter().enumerate() {
+        let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+        let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+        assert!(!indices.is_empty() && indices.len() < 64 * 3, "primitive {} has {} indices", side, indices.len());
+        for i in indices {
+            let x = positions[i[0] as usize][0];
+            assert!(if side == 0 { x <= 0.5 + 1e-6 } else { x >= 0.5 - 1e-6 }, "primitive {} uses x = {}", side, x);
+        }
+    }
+}
+
+#[test]
+fn strips_sharing_their_indices_are_both_unrolled() {
+    // Two strips over the bottom row of the grid, with the same indices
+    let strip: Vec<u16> = vec![0, 4, 1, 5, 2, 6, 3, 7];
+    let file = mesh_file(&[grid(4)], &[strip], json!({"primitives": [
+        {"attributes": {"POSITION": 0}, "indices": 1, "mode": 5},
+        {"attributes": {"POSITION": 0}, "indices": 1, "mode": 5},
+    ]}));
+    let (json, binary) = run("strips", file, &["percent", "1"]);
+    for primitive in json["meshes"][0]["primitives"].as_array().unwrap() {
+        assert!(primitive["mode"].is_null());
+        assert_eq!(read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap()).len(), 6 * 3);
+    }
+}
+
+#[test]
+fn a_loop_of_two_vertices_is_a_single_segment() {
+    // Left as it is, the segments are written as they are unrolled
+    let file = mesh_file(&[vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]], &[vec![0, 1]], json!({
+        "primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "mode": 2}],
+        "extras": {"decimate": false},
+    }));
+    let (json, binary) = run("loop", file, &["percent", "1"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert_eq!(primitive["mode"], 1);
+    assert_eq!(read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap()).len(), 2);
+}
+
+#[test]
+fn vertices_torn_apart_by_a_morph_target_are_not_merged() {
+    // Two bumpy 5 by 5 grids side by side, their vertices along x = 0.5 at the same position or a gap under the
+    // proximity apart, and a morph target raising the left one and lowering the right one
+    for gap in [0.0, 0.001] {
+        let n = 5u16;
+        let side = |offset:f32| (0..n * n).map(move |i| {
+            let (x, y) = (offset + (i % n) as f32 / (n - 1) as f32 / 2.0, (i / n) as f32 / (n - 1) as f32);
+            [x, y, 0.3 * (x * 9.0).sin() * (y * 7.0).cos()]
+        });
+        let positions: Vec<[f32; 3]> = side(0.0).chain(side(0.5 + gap)).collect();
+        let deltas: Vec<[f32; 3]> = (0..2 * n * n).map(|i| [0.0, 0.0, if i < n * n { 0.05 } else { -0.05 }]).collect();
+        let mut indices = Vec::new();
+        for first in [0, n * n] {
+            for y in 0..n - 1 {
+                for x in 0..n - 1 {
+                    let i = first + y * n + x;
+                    indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+                }
+            }
+        }
+        let file = mesh_file(&[positions, deltas], &[indices], json!({"primitives": [
+            {"attributes": {"POSITION": 0}, "indices": 2, "targets": [{"POSITION": 1}]},
+        ]}));
+        let (json, binary) = run(&format!("morph_{}", gap), file, &["max", "16"]);
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let deltas = read_accessor(&json, &binary, primitive["targets"][0]["POSITION"].as_u64().unwrap());
+        let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+        assert!(!indices.is_empty());
+        for triangle in indices.chunks(3) {
+            let z: Vec<f64> = triangle.iter().map(|i| deltas[i[0] as usize][2]).collect();
+            assert!(z.iter().all(|d| (d.abs() - 0.05).abs() < 1e-6 && *d == z[0]), "gap {}: triangle with deltas {:?}", gap, z);
+        }
+    }
+}
+
+#[test]
+fn triangles_collapsed_to_an_edge_are_measured_along_it() {
+    // A unit square against the diagonal from (0.5, 0.5) to (0, 1), as a triangle whose first two corners meet
+    let v = |x:f64, y:f64| Vector3::new(x, y, 0.0);
+    let square = [[v(0.0, 0.0), v(1.0, 0.0), v(1.0, 1.0)], [v(0.0, 0.0), v(1.0, 1.0), v(0.0, 1.0)]];
+    let edge = [[v(0.5, 0.5), v(0.5, 0.5), v(0.0, 1.0)]];
+    let distances = compare::compare(&square, &edge, 1000).forward;
+    // The farthest point, (1, 0), is 0.5 * sqrt(2) from (0.5, 0.5)
+    assert!((distances.hausdorff - 0.5f64.sqrt()).abs() < 1e-9, "{:?}", distances);
+    assert!(distances.mean.is_finite() && distances.rms.is_finite(), "{:?}", distances);
+}
+
+#[test]
+fn symmetry_with_partition_is_an_error() {
+    // Clap keeps them apart on the command line, the library returns an error before reading the file
+    let options = Options { partition: true, symmetry: Some(Symmetry::X), ..Options::new(Method::Percent, 0.5) };
+    let progress = Progress::new(|_| {}, Cancel::default());
+    let error = decimate(Path::new("missing.glb"), &options, &progress).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn draco_primitives_decompress_and_drop_the_extension() {
+    // Compressed first, then decompressed as it is
+    let n = 5;
+    let mut indices = Vec::new();
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let file = mesh_file(&[grid(n)], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (json, binary) = run("draco_compress", file, &["percent", "1", "--draco"]);
+    assert!(json["meshes"][0]["primitives"][0]["extensions"]["KHR_draco_mesh_compression"].is_object());
+    let (json, binary) = run("draco_decompress", glb(&json, binary), &["percent", "1"]);
+    assert!(json["extensionsUsed"].as_array().into_iter().flatten().all(|e| e != "KHR_draco_mesh_compression"));
+    assert!(json["extensionsRequired"].as_array().into_iter().flatten().all(|e| e != "KHR_draco_mesh_compression"));
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert!(primitive["extensions"].is_null());
+    assert_eq!(read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap()).len(), 32 * 3);
+    // Every vertex is back on the grid, within the 14 bits of the positions
+    let original = grid(n);
+    for p in read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap()) {
+        assert!(original.iter().any(|o| (0..3).all(|c| (o[c] as f64 - p[c]).abs() < 1e-3)), "{:?} is off the grid", p);
+    }
+}
+
+#[test]
+fn a_draco_fixture_decodes_to_its_fan() {
+    // Its Draco buffer was written by hand rather than by an encoder: edgebreaker connectivity of six triangles
+    // around the second point, and 3 bit positions predicted by parallelograms
+    let file = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("draco_fan.glb")).unwrap();
+    let (json, binary) = run("draco_fixture", file, &["percent", "1"]);
+    assert!(json["extensionsUsed"].as_array().into_iter().flatten().all(|e| e != "KHR_draco_mesh_compression"));
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+    let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+    let fan = [[2.0, 0.0, 1.0], [0.0, 0.0, 3.0], [2.0, 2.0, 0.0], [0.0, 2.0, 2.0], [-2.0, 2.0, 1.0],
+               [-2.0, -2.0, 0.0], [0.0, -2.0, 2.0]];
+    // Triangles compared by their corners, starting from the smallest point of the fan
+    let canonical = |face:[usize; 3]| {
+        let k = (0..3).min_by_key(|k| face[*k]).unwrap();
+        [face[k], face[(k + 1) % 3], face[(k + 2) % 3]]
+    };
+    let mut faces: Vec<[usize; 3]> = indices.chunks(3).map(|face| canonical([0, 1, 2].map(|k| {
+        let p = &positions[face[k][0] as usize];
+        fan.iter().position(|f| (0..3).all(|c| f[c] == p[c])).unwrap_or_else(|| panic!("{:?} is not in the fan", p))
+    }))).collect();
+    let mut expected = [[0, 1, 2], [2, 1, 3], [3, 1, 4], [4, 1, 5], [5, 1, 6], [1, 0, 6]].map(canonical);
+    faces.sort();
+    expected.sort();
+    assert_eq!(faces, expected);
+}
+
+#[test]
+fn a_corrupt_draco_buffer_is_an_error() {
+    // The fixture with its Draco buffer cut short
+    let file = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("draco_fan.glb")).unwrap();
+    let (mut json, binary) = read_glb(&file);
+    json["bufferViews"][0]["byteLength"] = json!(40);
+    let error = run_error("draco_corrupt", glb(&json, binary), &["percent", "1"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+// The quadric, counting the collapses it evaluates
+#[derive(Debug, Default)]
+struct Counted(AtomicUsize);
+
+impl CostMetric for Counted {
+    fn zero(&self) -> Vec<f64> {
+        Quadric.zero()
+    }
+
+    fn face(&self, v1:&Vector3<f32>, v2:&Vector3<f32>, v3:&Vector3<f32>) -> Vec<f64> {
+        Quadric.face(v1, v2, v3)
+    }
+
+    fn plane(&self, normal:&Vector3<f64>, d:f64) -> Vec<f64> {
+        Quadric.plane(normal, d)
+    }
+
+    fn evaluate(&self, state:&[f64], position:&Vector3<f32>) -> f64 {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Quadric.evaluate(state, position)
+    }
+
+    fn quadric(&self, state:&[f64]) -> Option<(Matrix3<f64>, Vector3<f64>)> {
+        Quadric.quadric(state)
+    }
+}
+
+// Collapses onto their first vertex, which leaves every vertex where it was
+#[derive(Debug)]
+struct First;
+
+impl PlacementPolicy for First {
+    fn place(&self, u:u32, _v:u32, _index_list:&[u32], position_list:&HashMap<u32, Vector3<f32>>, _state:&[f64],
+             _metric:&dyn CostMetric) -> Vector3<f32> {
+        position_list[&u]
+    }
+}
+
+#[test]
+fn a_custom_metric_and_placement_are_used() {
+    let n = 9;
+    let mut indices = Vec::new();
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let file = mesh_file(&[grid(n)], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let metric = Counted::default();
+    let options = Options { metric: &metric, placement: &First, ..Options::new(Method::Percent, 0.25) };
+    let (json, binary) = run_options("custom", file, options);
+    assert!(metric.0.load(Ordering::Relaxed) > 0);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert!(read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap()).len() <= 32 * 3);
+    // Midpoints would leave the vertices between the ones of the grid
+    let original = grid(n);
+    for p in read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap()) {
+        assert!(original.iter().any(|o| (0..3).all(|c| (o[c] as f64 - p[c]).abs() < 1e-6)), "{:?} is off the grid", p);
+    }
+}
+
+#[test]
+fn sparse_positions_and_targets_are_read_and_written_back() {
+    // A flat 5 by 5 grid whose sparse positions raise the vertices of the middle row, and a morph target of sparse
+    // deltas only, without a bufferView, lifting the vertices of the right column
+    let n = 5u16;
+    let flat: Vec<[f32; 3]> = (0..n * n).map(|i| [(i % n) as f32, (i / n) as f32, 0.0]).collect();
+    let raised: Vec<u16> = (0..n).map(|x| 2 * n + x).collect();
+    let lifted: Vec<u16> = (0..n).map(|y| y * n + n - 1).collect();
+    let mut indices = Vec::new();
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let mut binary: Vec<u8> = flat.iter().flatten().flat_map(|c| c.to_le_bytes()).collect();
+    let view = |binary:&mut Vec<u8>, data:Vec<u8>| {
+        let offset = binary.len();
+        binary.extend(data);
+        while !binary.len().is_multiple_of(4) {
+            binary.push(0);
+        }
+        json!({"buffer": 0, "byteOffset": offset, "byteLength": binary.len() - offset})
+    };
+    let u16s = |list:&[u16]| list.iter().flat_map(|i| i.to_le_bytes()).collect::<Vec<u8>>();
+    let f32s = |list:&[f32]| list.iter().flat_map(|c| c.to_le_bytes()).collect::<Vec<u8>>();
+    let views = vec![
+        json!({"buffer": 0, "byteOffset": 0, "byteLength": binary.len()}),
+        view(&mut binary, u16s(&raised)),
+        view(&mut binary, f32s(&raised.iter().flat_map(|i| [(i % n) as f32, 2.0, 1.0]).collect::<Vec<_>>())),
+        view(&mut binary, u16s(&lifted)),
+        view(&mut binary, f32s(&lifted.iter().flat_map(|_| [0.0, 0.0, 0.5]).collect::<Vec<_>>())),
+        view(&mut binary, u16s(&indices)),
+    ];
+    let json = json!({
+        "asset": {"version": "2.0"},
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{"mesh": 0}],
+        "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 2, "targets": [{"POSITION": 1}]}]}],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": n * n, "type": "VEC3", "min": [0.0, 0.0, 0.0],
+             "max": [4.0, 4.0, 1.0], "sparse": {"count": n, "indices": {"bufferView": 1, "componentType": 5123},
+                                                "values": {"bufferView": 2}}},
+            {"componentType": 5126, "count": n * n, "type": "VEC3", "min": [0.0, 0.0, 0.0], "max": [0.0, 0.0, 0.5],
+             "sparse": {"count": n, "indices": {"bufferView": 3, "componentType": 5123}, "values": {"bufferView": 4}}},
+            {"bufferView": 5, "componentType": 5123, "count": indices.len(), "type": "SCALAR"},
+        ],
+        "bufferViews": views,
+        "buffers": [{"byteLength": binary.len()}],
+    });
+    let file = glb(&json, binary);
+    // The ridge of the middle row and the lifted column, in the morphed positions of any vertex written back
+    let expected = |p:&[f64]| (if p[1] == 2.0 { 1.0 } else { 0.0 }, if p[0] == 4.0 { 0.5 } else { 0.0 });
+    for (name, options) in [("sparse_kept", &["percent", "1"][..]), ("sparse_written", &["percent", "1", "--sparse"]),
+                            ("sparse_decimated", &["max", "16", "--sparse"])] {
+        let (json, binary) = run(name, file.clone(), options);
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+        let deltas = read_accessor(&json, &binary, primitive["targets"][0]["POSITION"].as_u64().unwrap());
+        let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+        assert!(!indices.is_empty() && indices.len() <= 32 * 3, "{}: {} indices", name, indices.len());
+        if name == "sparse_kept" {
+            assert_eq!(indices.len(), 32 * 3);
+        }
+        if options.contains(&"--sparse") {
+            assert!(json["accessors"][primitive["targets"][0]["POSITION"].as_u64().unwrap() as usize]["sparse"].is_object());
+        }
+        for i in indices {
+            let (p, d) = (&positions[i[0] as usize], &deltas[i[0] as usize]);
+            // Vertices of the grid keep their values, the ones collapsed along an edge stay between them
+            if p[0].fract() == 0.0 && p[1].fract() == 0.0 {
+                assert_eq!((p[2], d[2]), expected(p), "{}: vertex at {:?} with delta {:?}", name, p, d);
+            } else {
+                assert!((0.0..=1.0).contains(&p[2]) && (0.0..=0.5).contains(&d[2]), "{}: {:?} {:?}", name, p, d);
+            }
+        }
+    }
+}
+
+#[test]
+fn an_unknown_accessor_type_is_an_error() {
+    let file = mesh_file(&[grid(3)], &[vec![0, 1, 4]], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (mut json, binary) = read_glb(&file);
+    json["accessors"][0]["type"] = json!("VEC5");
+    let error = run_error("unknown_type", glb(&json, binary), &["percent", "0.5"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn an_unknown_primitive_mode_is_an_error() {
+    let file = mesh_file(&[grid(3)], &[vec![0, 1, 4]], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1,
+                                                                              "mode": 7}]}));
+    let error = run_error("unknown_mode", file, &["percent", "0.5"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+// World positions of the vertices of the first primitive of mesh 0 under every node using it, through the
+// translations and uniform scales of the nodes above it
+fn world_positions(json:&Value, binary:&[u8]) -> Vec<[f64; 3]> {
+    let nodes = json["nodes"].as_array().unwrap();
+    let parent = |n:usize| nodes.iter().position(|node| node["children"].as_array().into_iter().flatten().any(|c| c == n));
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let local = read_accessor(json, binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+    let mut world = Vec::new();
+    for (n, _) in nodes.iter().enumerate().filter(|(_, node)| node["mesh"] == 0) {
+        let mut positions: Vec<[f64; 3]> = local.iter().map(|p| [p[0], p[1], p[2]]).collect();
+        let mut at = Some(n);
+        while let Some(n) = at {
+            assert!(nodes[n]["rotation"].is_null() && nodes[n]["matrix"].is_null());
+            let scale = nodes[n]["scale"][0].as_f64().unwrap_or(1.0);
+            for p in &mut positions {
+                for c in 0..3 {
+                    p[c] = p[c] * scale + nodes[n]["translation"][c].as_f64().unwrap_or(0.0);
+                }
+            }
+            at = parent(n);
+        }
+        world.extend(positions);
+    }
+    world
+}
+
+#[test]
+fn quantized_positions_are_read_and_written_in_world_space() {
+    // A grid moved to x = 10, then quantized into 16 bits under a dequantizing node
+    let n = 5;
+    let mut indices = Vec::new();
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let file = mesh_file(&[grid(n)], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (mut json, binary) = read_glb(&file);
+    json["nodes"][0]["translation"] = json!([10.0, 0.0, 0.0]);
+    let file = glb(&json, binary);
+    let original: Vec<[f64; 3]> = grid(n).iter().map(|p| [p[0] as f64 + 10.0, p[1] as f64, p[2] as f64]).collect();
+    let near = |p:&[f64; 3], tolerance:f64| original.iter().any(|o| (0..3).all(|c| (o[c] - p[c]).abs() < tolerance));
+    let (quantized, quantized_binary) = run("quantize_write", file, &["percent", "1", "--quantize"]);
+    let primitive = &quantized["meshes"][0]["primitives"][0];
+    assert_eq!(quantized["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize]["componentType"], 5123);
+    assert!(quantized["extensionsRequired"].as_array().unwrap().iter().any(|e| e == "KHR_mesh_quantization"));
+    // The grid of 16 bits over the unit square is 1 / 65535 wide
+    let world = world_positions(&quantized, &quantized_binary);
+    assert_eq!(world.len(), original.len());
+    assert!(world.iter().all(|p| near(p, 1e-4)), "{:?}", world);
+    // Read back as it is written, the quantized file decimates in world units like the original
+    let (json, binary) = run("quantize_read", glb(&quantized, quantized_binary), &["percent", "1"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert_eq!(json["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize]["componentType"], 5126);
+    let world = world_positions(&json, &binary);
+    assert_eq!(world.len(), original.len());
+    assert!(world.iter().all(|p| near(p, 1e-4)), "{:?}", world);
+}
+
+#[test]
+fn a_mesh_without_a_node_keeps_float_positions() {
+    let file = mesh_file(&[grid(3)], &[vec![0, 1, 4, 0, 4, 3]], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (mut json, binary) = read_glb(&file);
+    json["nodes"] = json!([]);
+    json["scenes"] = json!([{"nodes": []}]);
+    let (json, binary) = run("quantize_without_node", glb(&json, binary), &["percent", "1", "--quantize"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    assert_eq!(json["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize]["componentType"], 5126);
+    let original = grid(3);
+    for p in read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap()) {
+        assert!(original.iter().any(|o| (0..3).all(|c| (o[c] as f64 - p[c]).abs() < 1e-6)), "{:?} is off the grid", p);
+    }
+}
+
+// The grid with a normal per vertex, leaning with the bumps
+fn grid_with_normals(n:u16) -> Vec<u8> {
+    let mut indices = Vec::new();
+    for y in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = y * n + x;
+            indices.extend([i, i + 1, i + n + 1, i, i + n + 1, i + n]);
+        }
+    }
+    let normals = grid(n).iter().map(|p| {
+        let normal = Vector3::new(-0.7 * (p[0] * 7.0).cos(), 0.5 * (p[1] * 5.0).sin(), 1.0).normalize();
+        [normal.x, normal.y, normal.z]
+    }).collect();
+    mesh_file(&[grid(n), normals], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1}, "indices": 2}]}))
+}
+
+// Normals of the first primitive, against the ones of the grid at the same position
+fn assert_normals(json:&Value, binary:&[u8], tolerance:f64) {
+    let (original, original_binary) = read_glb(&grid_with_normals(5));
+    let original_positions = read_accessor(&original, &original_binary, 0);
+    let original_normals = read_accessor(&original, &original_binary, 1);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let normal = primitive["attributes"]["NORMAL"].as_u64().unwrap();
+    let scale = if json["accessors"][normal as usize]["componentType"] == 5120 { 1.0 / 127.0 } else { 1.0 };
+    let positions = world_positions(json, binary);
+    for (p, n) in positions.iter().zip(read_accessor(json, binary, normal)) {
+        let k = original_positions.iter().position(|o| (0..3).all(|c| (o[c] - p[c]).abs() < 1e-3)).unwrap();
+        for c in 0..3 {
+            assert!((n[c] * scale - original_normals[k][c]).abs() < tolerance, "{:?} for {:?}", n, original_normals[k]);
+        }
+    }
+}
+
+#[test]
+fn meshopt_normals_are_read_back_whether_compressed_or_not() {
+    // Compressed with the octahedral filter, then read back as floats
+    let (json, binary) = run("meshopt_normals", grid_with_normals(5), &["percent", "1", "--quantize", "--meshopt"]);
+    let view = json["accessors"][json["meshes"][0]["primitives"][0]["attributes"]["NORMAL"].as_u64().unwrap() as usize]["bufferView"]
+        .as_u64().unwrap() as usize;
+    assert_eq!(json["bufferViews"][view]["extensions"]["EXT_meshopt_compression"]["filter"], "OCTAHEDRAL");
+    let (json, binary) = run("meshopt_normals_read", glb(&json, binary), &["percent", "1"]);
+    assert_normals(&json, &binary, 0.02);
+
+    // Vertex views marked as holding indices are not compressed, the normals stay plain snorm values
+    let (mut json, binary) = read_glb(&grid_with_normals(5));
+    json["bufferViews"][0]["target"] = json!(34963);
+    json["bufferViews"][1]["target"] = json!(34963);
+    let (json, binary) = run("meshopt_normals_fallback", glb(&json, binary), &["percent", "1", "--quantize", "--meshopt"]);
+    let normal = json["meshes"][0]["primitives"][0]["attributes"]["NORMAL"].as_u64().unwrap() as usize;
+    let view = json["accessors"][normal]["bufferView"].as_u64().unwrap() as usize;
+    assert!(json["bufferViews"][view]["extensions"].is_null());
+    assert_eq!(json["accessors"][normal]["componentType"], 5120);
+    assert_normals(&json, &binary, 0.01);
+}
+
+#[test]
+fn broken_meshopt_views_are_errors() {
+    let (json, binary) = run("meshopt_compress", grid_with_normals(5), &["percent", "1", "--meshopt"]);
+    let view = (0..json["bufferViews"].as_array().unwrap().len())
+        .find(|v| json["bufferViews"][*v]["extensions"]["EXT_meshopt_compression"].is_object()).unwrap();
+    let mut external = json.clone();
+    external["bufferViews"][view]["extensions"]["EXT_meshopt_compression"]["buffer"] = json!(1);
+    let error = run_error("meshopt_external", glb(&external, binary.clone()), &["percent", "1"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    let mut truncated = json.clone();
+    truncated["bufferViews"][view]["extensions"]["EXT_meshopt_compression"]["byteLength"] = json!(8);
+    let error = run_error("meshopt_truncated", glb(&truncated, binary), &["percent", "1"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+// The triangles of a bumpy n by n grid
+fn grid_indices(n:u16) -> Vec<u16> {
+    (0..n - 1).flat_map(|y| (0..n - 1).flat_map(move |x| {
+        let i = y * n + x;
+        [i, i + 1, i + n + 1, i, i + n + 1, i + n]
+    })).collect()
+}
+
+#[test]
+fn extras_override_the_command_line() {
+    let n = 11;
+    let mesh = json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}],
+                      "extras": {"decimate": {"ratio": 0.3, "lockBoundary": true}}});
+    let (json, binary) = run("extras", mesh_file(&[grid(n)], &[grid_indices(n)], mesh), &["percent", "0.9"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let triangles = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap()).len() / 3;
+    let before = 2 * (n as usize - 1) * (n as usize - 1);
+    assert!(triangles <= before * 3 / 10 + 2, "{} of {} triangles are left", triangles, before);
+    // Every vertex of the border is still there
+    let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+    for (i, p) in grid(n).iter().enumerate() {
+        let (x, y) = (i as u16 % n, i as u16 / n);
+        if x == 0 || y == 0 || x == n - 1 || y == n - 1 {
+            assert!(positions.iter().any(|q| (0..3).all(|c| (q[c] - p[c] as f64).abs() < 1e-6)),
+                    "border vertex {:?} moved", p);
+        }
+    }
+}
+
+#[test]
+fn the_placement_of_the_extras_is_used() {
+    let n = 11;
+    let file = |extras:Value| mesh_file(&[grid(n)], &[grid_indices(n)],
+        json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}], "extras": extras}));
+    let from_extras = run("placement_extras", file(json!({"decimate": {"placement": "volume"}})), &["percent", "0.3"]);
+    let from_command_line = run("placement_command_line", file(json!({})), &["--placement", "volume", "percent", "0.3"]);
+    let midpoint = run("placement_midpoint", file(json!({})), &["percent", "0.3"]);
+    assert_eq!(from_extras.1, from_command_line.1);
+    assert_ne!(from_extras.1, midpoint.1);
+}
+
+#[test]
+fn a_streamed_file_parses_and_keeps_to_the_target() {
+    // Two primitives over their own grids, the second one left alone as points
+    let n = 41;
+    let file = mesh_file(&[grid(n), grid(3)], &[grid_indices(n)], json!({"primitives": [
+        {"attributes": {"POSITION": 0}, "indices": 2},
+        {"attributes": {"POSITION": 1}, "mode": 0},
+    ]}));
+    let (json, binary) = run("stream", file, &["--stream", "--chunk-triangles", "1600", "max", "1000"]);
+    let primitives = json["meshes"][0]["primitives"].as_array().unwrap();
+    let indices = read_accessor(&json, &binary, primitives[0]["indices"].as_u64().unwrap());
+    assert!(!indices.is_empty() && indices.len() / 3 <= 1000, "{} triangles are left", indices.len() / 3);
+    assert_eq!(read_accessor(&json, &binary, primitives[1]["attributes"]["POSITION"].as_u64().unwrap()).len(), 9);
+    // The accessors of the original triangles are gone rather than left without data
+    let accessors = json["accessors"].as_array().unwrap();
+    assert_eq!(accessors.len(), 3);
+    assert!(accessors.iter().all(|accessor| accessor["bufferView"].is_u64()));
+    let positions = read_accessor(&json, &binary, primitives[0]["attributes"]["POSITION"].as_u64().unwrap());
+    assert!(indices.iter().all(|i| (i[0] as usize) < positions.len()));
+}
+
+#[test]
+fn what_streaming_cannot_do_is_an_unsupported_error() {
+    let n = 5;
+    let file = || mesh_file(&[grid(n)], &[grid_indices(n)], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let error = run_error("stream_budget", file(), &["--stream", "budget", "10"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+    let (json, binary) = read_glb(&file());
+    let mut json = json;
+    json["accessors"][0]["sparse"] = json!({"count": 1, "indices": {"bufferView": 1, "componentType": 5123},
+                                            "values": {"bufferView": 0}});
+    let error = run_error("stream_sparse", glb(&json, binary), &["--stream", "percent", "0.5"]);
+    assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn the_report_measures_the_error_of_every_collapse() {
+    let report = |name:&str, file:Vec<u8>, options:&[&str]| {
+        let path = std::env::temp_dir().join(format!("decimation_gltf_primitives_{}_{}.json", std::process::id(), name));
+        let mut command = vec!["decimation_gltf", "input.glb", "--report", path.to_str().unwrap()];
+        command.extend_from_slice(options);
+        run_options(name, file, Args::parse_from(command).options());
+        let report: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        report["primitives"][0].clone()
+    };
+    // The costs of the seams of test1 were measured before their duplicate vertices were merged
+    let n = 11;
+    let runs = [
+        report("report_budget", fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("test1.glb")).unwrap(),
+               &["budget", "500"]),
+        report("report_cluster", mesh_file(&[grid(n)], &[grid_indices(n)],
+            json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]})), &["cluster", "0.25"]),
+    ];
+    for entry in runs {
+        let (max, mean) = (entry["maxError"].as_f64().unwrap(), entry["meanError"].as_f64().unwrap());
+        assert!(entry["collapses"].as_u64().unwrap() > 0, "{}", entry);
+        assert!(max > 0.0 && mean > 0.0 && mean <= max, "{}", entry);
+    }
+}
+
+#[test]
+fn polyline_endpoints_and_junctions_survive() {
+    // Three wavy arms of 20 segments from a junction at the origin, each arm with a vertex of its own there
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for arm in 0..3 {
+        let angle = arm as f32 * 2.0 * std::f32::consts::PI / 3.0;
+        let (direction, side) = ([angle.cos(), angle.sin()], [-angle.sin(), angle.cos()]);
+        let first = positions.len() as u16;
+        for k in 0..=20 {
+            let (along, across) = (k as f32 / 20.0, 0.05 * (k as f32 * 1.3).sin() * (k as f32 / 20.0));
+            positions.push([direction[0] * along + side[0] * across, direction[1] * along + side[1] * across, 0.0]);
+        }
+        indices.extend((first..first + 20).flat_map(|i| [i, i + 1]));
+    }
+    let fixed: Vec<[f32; 3]> = (0..3).map(|arm| positions[arm * 21 + 20]).chain([[0.0; 3]]).collect();
+    let file = mesh_file(&[positions], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "mode": 1}]}));
+    let (json, binary) = run("polyline", file, &["percent", "0.2"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+    let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+    assert!(indices.len() / 2 <= 12, "{} segments are left", indices.len() / 2);
+    // The junction still joins three segments and every arm still reaches its end
+    let at = |p:&[f32; 3]| indices.iter().filter(|i| (0..3).all(|c| (positions[i[0] as usize][c] - p[c] as f64).abs() < 1e-6)).count();
+    for (k, p) in fixed.iter().enumerate() {
+        assert_eq!(at(p), if k == 3 { 3 } else { 1 }, "{:?}", p);
+    }
+}
+
+#[test]
+fn point_clouds_keep_the_ratio_and_their_attributes() {
+    // A bumpy 30 by 30 grid of points colored by their position, without indices
+    let points = grid(30);
+    let colors: Vec<[f32; 3]> = points.iter().map(|p| [p[0], p[1], 0.5]).collect();
+    let mesh = json!({"primitives": [{"attributes": {"POSITION": 0, "COLOR_0": 1}, "mode": 0}]});
+    for sampling in ["voxel", "poisson", "random"] {
+        let file = mesh_file(&[points.clone(), colors.clone()], &[], mesh.clone());
+        let (json, binary) = run(&format!("points_{}", sampling), file, &["--points", sampling, "percent", "0.25"]);
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+        let colors = read_accessor(&json, &binary, primitive["attributes"]["COLOR_0"].as_u64().unwrap());
+        let kept: Vec<usize> = match primitive["indices"].as_u64() {
+            Some(indices) => read_accessor(&json, &binary, indices).iter().map(|i| i[0] as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        // 225 points are asked for
+        assert!((180..=225).contains(&kept.len()), "{} keeps {} points", sampling, kept.len());
+        for i in kept {
+            let (p, color) = (&positions[i], &colors[i]);
+            assert!((color[0] - p[0]).abs() < 1e-6 && (color[1] - p[1]).abs() < 1e-6 && color[2] == 0.5,
+                    "{}: point {:?} has the color {:?}", sampling, p, color);
+        }
+    }
+}
+
+#[test]
+fn instances_decimate_against_the_largest() {
+    let n = 21;
+    let file = |scales:&[f64]| {
+        let (mut json, binary) = read_glb(&mesh_file(&[grid(n)], &[grid_indices(n)],
+            json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]})));
+        json["nodes"] = scales.iter().map(|s| json!({"mesh": 0, "scale": [s, s, s]})).collect();
+        json["scenes"][0]["nodes"] = (0..scales.len()).collect();
+        glb(&json, binary)
+    };
+    let triangles = |name:&str, scales:&[f64]| {
+        let (json, binary) = run(name, file(scales), &["--max-error", "0.05", "percent", "0"]);
+        read_accessor(&json, &binary, json["meshes"][0]["primitives"][0]["indices"].as_u64().unwrap()).len() / 3
+    };
+    // The bumps of the grid are 0.1 high at scale 1, about the error, and 10 at scale 100
+    let (both, large, small) = (triangles("scale_both", &[1.0, 100.0]), triangles("scale_large", &[100.0]),
+                                triangles("scale_small", &[1.0]));
+    assert_eq!(both, large);
+    assert!(large > 4 * small, "{} triangles at scale 100, {} at scale 1", large, small);
+}
+
+#[test]
+fn a_budget_is_shared_out_by_area() {
+    let n = 21;
+    let large: Vec<[f32; 3]> = grid(n).iter().map(|p| p.map(|c| c * 3.0)).collect();
+    let (mut json, binary) = read_glb(&mesh_file(&[grid(n), large], &[grid_indices(n), grid_indices(n)],
+        json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 2}]})));
+    json["meshes"] = json!([json["meshes"][0], {"primitives": [{"attributes": {"POSITION": 1}, "indices": 3}]}]);
+    json["nodes"] = json!([{"mesh": 0}, {"mesh": 1}]);
+    json["scenes"][0]["nodes"] = json!([0, 1]);
+    let (json, binary) = run("budget", glb(&json, binary), &["budget", "300"]);
+    let triangles: Vec<usize> = (0..2).map(|mesh| {
+        read_accessor(&json, &binary, json["meshes"][mesh]["primitives"][0]["indices"].as_u64().unwrap()).len() / 3
+    }).collect();
+    assert!(triangles[0] + triangles[1] <= 300, "{:?}", triangles);
+    assert!(triangles[1] > 4 * triangles[0] && triangles[0] > 0, "{:?}", triangles);
+}
+
+#[test]
+fn a_vertex_of_weight_one_never_moves() {
+    let n = 21;
+    let points = grid(n);
+    let locked = [n as usize * 5 + 7, n as usize * 10 + 10, n as usize * 16 + 3];
+    let weights: Vec<[f32; 3]> = (0..points.len()).map(|i| [if locked.contains(&i) { 1.0 } else { 0.0 }, 0.0, 0.0]).collect();
+    let mesh = json!({"primitives": [{"attributes": {"POSITION": 0, "_DECIMATE_WEIGHT": 1}, "indices": 2}]});
+    for (name, options) in [("weight", &["percent", "0.05"][..]),
+                            ("weight_partition", &["--partition", "--chunk-triangles", "100", "percent", "0.05"][..])] {
+        let file = mesh_file(&[points.clone(), weights.clone()], &[grid_indices(n)], mesh.clone());
+        let (json, binary) = run(name, file, options);
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+        let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+        assert!(indices.len() / 3 < 200, "{}: {} triangles are left", name, indices.len() / 3);
+        for i in locked {
+            assert!(indices.iter().any(|k| (0..3).all(|c| (positions[k[0] as usize][c] - points[i][c] as f64).abs() < 1e-6)),
+                    "{}: vertex {} at {:?} moved", name, i, points[i]);
+        }
+    }
+}
+
+#[test]
+fn a_mirrored_input_stays_mirrored() {
+    // A bumpy grid over [-1, 1] by [0, 1], mirrored across x = 0 down to the diagonals of its quads
+    let n: u16 = 21;
+    let points: Vec<[f32; 3]> = grid(n).iter().map(|p| {
+        let x = 2.0 * p[0] - 1.0;
+        [x, p[1], 0.1 * (x.abs() * 7.0).sin() * (p[1] * 5.0).cos()]
+    }).collect();
+    let indices: Vec<u16> = (0..n - 1).flat_map(|y| (0..n - 1).flat_map(move |x| {
+        let i = y * n + x;
+        match 2 * x < n - 1 {
+            true => [i, i + 1, i + n, i + 1, i + n + 1, i + n],
+            false => [i, i + 1, i + n + 1, i, i + n + 1, i + n],
+        }
+    })).collect();
+    let file = mesh_file(&[points], &[indices], json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (json, binary) = run("mirror", file, &["--symmetry", "x", "percent", "0.2"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+    let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+    assert!(indices.len() / 3 < 200, "{} triangles are left", indices.len() / 3);
+    // Every triangle has its mirror, corners rounded to a grid finer than the bumps
+    let key = |corners:[[f64; 3]; 3]| {
+        let mut corners = corners.map(|p| p.map(|c| (c * 1e4).round() as i64));
+        corners.sort();
+        corners
+    };
+    let corner = |i:&Vec<f64>| {
+        let p = &positions[i[0] as usize];
+        [p[0], p[1], p[2]]
+    };
+    let triangles: Vec<[[f64; 3]; 3]> = indices.chunks_exact(3).map(|t| [corner(&t[0]), corner(&t[1]), corner(&t[2])]).collect();
+    let keys: std::collections::HashSet<_> = triangles.iter().map(|t| key(*t)).collect();
+    for t in &triangles {
+        assert!(keys.contains(&key(t.map(|p| [-p[0], p[1], p[2]]))), "{:?} has no mirror", t);
+    }
+}
+
+#[test]
+fn clustering_a_dense_grid_keeps_about_a_vertex_per_cell() {
+    // A flat 61 by 61 grid over the unit square, on cells of 0.1 from its corner: 11 by 11 cells
+    let n = 61;
+    let file = || mesh_file(&[grid(n).iter().map(|p| [p[0], p[1], 0.0]).collect()], &[grid_indices(n)],
+                            json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}));
+    let (json, binary) = run("cluster_grid", file(), &["cluster", "0.1"]);
+    let primitive = &json["meshes"][0]["primitives"][0];
+    let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+    let vertices: std::collections::HashSet<u64> = indices.iter().map(|i| i[0] as u64).collect();
+    assert!((100..=121).contains(&vertices.len()), "{} vertices are left", vertices.len());
+    assert!((150..=250).contains(&(indices.len() / 3)), "{} triangles are left", indices.len() / 3);
+    // The cell is searched for a number of triangles
+    let (json, binary) = run("cluster_count", file(), &["cluster-count", "500"]);
+    let triangles = read_accessor(&json, &binary, json["meshes"][0]["primitives"][0]["indices"].as_u64().unwrap()).len() / 3;
+    assert!((350..=650).contains(&triangles), "{} triangles are left", triangles);
+}
+
+#[test]
+fn a_cancelled_decimation_still_writes_a_valid_file() {
+    let n = 21;
+    let dir = std::env::temp_dir().join(format!("decimation_gltf_primitives_{}_cancel", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("input.glb"), mesh_file(&[grid(n)], &[grid_indices(n)],
+        json!({"primitives": [{"attributes": {"POSITION": 0}, "indices": 1}]}))).unwrap();
+    for partition in [false, true] {
+        let cancel = Cancel::default();
+        cancel.cancel();
+        let updates = Arc::new(AtomicUsize::new(0));
+        let counted = updates.clone();
+        let progress = Progress::new(move |update| { counted.fetch_add(update.done, Ordering::Relaxed); }, cancel);
+        let options = Options { output: dir.join("output.glb").to_str().unwrap().to_string(), partition,
+                                chunk_triangles: 100, ..Options::new(Method::Percent, 0.1) };
+        decimate(&dir.join("input.glb"), &options, &progress).unwrap();
+        assert_eq!(updates.load(Ordering::Relaxed), 0, "partition {}", partition);
+        // Every triangle is still there, pointing at vertices that are
+        let (json, binary) = read_glb(&fs::read(dir.join("output.glb")).unwrap());
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let positions = read_accessor(&json, &binary, primitive["attributes"]["POSITION"].as_u64().unwrap());
+        let indices = read_accessor(&json, &binary, primitive["indices"].as_u64().unwrap());
+        assert_eq!(indices.len() / 3, 2 * (n as usize - 1) * (n as usize - 1), "partition {}", partition);
+        assert!(indices.iter().all(|i| (i[0] as usize) < positions.len()), "partition {}", partition);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,55 @@
+// The sample assets must decimate into the same bytes on every run, whatever the number of threads.
+// When a change to the decimation is meant to change the output, the reference hashes are updated with it.
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Asset, command line options, FNV-1a hash of output.glb
+const CASES: [(&str, &[&str], u64); 6] = [
+    ("cube.glb", &["percent", "0.5"], 0x12b6344ed290a1ad),
+    ("test.glb", &["percent", "0.5"], 0x1bb72c3dfc0fa19f),
+    ("test1.glb", &["percent", "0.5"], 0x19dbef7dbf014f56),
+    ("test1.glb", &["max", "200", "--placement", "volume", "--crease-angle", "30"], 0x008a268b2cb0b108),
+    ("test1.glb", &["cluster-count", "200"], 0x01b64db6d631ffb3),
+    ("test1.glb", &["percent", "0.3", "--partition", "--chunk-triangles", "100"], 0xda0014f80d413a87),
+];
+
+// Stable across Rust releases, unlike the hasher of the standard library
+fn fnv1a(bytes:&[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+// Decimate an asset in a directory of its own, output.glb being written to the working directory
+fn decimate(asset:&str, options:&[&str], threads:usize, run:&str) -> Vec<u8> {
+    let dir = std::env::temp_dir().join(format!("decimation_gltf_test_{}_{}", std::process::id(), run));
+    fs::create_dir_all(&dir).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_decimation_gltf"))
+        .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(asset))
+        .args(options)
+        .args(["--threads", &threads.to_string()])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} {:?} failed", asset, options);
+    let output = fs::read(dir.join("output.glb")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    output
+}
+
+#[test]
+fn output_matches_reference_hashes() {
+    for (k, (asset, options, hash)) in CASES.iter().enumerate() {
+        let output = decimate(asset, options, 1, &format!("reference_{}", k));
+        assert_eq!(fnv1a(&output), *hash, "{} {:?} changed its output", asset, options);
+    }
+}
+
+#[test]
+fn output_does_not_depend_on_threads() {
+    for (k, (asset, options, _)) in CASES.iter().enumerate() {
+        let one = decimate(asset, options, 1, &format!("one_{}", k));
+        let four = decimate(asset, options, 4, &format!("four_{}", k));
+        assert!(one == four, "{} {:?} differs between 1 and 4 threads", asset, options);
+    }
+}